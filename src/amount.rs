@@ -0,0 +1,110 @@
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+const SATS_PER_BTC: u64 = 100_000_000;
+const MAX_SATS: u64 = 21_000_000 * SATS_PER_BTC;
+
+/// A BTC amount held in whole sats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(transparent)]
+pub struct Amount(u64);
+
+impl Amount {
+    pub fn from_sats(sats: u64) -> Self {
+        Amount(sats)
+    }
+    
+    pub fn to_sats(self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} sats", self.0)
+    }
+}
+
+/// Parse `50000`, `50000sat(s)` or `0.0005btc`. Decimals need the `btc` suffix, and
+/// amounts finer than one sat are rejected rather than rounded.
+impl FromStr for Amount {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let input = s.trim().to_ascii_lowercase();
+        
+        let sats = if let Some(btc) = input.strip_suffix("btc") {
+            parse_btc(btc.trim())?
+        } else {
+            let sats = input
+                .strip_suffix("sats")
+                .or_else(|| input.strip_suffix("sat"))
+                .unwrap_or(&input)
+                .trim();
+            if sats.contains('.') {
+                return Err(format!(
+                    "ambiguous amount '{}': sats must be whole, use a 'btc' suffix for BTC amounts",
+                    s.trim()
+                ));
+            }
+            sats.parse::<u64>().map_err(|_| format!("invalid sats amount '{}'", s.trim()))?
+        };
+        
+        if sats > MAX_SATS {
+            return Err(format!("amount '{}' exceeds the 21M BTC supply", s.trim()));
+        }
+        Ok(Amount(sats))
+    }
+}
+
+/// Convert a decimal BTC string to sats without going through floats
+fn parse_btc(btc: &str) -> Result<u64, String> {
+    let (whole, fraction) = btc.split_once('.').unwrap_or((btc, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return Err("empty BTC amount".to_string());
+    }
+    if !whole.chars().all(|c| c.is_ascii_digit()) || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("invalid BTC amount '{}'", btc));
+    }
+    
+    let significant = fraction.trim_end_matches('0');
+    if significant.len() > 8 {
+        return Err(format!("BTC amount '{}' has fractional sats", btc));
+    }
+    
+    let whole_sats = if whole.is_empty() { 0 } else { whole.parse::<u64>().map_err(|_| format!("invalid BTC amount '{}'", btc))? };
+    let fraction_sats = if significant.is_empty() {
+        0
+    } else {
+        format!("{:0<8}", significant).parse::<u64>().map_err(|_| format!("invalid BTC amount '{}'", btc))?
+    };
+    
+    whole_sats
+        .checked_mul(SATS_PER_BTC)
+        .and_then(|sats| sats.checked_add(fraction_sats))
+        .ok_or_else(|| format!("BTC amount '{}' is too large", btc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn parses_sats_and_btc() {
+        assert_eq!("50000".parse(), Ok(Amount::from_sats(50_000)));
+        assert_eq!("50000sats".parse(), Ok(Amount::from_sats(50_000)));
+        assert_eq!(" 1 SAT ".parse(), Ok(Amount::from_sats(1)));
+        assert_eq!("0.0005btc".parse(), Ok(Amount::from_sats(50_000)));
+        assert_eq!("1.5BTC".parse(), Ok(Amount::from_sats(150_000_000)));
+        assert_eq!(".00000001btc".parse(), Ok(Amount::from_sats(1)));
+        assert_eq!("21000000btc".parse(), Ok(Amount::from_sats(MAX_SATS)));
+    }
+    
+    #[test]
+    fn rejects_ambiguous_and_invalid_amounts() {
+        for input in ["0.5", "12.3sats", "0.000000001btc", "btc", "1e5", "-1", "", "21000000.00000001btc"] {
+            assert!(input.parse::<Amount>().is_err(), "{} should be rejected", input);
+        }
+    }
+}
@@ -0,0 +1,822 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::env;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use crate::amount::Amount;
+
+/// Config fields holding secrets, never returned as-is
+const SECRET_FIELDS: &[&str] = &[
+    "api_key",
+    "s3_secret_access_key",
+    "s3_session_token",
+    "faucet_headers",
+    "btc_faucet_headers",
+    "lava_usd_faucet_headers",
+    "alert_webhook_url",
+];
+
+/// Runtime configuration loaded from environment variables
+#[derive(Debug, Clone, Serialize)]
+pub struct Config {
+    /// Max characters of a response body written to the log
+    pub max_log_body_len: usize,
+    /// Key required in the X-API-Key header for guarded endpoints
+    pub api_key: Option<String>,
+    /// Leave guarded endpoints open when no API_KEY is set, instead of refusing them
+    pub allow_unguarded_endpoints: bool,
+    /// Proxies whose forwarding headers are trusted for the audit log's forwarded client IP
+    pub trusted_proxies: Vec<IpAddr>,
+    /// BTC faucet body field carrying the address
+    pub btc_faucet_address_field: String,
+    /// BTC faucet body field carrying the amount in sats
+    pub btc_faucet_amount_field: String,
+    /// Amount requested from the BTC faucet per run
+    pub btc_faucet_amount: Amount,
+    /// LavaUSD faucet body field carrying the pubkey
+    pub lava_usd_faucet_pubkey_field: String,
+    /// Allow running the suite with the hardcoded demo wallet
+    pub allow_placeholder_wallet: bool,
+    /// Retry behavior per suite step
+    pub retries: StepRetries,
+    /// Directory database backups are written to
+    pub backup_dir: String,
+    /// Dotted JSON paths searched for the txid in BTC faucet responses
+    pub btc_faucet_txid_paths: Vec<String>,
+    /// Dotted JSON paths searched for the txid/signature in LavaUSD faucet responses
+    pub lava_usd_faucet_txid_paths: Vec<String>,
+    /// Allow /run-test to force a step to fail via fail_at_step
+    pub enable_failure_injection: bool,
+    /// Max wallet flows running at once across all requests
+    pub max_concurrent_runs: usize,
+    /// Single-wallet runs that may wait for a slot before /run-test returns 429
+    pub max_queued_runs: usize,
+    /// How long /run-test-sync waits for its run before answering 504
+    pub sync_run_timeout_secs: u64,
+    /// Max faucet requests in flight at once across all runs
+    pub max_concurrent_faucet_calls: usize,
+    /// Request BTC and LavaUSD at the same time when they come from different faucets
+    pub parallel_faucet_requests: bool,
+    /// Max wallets a single /run-test may request
+    pub max_wallets_per_run: u32,
+    /// What to do when a run's BTC address appears in recent results
+    pub address_reuse_policy: AddressReusePolicy,
+    /// How many recent results to check for address reuse
+    pub address_reuse_lookback: u32,
+    /// Minimum time between runs against the same BTC address; 0 disables the check
+    pub wallet_cooldown_secs: u64,
+    /// URL probed before each run to detect missing connectivity
+    pub connectivity_probe_url: String,
+    /// Timeout for the connectivity probe
+    pub connectivity_probe_timeout_ms: u64,
+    /// Directory GET /cli/logs archives CLI log files from
+    pub cli_work_dir: String,
+    /// Seed for the simulated txids and contract IDs; random when unset
+    pub rng_seed: Option<u64>,
+    /// Case-insensitive faucet response substrings that mean the faucet ran dry
+    pub faucet_exhaustion_patterns: Vec<String>,
+    /// Number of recent calls per faucet considered for its health
+    pub faucet_health_window: usize,
+    /// Bucket results are archived to after each run; archiving is off when unset
+    pub s3_bucket: Option<String>,
+    /// Key prefix for archived results
+    pub s3_prefix: String,
+    /// Region used to sign S3 requests
+    pub s3_region: String,
+    /// Custom S3-compatible endpoint, e.g. MinIO
+    pub s3_endpoint: Option<String>,
+    /// Longest an S3 upload may take, so a hung endpoint can't hold up saving the result
+    pub s3_request_timeout_ms: u64,
+    pub s3_access_key_id: Option<String>,
+    pub s3_secret_access_key: Option<String>,
+    pub s3_session_token: Option<String>,
+    /// Allow exporting a run's wallet, mnemonic included, via /results/{id}/wallet-export
+    pub expose_mnemonic: bool,
+    /// Extra `Name: value` headers sent with every faucet request
+    pub faucet_headers: Vec<(String, String)>,
+    /// Headers sent to the BTC faucet only, overriding shared ones of the same name
+    pub btc_faucet_headers: Vec<(String, String)>,
+    /// Headers sent to the LavaUSD faucet only, overriding shared ones of the same name
+    pub lava_usd_faucet_headers: Vec<(String, String)>,
+    /// Network name confirmation history is kept under
+    pub network: String,
+    /// Esplora-compatible explorer used to poll transaction confirmations
+    pub explorer_url: String,
+    /// BTC faucet endpoint
+    pub btc_faucet_url: String,
+    /// LavaUSD faucet endpoint
+    pub lava_usd_faucet_url: String,
+    /// Endpoints per network selectable via `network` in /run-test
+    pub networks: BTreeMap<String, NetworkEndpoints>,
+    /// Delay between confirmation polls
+    pub confirmation_poll_interval_ms: u64,
+    /// Confirmation timeout used until there's history to adapt from, and the least the
+    /// adaptive timeout ever waits
+    pub confirmation_timeout_ms: u64,
+    /// Upper bound for the adaptive confirmation timeout
+    pub confirmation_max_timeout_ms: u64,
+    /// Adaptive timeout as a multiple of the median observed confirmation time
+    pub confirmation_timeout_multiplier: f64,
+    /// Adapt the confirmation timeout to history; when off CONFIRMATION_TIMEOUT_MS always applies
+    pub adaptive_confirmation_timeout: bool,
+    /// Number of observed confirmation times kept per network
+    pub confirmation_history_window: u32,
+    /// Observed confirmation times needed before the timeout adapts to them
+    pub confirmation_min_samples: u32,
+    /// Max serialized size of a result's details before they're truncated
+    pub max_details_bytes: usize,
+    /// Gzip each result's details before storing them
+    pub compress_details: bool,
+    /// Return faucet funds in a cleanup phase when a run stops before returning them
+    pub cleanup_return_funds: bool,
+    /// Fail an otherwise successful run whose fund return failed, instead of only logging it
+    pub strict_fund_return: bool,
+    /// File the wallet mnemonic is read from at the start of each run
+    pub mnemonic_file: Option<String>,
+    /// Mnemonic file of a funded wallet that funds each run instead of the faucets
+    pub prefund_mnemonic_file: Option<String>,
+    /// Named loan term presets selectable via `preset` in /run-test
+    pub loan_presets: BTreeMap<String, LoanTerms>,
+    /// Base directory each run gets its own CLI working directory under
+    pub run_dir_base: String,
+    /// Keep per-run working directories instead of removing them after the run
+    pub keep_run_dirs: bool,
+    /// Smallest plausible size of the downloaded CLI binary
+    pub cli_min_size_bytes: u64,
+    /// Most wallets funded by the faucets per budget day
+    pub daily_wallet_cap: Option<u32>,
+    /// Most sats requested from the BTC faucet per budget day
+    pub daily_sats_cap: Option<u64>,
+    /// UTC hour (0-23) the daily faucet budget resets at
+    pub daily_budget_reset_hour: u32,
+    /// Base directory for each run's CLI wallet state; unset keeps it inside the run directory
+    pub cli_state_dir: Option<String>,
+    /// How long a CLI invocation may run before it's killed
+    pub cli_timeout_ms: u64,
+    /// Line written to the CLI's stdin to answer a confirmation prompt
+    pub cli_confirm_response: String,
+    /// Flag appended to every CLI invocation to skip confirmation prompts, e.g. `--yes`
+    pub cli_yes_flag: Option<String>,
+    /// Output fragments (case-insensitive) marking the CLI's last line as a prompt it's waiting at
+    pub cli_prompt_patterns: Vec<String>,
+    /// Reject a BTC faucet txid confirmed longer ago than this; unset skips the explorer check
+    pub faucet_txid_max_age_secs: Option<u64>,
+    /// How long a database connection waits on a lock before giving up
+    pub db_busy_timeout_ms: u64,
+    /// Results kept in the /results/{id} read cache; 0 disables it
+    pub result_cache_capacity: usize,
+    /// How long a cached result is served before it's re-read
+    pub result_cache_ttl_ms: u64,
+    /// Webhook notified when a canary run fails or the success rate stays low
+    pub alert_webhook_url: Option<String>,
+    /// Longest an alert webhook request may take
+    pub alert_webhook_timeout_ms: u64,
+    /// Alert when the rolling success rate is below this; the monitor is off when unset
+    pub success_rate_alert_threshold: Option<f64>,
+    /// Number of most recent runs the rolling success rate is computed over
+    pub success_rate_alert_window: u32,
+    /// Consecutive low checks before alerting
+    pub success_rate_alert_sustain_checks: u32,
+    /// How often the rolling success rate is checked
+    pub success_rate_check_interval_secs: u64,
+    /// Store a run's funding confirmation as soon as it's seen so a rerun of the run skips the wait
+    pub persist_funding_confirmation: bool,
+    /// Only count a loan as closed once its repayment txid confirms on the explorer
+    pub verify_repayment_onchain: bool,
+    /// How long to wait for the repayment txid to confirm
+    pub repayment_confirmation_timeout_ms: u64,
+    /// Executable run after each run with the result JSON on stdin
+    pub post_run_hook: Option<String>,
+    /// How long the post-run hook may take before it's killed
+    pub post_run_hook_timeout_ms: u64,
+    /// Faucet body field carrying the per-run idempotency key; the key is only sent as a header when unset
+    pub faucet_idempotency_field: Option<String>,
+    /// Archive successful results older than this many hours; off when unset
+    pub archive_success_after_hours: Option<u64>,
+    /// Archive failed results older than this many hours; off when unset
+    pub archive_failed_after_hours: Option<u64>,
+    /// How often the background sweep runs
+    pub sweep_interval_secs: u64,
+    /// Timeout for a single faucet HTTP request
+    pub faucet_request_timeout_ms: u64,
+    /// Handling of a database file that fails PRAGMA integrity_check at startup
+    pub db_corruption_policy: DbCorruptionPolicy,
+    /// Open database connections at startup instead of on first use
+    pub db_pool_warmup: bool,
+    /// Connections opened and kept idle by the warmup
+    pub db_pool_warmup_connections: u32,
+    /// Wait for the LavaUSD transfer to finalize on Solana before creating the loan
+    pub lava_usd_confirmation: bool,
+    /// Solana JSON-RPC endpoint polled for LavaUSD transfer status
+    pub solana_rpc_url: String,
+    /// How long to wait for the LavaUSD transfer to finalize
+    pub lava_usd_confirmation_timeout_ms: u64,
+    /// Most results GET /results returns in one response
+    pub max_export_rows: u32,
+    /// Dotted paths in contract details that must be present for a run to pass
+    pub closed_loan_required_paths: Vec<String>,
+    /// What to do when the loan is already closed before the repay step
+    pub pre_closed_loan_policy: PreClosedLoanPolicy,
+    /// Dotted paths that must be present for a run whose loan closed before repayment
+    pub pre_closed_loan_required_paths: Vec<String>,
+    /// Which redirects faucet requests follow
+    pub faucet_redirect_policy: FaucetRedirectPolicy,
+    /// How often the background faucet reachability probe runs; off when unset
+    pub faucet_probe_interval_ms: Option<u64>,
+    /// Probe results kept per faucet
+    pub faucet_probe_history: u32,
+    /// Fields rewritten in results served by the read endpoints
+    pub redaction_rules: Vec<RedactionRule>,
+}
+
+/// Handling of a BTC address already used in recent runs
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressReusePolicy {
+    Warn,
+    Error,
+}
+
+impl FromStr for AddressReusePolicy {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "warn" => Ok(AddressReusePolicy::Warn),
+            "error" => Ok(AddressReusePolicy::Error),
+            other => Err(format!("unknown address reuse policy: {}", other)),
+        }
+    }
+}
+
+/// Handling of a loan that closed on its own (liquidation, early settlement) before we repaid it
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PreClosedLoanPolicy {
+    /// Skip repayment and judge the run by how the loan actually closed
+    Skip,
+    /// Fail the run
+    Fail,
+}
+
+impl FromStr for PreClosedLoanPolicy {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "skip" => Ok(PreClosedLoanPolicy::Skip),
+            "fail" => Ok(PreClosedLoanPolicy::Fail),
+            other => Err(format!("unknown pre-closed loan policy: {}", other)),
+        }
+    }
+}
+
+/// Redirects faucet requests follow. Following a POST redirect can resend its body,
+/// including the wallet address, to wherever the redirect points.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FaucetRedirectPolicy {
+    /// Follow no redirects
+    None,
+    /// Follow redirects to the same host and port only
+    SameHost,
+    /// Follow every redirect
+    All,
+}
+
+impl FromStr for FaucetRedirectPolicy {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(FaucetRedirectPolicy::None),
+            "same_host" => Ok(FaucetRedirectPolicy::SameHost),
+            "all" => Ok(FaucetRedirectPolicy::All),
+            other => Err(format!("unknown faucet redirect policy: {}", other)),
+        }
+    }
+}
+
+/// How a redacted field is rewritten
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactionStyle {
+    /// Replace with "[REDACTED]"
+    Mask,
+    /// Keep the first and last 4 characters
+    Partial,
+    /// Replace with a short SHA-256 digest, so equal values still match up
+    Hash,
+}
+
+impl FromStr for RedactionStyle {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "mask" => Ok(RedactionStyle::Mask),
+            "partial" => Ok(RedactionStyle::Partial),
+            "hash" => Ok(RedactionStyle::Hash),
+            other => Err(format!("unknown redaction style: {}", other)),
+        }
+    }
+}
+
+/// A dotted path into a serialized result and how to redact it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RedactionRule {
+    pub path: String,
+    pub style: RedactionStyle,
+}
+
+/// Parse `path` or `path:style`; the style defaults to mask
+impl FromStr for RedactionRule {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (path, style) = match s.split_once(':') {
+            Some((path, style)) => (path.trim(), style.trim().parse()?),
+            None => (s.trim(), RedactionStyle::Mask),
+        };
+        if path.is_empty() {
+            return Err(format!("missing field path in redaction rule {}", s));
+        }
+        Ok(RedactionRule {
+            path: path.to_string(),
+            style,
+        })
+    }
+}
+
+/// What to do when the database file fails its startup integrity check
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DbCorruptionPolicy {
+    /// Move the corrupt file aside and start with an empty database
+    Quarantine,
+    /// Refuse to start
+    Fail,
+}
+
+impl FromStr for DbCorruptionPolicy {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "quarantine" => Ok(DbCorruptionPolicy::Quarantine),
+            "fail" => Ok(DbCorruptionPolicy::Fail),
+            other => Err(format!("unknown db corruption policy: {}", other)),
+        }
+    }
+}
+
+/// Parameters a run's loan is created with
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LoanTerms {
+    pub loan_amount: u64,
+    pub loan_duration_days: u32,
+    pub ltv_ratio_bp: u32,
+}
+
+impl Default for LoanTerms {
+    fn default() -> Self {
+        LoanTerms {
+            loan_amount: 2,
+            loan_duration_days: 4,
+            ltv_ratio_bp: 5000,
+        }
+    }
+}
+
+/// Parse `amount:days:ltv_bp`
+impl FromStr for LoanTerms {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').map(|p| p.trim()).collect();
+        match parts.as_slice() {
+            [amount, days, ltv] => Ok(LoanTerms {
+                loan_amount: amount.parse().map_err(|_| format!("invalid loan amount: {}", amount))?,
+                loan_duration_days: days.parse().map_err(|_| format!("invalid loan duration: {}", days))?,
+                ltv_ratio_bp: ltv.parse().map_err(|_| format!("invalid LTV: {}", ltv))?,
+            }),
+            _ => Err(format!("expected amount:days:ltv_bp, got {}", s)),
+        }
+    }
+}
+
+/// Retry behavior for a single suite step
+#[derive(Debug, Clone, Serialize)]
+pub struct RetryPolicy {
+    /// Total attempts including the first
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled on each further retry
+    pub backoff_ms: u64,
+    /// Retry when the step returns an error
+    pub retry_on_error: bool,
+    /// Retry when an HTTP step gets a 5xx response
+    pub retry_on_server_error: bool,
+}
+
+impl RetryPolicy {
+    /// Load a step's policy from RETRY_<STEP>_* env vars
+    fn from_env(step: &str, default: RetryPolicy) -> Self {
+        RetryPolicy {
+            max_attempts: env_or(&format!("RETRY_{}_ATTEMPTS", step), default.max_attempts).max(1),
+            backoff_ms: env_or(&format!("RETRY_{}_BACKOFF_MS", step), default.backoff_ms),
+            retry_on_error: env_or(&format!("RETRY_{}_ON_ERROR", step), default.retry_on_error),
+            retry_on_server_error: env_or(&format!("RETRY_{}_ON_5XX", step), default.retry_on_server_error),
+        }
+    }
+}
+
+/// Retry policies for each suite step
+#[derive(Debug, Clone, Serialize)]
+pub struct StepRetries {
+    pub btc_faucet: RetryPolicy,
+    pub lava_usd_faucet: RetryPolicy,
+    pub setup_cli: RetryPolicy,
+    pub create_loan: RetryPolicy,
+    pub repay_loan: RetryPolicy,
+    pub contract_details: RetryPolicy,
+    pub return_funds: RetryPolicy,
+    pub prefund: RetryPolicy,
+}
+
+impl StepRetries {
+    fn from_env() -> Self {
+        let faucet = RetryPolicy {
+            max_attempts: 3,
+            backoff_ms: 2000,
+            retry_on_error: true,
+            retry_on_server_error: true,
+        };
+        // Steps that move funds don't retry unless asked to: an error after the transaction
+        // went out, such as a timeout, would otherwise send it twice
+        let no_retry = RetryPolicy {
+            max_attempts: 1,
+            backoff_ms: 0,
+            retry_on_error: false,
+            retry_on_server_error: false,
+        };
+        let on_error = RetryPolicy {
+            max_attempts: 2,
+            backoff_ms: 2000,
+            retry_on_error: true,
+            retry_on_server_error: false,
+        };
+        
+        StepRetries {
+            btc_faucet: RetryPolicy::from_env("BTC_FAUCET", faucet.clone()),
+            lava_usd_faucet: RetryPolicy::from_env("LAVA_USD_FAUCET", faucet),
+            setup_cli: RetryPolicy::from_env("SETUP_CLI", on_error.clone()),
+            create_loan: RetryPolicy::from_env("CREATE_LOAN", no_retry.clone()),
+            repay_loan: RetryPolicy::from_env("REPAY_LOAN", no_retry.clone()),
+            contract_details: RetryPolicy::from_env("CONTRACT_DETAILS", on_error),
+            return_funds: RetryPolicy::from_env("RETURN_FUNDS", no_retry.clone()),
+            prefund: RetryPolicy::from_env("PREFUND", no_retry),
+        }
+    }
+}
+
+/// Faucet and explorer endpoints for one network
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NetworkEndpoints {
+    pub btc_faucet_url: String,
+    pub lava_usd_faucet_url: String,
+    pub explorer_url: String,
+}
+
+impl FromStr for NetworkEndpoints {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split('|').map(|p| p.trim()).collect();
+        match parts.as_slice() {
+            [btc, lava_usd, explorer] if !btc.is_empty() && !lava_usd.is_empty() && !explorer.is_empty() => {
+                Ok(NetworkEndpoints {
+                    btc_faucet_url: btc.to_string(),
+                    lava_usd_faucet_url: lava_usd.to_string(),
+                    explorer_url: explorer.to_string(),
+                })
+            }
+            _ => Err(format!("expected btc_faucet_url|lava_usd_faucet_url|explorer_url, got {}", s)),
+        }
+    }
+}
+
+impl Config {
+    /// Config for a run on the given network, or the default network when none is given
+    pub fn for_network(&self, network: Option<&str>) -> Option<Config> {
+        let name = network.unwrap_or(&self.network);
+        if name == self.network {
+            return Some(self.clone());
+        }
+        let endpoints = self.networks.get(name)?;
+        
+        let mut config = self.clone();
+        config.network = name.to_string();
+        config.btc_faucet_url = endpoints.btc_faucet_url.clone();
+        config.lava_usd_faucet_url = endpoints.lava_usd_faucet_url.clone();
+        config.explorer_url = endpoints.explorer_url.clone();
+        Some(config)
+    }
+    
+    /// Loan terms for a preset, or the `default` preset when none is given
+    pub fn loan_terms(&self, preset: Option<&str>) -> Option<LoanTerms> {
+        match preset {
+            Some(name) => self.loan_presets.get(name).copied(),
+            None => Some(self.loan_presets.get("default").copied().unwrap_or_default()),
+        }
+    }
+    
+    /// Serialize the effective config with secret values redacted
+    pub fn redacted(&self) -> Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        
+        if let Some(map) = value.as_object_mut() {
+            for field in SECRET_FIELDS {
+                if let Some(secret) = map.get_mut(*field) {
+                    if !secret.is_null() {
+                        *secret = Value::String("[REDACTED]".to_string());
+                    }
+                }
+            }
+        }
+        
+        value
+    }
+    
+    /// Load config from env, falling back to defaults
+    pub fn from_env() -> Self {
+        let network = env_or("NETWORK", "mutinynet".to_string());
+        let explorer_url = env_or("EXPLORER_URL", "https://mutinynet.com".to_string());
+        let btc_faucet_url = env_or("BTC_FAUCET_URL", "https://faucet.testnet.lava.xyz/mint-mutinynet".to_string());
+        let lava_usd_faucet_url = env_or(
+            "LAVA_USD_FAUCET_URL",
+            "https://faucet.testnet.lava.xyz/transfer-lava-usd".to_string(),
+        );
+        
+        // The default network always comes from the single-network vars; NETWORKS adds others
+        let mut networks: BTreeMap<String, NetworkEndpoints> = env_list("NETWORKS", &[])
+            .iter()
+            .map(|entry| {
+                let (name, endpoints) = entry
+                    .split_once('=')
+                    .unwrap_or_else(|| panic!("Invalid NETWORKS: expected name=endpoints, got {}", entry));
+                let endpoints = endpoints
+                    .parse()
+                    .unwrap_or_else(|e| panic!("Invalid NETWORKS entry for {}: {}", name.trim(), e));
+                (name.trim().to_string(), endpoints)
+            })
+            .collect();
+        networks.insert(network.clone(), NetworkEndpoints {
+            btc_faucet_url: btc_faucet_url.clone(),
+            lava_usd_faucet_url: lava_usd_faucet_url.clone(),
+            explorer_url: explorer_url.clone(),
+        });
+        
+        Config {
+            max_log_body_len: env_or("MAX_LOG_BODY_LEN", 512),
+            api_key: env_opt("API_KEY"),
+            allow_unguarded_endpoints: env_or("ALLOW_UNGUARDED_ENDPOINTS", false),
+            trusted_proxies: env_list("TRUSTED_PROXIES", &[])
+                .iter()
+                .map(|ip| ip.parse().unwrap_or_else(|e| panic!("Invalid TRUSTED_PROXIES: '{}' {}", ip, e)))
+                .collect(),
+            btc_faucet_address_field: env_or("BTC_FAUCET_ADDRESS_FIELD", "address".to_string()),
+            btc_faucet_amount_field: env_or("BTC_FAUCET_AMOUNT_FIELD", "sats".to_string()),
+            btc_faucet_amount: env_strict("BTC_FAUCET_AMOUNT", Amount::from_sats(50_000)),
+            lava_usd_faucet_pubkey_field: env_or("LAVA_USD_FAUCET_PUBKEY_FIELD", "pubkey".to_string()),
+            allow_placeholder_wallet: env_or("ALLOW_PLACEHOLDER_WALLET", false),
+            retries: StepRetries::from_env(),
+            backup_dir: env_or("BACKUP_DIR", "./data/backups".to_string()),
+            btc_faucet_txid_paths: env_list(
+                "BTC_FAUCET_TXID_PATHS",
+                &["txid", "data.txid", "result.txid", "data.result.txid"],
+            ),
+            lava_usd_faucet_txid_paths: env_list(
+                "LAVA_USD_FAUCET_TXID_PATHS",
+                &["signature", "txid", "data.signature", "data.txid", "result.signature", "result.txid"],
+            ),
+            enable_failure_injection: env_or("ENABLE_FAILURE_INJECTION", false),
+            max_concurrent_runs: env_or("MAX_CONCURRENT_RUNS", 2).max(1),
+            max_concurrent_faucet_calls: env_or("MAX_CONCURRENT_FAUCET_CALLS", 1).max(1),
+            parallel_faucet_requests: env_or("PARALLEL_FAUCET_REQUESTS", false),
+            max_queued_runs: env_or("MAX_QUEUED_RUNS", 10),
+            sync_run_timeout_secs: env_or("SYNC_RUN_TIMEOUT_SECS", 1800),
+            max_wallets_per_run: env_or("MAX_WALLETS_PER_RUN", 5).max(1),
+            address_reuse_policy: env_strict("ADDRESS_REUSE_POLICY", AddressReusePolicy::Warn),
+            address_reuse_lookback: env_or("ADDRESS_REUSE_LOOKBACK", 50),
+            wallet_cooldown_secs: env_or("WALLET_COOLDOWN_SECS", 0),
+            connectivity_probe_url: env_or("CONNECTIVITY_PROBE_URL", "https://faucet.testnet.lava.xyz".to_string()),
+            connectivity_probe_timeout_ms: env_or("CONNECTIVITY_PROBE_TIMEOUT_MS", 3000),
+            cli_work_dir: env_or("CLI_WORK_DIR", ".".to_string()),
+            rng_seed: env_strict_opt("RNG_SEED"),
+            faucet_exhaustion_patterns: env_list(
+                "FAUCET_EXHAUSTION_PATTERNS",
+                &["out of funds", "faucet empty", "faucet is empty", "insufficient funds", "insufficient balance"],
+            ),
+            faucet_health_window: env_or("FAUCET_HEALTH_WINDOW", 5).max(1),
+            s3_bucket: env_opt("S3_BUCKET"),
+            s3_prefix: env_or("S3_PREFIX", "test-results".to_string()),
+            s3_region: env_opt("S3_REGION")
+                .or_else(|| env_opt("AWS_REGION"))
+                .unwrap_or_else(|| "us-east-1".to_string()),
+            s3_endpoint: env_opt("S3_ENDPOINT"),
+            s3_request_timeout_ms: env_or("S3_REQUEST_TIMEOUT_MS", 30_000),
+            s3_access_key_id: env_opt("AWS_ACCESS_KEY_ID"),
+            s3_secret_access_key: env_opt("AWS_SECRET_ACCESS_KEY"),
+            s3_session_token: env_opt("AWS_SESSION_TOKEN"),
+            expose_mnemonic: env_or("EXPOSE_MNEMONIC", false),
+            faucet_headers: env_headers("FAUCET_HEADERS"),
+            btc_faucet_headers: env_headers("BTC_FAUCET_HEADERS"),
+            lava_usd_faucet_headers: env_headers("LAVA_USD_FAUCET_HEADERS"),
+            network,
+            explorer_url,
+            btc_faucet_url,
+            lava_usd_faucet_url,
+            networks,
+            confirmation_poll_interval_ms: env_or("CONFIRMATION_POLL_INTERVAL_MS", 2000).max(100),
+            confirmation_timeout_ms: env_or("CONFIRMATION_TIMEOUT_MS", 120_000),
+            confirmation_max_timeout_ms: env_or("CONFIRMATION_MAX_TIMEOUT_MS", 600_000),
+            confirmation_timeout_multiplier: env_or("CONFIRMATION_TIMEOUT_MULTIPLIER", 3.0),
+            adaptive_confirmation_timeout: env_or("ADAPTIVE_CONFIRMATION_TIMEOUT", true),
+            confirmation_history_window: env_or("CONFIRMATION_HISTORY_WINDOW", 20).max(1),
+            confirmation_min_samples: env_or("CONFIRMATION_MIN_SAMPLES", 5).max(1),
+            max_details_bytes: env_or("MAX_DETAILS_BYTES", 65536),
+            compress_details: env_or("COMPRESS_DETAILS", false),
+            cleanup_return_funds: env_or("CLEANUP_RETURN_FUNDS", true),
+            strict_fund_return: env_or("STRICT_FUND_RETURN", false),
+            mnemonic_file: env_opt("MNEMONIC_FILE"),
+            prefund_mnemonic_file: env_opt("PREFUND_MNEMONIC_FILE"),
+            loan_presets: env_list(
+                "LOAN_PRESETS",
+                &["default=2:4:5000", "short-30d-50ltv=2:30:5000", "long-180d-30ltv=2:180:3000"],
+            )
+            .iter()
+            .map(|preset| {
+                let (name, terms) = preset
+                    .split_once('=')
+                    .unwrap_or_else(|| panic!("Invalid LOAN_PRESETS: expected name=amount:days:ltv_bp, got {}", preset));
+                let terms = terms
+                    .parse()
+                    .unwrap_or_else(|e| panic!("Invalid LOAN_PRESETS entry for {}: {}", name.trim(), e));
+                (name.trim().to_string(), terms)
+            })
+            .collect(),
+            run_dir_base: env_or(
+                "RUN_DIR_BASE",
+                env::temp_dir().join("borrower-cli-runs").to_string_lossy().into_owned(),
+            ),
+            keep_run_dirs: env_or("KEEP_RUN_DIRS", false),
+            cli_min_size_bytes: env_or("CLI_MIN_SIZE_BYTES", 1_048_576),
+            cli_state_dir: env_opt("CLI_STATE_DIR"),
+            cli_timeout_ms: env_or("CLI_TIMEOUT_MS", 60_000),
+            cli_confirm_response: env_or("CLI_CONFIRM_RESPONSE", "y".to_string()),
+            cli_yes_flag: env_opt("CLI_YES_FLAG"),
+            cli_prompt_patterns: env_list(
+                "CLI_PROMPT_PATTERNS",
+                &["[y/n]", "(y/n)", "[yes/no]", "(yes/no)", "are you sure", "continue?"],
+            ),
+            daily_wallet_cap: env_strict_opt("DAILY_WALLET_CAP"),
+            daily_sats_cap: env_strict_opt("DAILY_SATS_CAP"),
+            daily_budget_reset_hour: env_strict_opt::<u32>("DAILY_BUDGET_RESET_HOUR")
+                .inspect(|hour| {
+                    if *hour > 23 {
+                        panic!("Invalid DAILY_BUDGET_RESET_HOUR: {} is not an hour between 0 and 23", hour);
+                    }
+                })
+                .unwrap_or(0),
+            faucet_txid_max_age_secs: env_strict_opt("FAUCET_TXID_MAX_AGE_SECS"),
+            db_busy_timeout_ms: env_or("DB_BUSY_TIMEOUT_MS", 5000),
+            result_cache_capacity: env_or("RESULT_CACHE_CAPACITY", 64),
+            result_cache_ttl_ms: env_or("RESULT_CACHE_TTL_MS", 5000),
+            alert_webhook_url: env_opt("ALERT_WEBHOOK_URL"),
+            alert_webhook_timeout_ms: env_or("ALERT_WEBHOOK_TIMEOUT_MS", 10_000),
+            success_rate_alert_threshold: env_strict_opt::<f64>("SUCCESS_RATE_ALERT_THRESHOLD").inspect(|rate| {
+                if !(0.0..=1.0).contains(rate) {
+                    panic!("Invalid SUCCESS_RATE_ALERT_THRESHOLD: {} is not between 0 and 1", rate);
+                }
+            }),
+            success_rate_alert_window: env_or("SUCCESS_RATE_ALERT_WINDOW", 20).max(1),
+            success_rate_alert_sustain_checks: env_or("SUCCESS_RATE_ALERT_SUSTAIN_CHECKS", 3).max(1),
+            success_rate_check_interval_secs: env_or("SUCCESS_RATE_CHECK_INTERVAL_SECS", 60).max(1),
+            persist_funding_confirmation: env_or("PERSIST_FUNDING_CONFIRMATION", true),
+            verify_repayment_onchain: env_or("VERIFY_REPAYMENT_ONCHAIN", false),
+            repayment_confirmation_timeout_ms: env_or("REPAYMENT_CONFIRMATION_TIMEOUT_MS", 120_000),
+            post_run_hook: env_opt("POST_RUN_HOOK"),
+            post_run_hook_timeout_ms: env_or("POST_RUN_HOOK_TIMEOUT_MS", 30_000),
+            faucet_idempotency_field: env_opt("FAUCET_IDEMPOTENCY_FIELD"),
+            archive_success_after_hours: env_strict_opt("ARCHIVE_SUCCESS_AFTER_HOURS"),
+            archive_failed_after_hours: env_strict_opt("ARCHIVE_FAILED_AFTER_HOURS"),
+            sweep_interval_secs: env_or("SWEEP_INTERVAL_SECS", 3600).max(1),
+            faucet_request_timeout_ms: env_or("FAUCET_REQUEST_TIMEOUT_MS", 30_000),
+            db_corruption_policy: env_strict("DB_CORRUPTION_POLICY", DbCorruptionPolicy::Quarantine),
+            db_pool_warmup: env_or("DB_POOL_WARMUP", true),
+            db_pool_warmup_connections: env_or("DB_POOL_WARMUP_CONNECTIONS", 4).max(1),
+            lava_usd_confirmation: env_or("LAVA_USD_CONFIRMATION", false),
+            solana_rpc_url: env_or("SOLANA_RPC_URL", "https://api.devnet.solana.com".to_string()),
+            lava_usd_confirmation_timeout_ms: env_or("LAVA_USD_CONFIRMATION_TIMEOUT_MS", 60_000),
+            max_export_rows: env_or("MAX_EXPORT_ROWS", 1000).max(1),
+            closed_loan_required_paths: env_list(
+                "CLOSED_LOAN_REQUIRED_PATHS",
+                &["Closed", "outcome.repayment.collateral_repayment_txid"],
+            ),
+            pre_closed_loan_policy: env_strict("PRE_CLOSED_LOAN_POLICY", PreClosedLoanPolicy::Skip),
+            pre_closed_loan_required_paths: env_list("PRE_CLOSED_LOAN_REQUIRED_PATHS", &["Closed"]),
+            faucet_redirect_policy: env_strict("FAUCET_REDIRECT_POLICY", FaucetRedirectPolicy::SameHost),
+            faucet_probe_interval_ms: env_strict_opt::<u64>("FAUCET_PROBE_INTERVAL_MS").map(|ms| ms.max(100)),
+            faucet_probe_history: env_or("FAUCET_PROBE_HISTORY", 1000).max(1),
+            redaction_rules: env_list("REDACTION_RULES", &["mnemonic"])
+                .iter()
+                .map(|rule| rule.parse().unwrap_or_else(|e| panic!("Invalid REDACTION_RULES: {}", e)))
+                .collect(),
+        }
+    }
+}
+
+/// Parse an env var, falling back to the default when unset or invalid
+fn env_or<T: FromStr>(key: &str, default: T) -> T {
+    env::var(key)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Read an env var, treating unset or empty as None
+fn env_opt(key: &str) -> Option<String> {
+    env::var(key).ok().filter(|v| !v.is_empty())
+}
+
+/// Read an env var that must parse when set, so a typo fails startup instead of using the default
+fn env_strict<T: FromStr<Err = String>>(key: &str, default: T) -> T {
+    match env_opt(key) {
+        Some(v) => v.parse().unwrap_or_else(|e| panic!("Invalid {}: {}", key, e)),
+        None => default,
+    }
+}
+
+/// Optional counterpart of `env_strict`: unset is None, a value that doesn't parse fails startup
+fn env_strict_opt<T: FromStr>(key: &str) -> Option<T>
+where
+    T::Err: std::fmt::Display,
+{
+    env_opt(key).map(|v| v.parse().unwrap_or_else(|e| panic!("Invalid {}: {}", key, e)))
+}
+
+/// Read comma-separated `Name: value` headers, skipping entries without a colon
+fn env_headers(key: &str) -> Vec<(String, String)> {
+    env_list(key, &[])
+        .into_iter()
+        .filter_map(|header| {
+            let (name, value) = header.split_once(':')?;
+            Some((name.trim().to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Read a comma-separated env var, falling back to the default when unset or empty
+fn env_list(key: &str, default: &[&str]) -> Vec<String> {
+    let values: Vec<String> = env::var(key)
+        .unwrap_or_default()
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect();
+    
+    if values.is_empty() {
+        default.iter().map(|v| v.to_string()).collect()
+    } else {
+        values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn redacted_masks_secret_fields() {
+        let mut config = Config::from_env();
+        config.api_key = Some("super-secret-key".to_string());
+        
+        let redacted = config.redacted();
+        for field in SECRET_FIELDS {
+            let value = &redacted[*field];
+            assert!(value.is_null() || value == "[REDACTED]", "{} not masked: {}", field, value);
+        }
+        assert_eq!(redacted["api_key"], "[REDACTED]");
+        assert!(!redacted.to_string().contains("super-secret-key"));
+        assert_eq!(redacted["max_log_body_len"], config.max_log_body_len);
+    }
+    
+    #[test]
+    fn redacted_keeps_unset_secrets_null() {
+        let mut config = Config::from_env();
+        config.api_key = None;
+        assert!(config.redacted()["api_key"].is_null());
+    }
+}
@@ -1,23 +1,78 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::params;
+use rusqlite::types::FromSql;
+use rusqlite::{params, Connection, OptionalExtension, Row, TransactionBehavior};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
 use std::path::Path;
-use log::info;
-use serde_json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use log::{error, info, warn};
 
-use crate::TestResult;
+use crate::config::{DbCorruptionPolicy, LoanTerms};
+use crate::secret::Mnemonic;
+use crate::{TestResult, RESULT_SCHEMA_VERSION};
 
 pub type DbPool = Pool<SqliteConnectionManager>;
 
-/// Init DB pool
-pub fn init_pool(db_path: &str) -> Result<DbPool> {
+/// Attempts for a read that still hits SQLITE_BUSY after the busy timeout
+const READ_ATTEMPTS: u64 = 3;
+
+/// Most connections the pool opens
+const POOL_MAX_SIZE: u32 = 10;
+
+/// Whether saved results have their details gzipped, from COMPRESS_DETAILS. Reads
+/// decompress by each row's `details_compressed` flag, whatever this is set to.
+static COMPRESS_DETAILS: AtomicBool = AtomicBool::new(false);
+
+/// Turn details compression on or off for results saved from now on
+pub fn set_details_compression(enabled: bool) {
+    COMPRESS_DETAILS.store(enabled, Ordering::Relaxed);
+}
+
+/// Init DB pool. Connections use WAL so reads don't block on writers, and wait up
+/// to `busy_timeout_ms` for locks instead of failing straight away. With
+/// `warmup_connections` that many connections are opened before this returns and
+/// kept idle; otherwise connections are opened on first use.
+pub fn init_pool(
+    db_path: &str,
+    busy_timeout_ms: u64,
+    on_corruption: DbCorruptionPolicy,
+    warmup_connections: Option<u32>,
+) -> Result<DbPool> {
     if let Some(parent) = Path::new(db_path).parent() {
         std::fs::create_dir_all(parent)?;
     }
     
-    let manager = SqliteConnectionManager::file(db_path);
-    let pool = Pool::new(manager)?;
+    if Path::new(db_path).exists() {
+        if let Err(problem) = check_integrity(db_path) {
+            error!("Database {} failed its integrity check: {}", db_path, problem);
+            match on_corruption {
+                DbCorruptionPolicy::Fail => {
+                    return Err(anyhow!("Database {} is corrupt: {}", db_path, problem));
+                }
+                DbCorruptionPolicy::Quarantine => {
+                    let moved_to = quarantine_db_file(db_path)?;
+                    error!("Moved corrupt database to {} and starting with an empty one", moved_to);
+                }
+            }
+        }
+    }
+    
+    let manager = SqliteConnectionManager::file(db_path).with_init(move |conn| {
+        conn.busy_timeout(Duration::from_millis(busy_timeout_ms))?;
+        conn.execute_batch("PRAGMA journal_mode = WAL;")
+    });
+    let min_idle = warmup_connections.unwrap_or(0).min(POOL_MAX_SIZE);
+    let pool = Pool::builder()
+        .max_size(POOL_MAX_SIZE)
+        .min_idle(Some(min_idle))
+        .build(manager)?;
     
     // DB schema
     let conn = pool.get()?;
@@ -36,30 +91,312 @@ pub fn init_pool(db_path: &str) -> Result<DbPool> {
             repayment_txid TEXT,
             details TEXT,
             error_message TEXT,
-            returned_funds INTEGER NOT NULL
+            returned_funds INTEGER NOT NULL,
+            sats_requested INTEGER NOT NULL DEFAULT 0,
+            sats_returned INTEGER NOT NULL DEFAULT 0,
+            net_sats_consumed INTEGER NOT NULL DEFAULT 0,
+            steps TEXT NOT NULL DEFAULT '[]',
+            return_txid TEXT
+        )",
+        [],
+    )?;
+    
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS audit_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp TEXT NOT NULL,
+            client_ip TEXT,
+            api_key_id TEXT,
+            method TEXT NOT NULL,
+            route TEXT NOT NULL,
+            status INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS fund_returns (
+            run_id TEXT NOT NULL,
+            btc_address TEXT NOT NULL,
+            loan_contract_id TEXT,
+            return_txid TEXT NOT NULL,
+            broadcast_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS faucet_fundings (
+            run_id TEXT PRIMARY KEY,
+            sats INTEGER NOT NULL,
+            funded_at TEXT NOT NULL
         )",
         [],
     )?;
     
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS confirmation_times (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            network TEXT NOT NULL,
+            duration_ms INTEGER NOT NULL,
+            recorded_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS funding_confirmations (
+            result_id TEXT PRIMARY KEY,
+            txid TEXT NOT NULL,
+            confirmations INTEGER NOT NULL,
+            confirmed_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS faucet_health (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            faucet TEXT NOT NULL,
+            status TEXT NOT NULL,
+            http_status INTEGER,
+            latency_ms INTEGER NOT NULL,
+            error TEXT,
+            checked_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    
+    // Columns added after the initial schema
+    ensure_column(&conn, "test_results", "sats_requested", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(&conn, "test_results", "sats_returned", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(&conn, "test_results", "net_sats_consumed", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(&conn, "test_results", "steps", "TEXT NOT NULL DEFAULT '[]'")?;
+    ensure_column(&conn, "test_results", "return_txid", "TEXT")?;
+    ensure_column(&conn, "test_results", "faucet_accepted", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(&conn, "test_results", "funds_confirmed", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(&conn, "test_results", "details_truncated", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(&conn, "test_results", "preset", "TEXT")?;
+    ensure_column(&conn, "test_results", "run_request", "TEXT")?;
+    ensure_column(&conn, "test_results", "parent_id", "TEXT")?;
+    ensure_column(&conn, "test_results", "canary", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(&conn, "test_results", "contract_id_collision", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(&conn, "test_results", "archived", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(&conn, "test_results", "loan_amount", "INTEGER")?;
+    ensure_column(&conn, "test_results", "loan_duration_days", "INTEGER")?;
+    ensure_column(&conn, "test_results", "ltv_ratio_bp", "INTEGER")?;
+    backfill_loan_terms(&conn)?;
+    // Insertion order, so listing doesn't depend on the host clock; rows from before the
+    // column existed keep the order they were inserted in
+    ensure_column(&conn, "test_results", "seq", "INTEGER")?;
+    conn.execute("UPDATE test_results SET seq = rowid WHERE seq IS NULL", [])?;
+    // Set when `details` holds gzipped JSON as a BLOB rather than JSON text
+    ensure_column(&conn, "test_results", "details_compressed", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(&conn, "test_results", "funding_source", "TEXT")?;
+    ensure_column(&conn, "test_results", "repayment_confirmed", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(&conn, "test_results", "cli_exit_code", "INTEGER")?;
+    ensure_column(&conn, "test_results", "preserved_wallet", "INTEGER NOT NULL DEFAULT 0")?;
+    // Rows from before versioning are version 1
+    ensure_column(&conn, "test_results", "schema_version", "INTEGER NOT NULL DEFAULT 1")?;
+    // Whether sats_returned/net_sats_consumed hold a real amount. Older rows that returned
+    // funds recorded 0 returned, which was never measured
+    ensure_column(&conn, "test_results", "sats_returned_known", "INTEGER")?;
+    conn.execute("UPDATE test_results SET sats_returned_known = (returned_funds = 0) WHERE sats_returned_known IS NULL", [])?;
+    ensure_column(&conn, "audit_log", "forwarded_ip", "TEXT")?;
+    
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_test_results_loan_contract_id ON test_results (loan_contract_id)",
+        [],
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_test_results_seq ON test_results (seq)", [])?;
+    
     info!("Database initialized at {}", db_path);
     Ok(pool)
 }
 
-/// Save tests to data/test_results.db
+/// Run PRAGMA integrity_check, returning what it reported when the file isn't healthy
+fn check_integrity(db_path: &str) -> std::result::Result<(), String> {
+    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare("PRAGMA integrity_check").map_err(|e| e.to_string())?;
+    let problems = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| e.to_string())?;
+    
+    if problems.len() == 1 && problems[0] == "ok" {
+        Ok(())
+    } else {
+        Err(problems.join("; "))
+    }
+}
+
+/// Rename a corrupt database, and its WAL and shared-memory files, aside with a timestamp suffix
+fn quarantine_db_file(db_path: &str) -> Result<String> {
+    let suffix = format!("corrupt-{}", chrono::Utc::now().format("%Y%m%dT%H%M%SZ"));
+    let moved_to = format!("{}.{}", db_path, suffix);
+    std::fs::rename(db_path, &moved_to)?;
+    
+    for sidecar in ["-wal", "-shm"] {
+        let path = format!("{}{}", db_path, sidecar);
+        if Path::new(&path).exists() {
+            std::fs::rename(&path, format!("{}{}.{}", db_path, sidecar, suffix))?;
+        }
+    }
+    
+    Ok(moved_to)
+}
+
+/// Retry a read that failed because the database was busy or locked. The backoff is an
+/// async sleep so a handler waiting out a burst of writes doesn't hold its worker thread.
+pub async fn with_busy_retry<T>(op: impl Fn() -> Result<T>) -> Result<T> {
+    let mut attempt = 1;
+    loop {
+        match op() {
+            Err(e) if attempt < READ_ATTEMPTS && is_busy(&e) => {
+                warn!("Database busy on read (attempt {}/{}), retrying", attempt, READ_ATTEMPTS);
+                tokio::time::sleep(Duration::from_millis(100 * attempt)).await;
+                attempt += 1;
+            }
+            outcome => return outcome,
+        }
+    }
+}
+
+/// Run a read in its own deferred transaction. It never takes the write lock, so under
+/// WAL it reads one consistent snapshot without blocking or waiting on writers.
+fn read_only<T>(pool: &DbPool, read: impl FnOnce(&Connection) -> Result<T>) -> Result<T> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Deferred)?;
+    let value = read(&tx)?;
+    tx.commit()?;
+    Ok(value)
+}
+
+fn is_busy(e: &anyhow::Error) -> bool {
+    match e.downcast_ref::<rusqlite::Error>() {
+        Some(rusqlite::Error::SqliteFailure(err, _)) => {
+            matches!(err.code, rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+        }
+        _ => false,
+    }
+}
+
+/// Add a column to an existing table if it's missing
+fn ensure_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|name| name.ok())
+        .any(|name| name == column);
+    
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+        info!("Added column {}.{}", table, column);
+    }
+    
+    Ok(())
+}
+
+/// Fill the loan term columns of rows stored before they existed from the `loan_terms`
+/// their contract details recorded. Rows without parseable terms keep NULL.
+fn backfill_loan_terms(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "SELECT id, details FROM test_results WHERE loan_amount IS NULL AND details LIKE '%loan_terms%'",
+    )?;
+    let rows: Vec<(String, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    
+    let mut backfilled = 0;
+    for (id, details) in rows {
+        let terms = serde_json::from_str::<serde_json::Value>(&details)
+            .ok()
+            .and_then(|details| serde_json::from_value::<LoanTerms>(details.get("loan_terms")?.clone()).ok());
+        if let Some(terms) = terms {
+            conn.execute(
+                "UPDATE test_results SET loan_amount = ?, loan_duration_days = ?, ltv_ratio_bp = ? WHERE id = ?",
+                params![terms.loan_amount as i64, terms.loan_duration_days, terms.ltv_ratio_bp, id],
+            )?;
+            backfilled += 1;
+        }
+    }
+    if backfilled > 0 {
+        info!("Backfilled loan terms for {} stored results", backfilled);
+    }
+    
+    Ok(())
+}
+
+/// Save tests to data/test_results.db, updating the row if the ID already exists. A new
+/// row gets the next sequence number; an update keeps its original one.
 pub fn save_test_result(pool: &DbPool, result: &TestResult) -> Result<()> {
+    save_test_result_with(pool, result, COMPRESS_DETAILS.load(Ordering::Relaxed))
+}
+
+fn save_test_result_with(pool: &DbPool, result: &TestResult, compress_details: bool) -> Result<()> {
     let conn = pool.get()?;
+    let details = result.details.as_ref().map(|d| serde_json::to_string(d).unwrap_or_default());
+    let details = match details {
+        Some(json) if compress_details => Some(rusqlite::types::Value::Blob(gzip(json.as_bytes())?)),
+        Some(json) => Some(rusqlite::types::Value::Text(json)),
+        None => None,
+    };
+    let details_compressed = compress_details && details.is_some();
     
     conn.execute(
         "INSERT INTO test_results (
             id, timestamp, status, mnemonic, btc_address, lava_usd_pubkey,
             btc_faucet_response, lava_usd_faucet_response, loan_contract_id,
-            loan_closed, repayment_txid, details, error_message, returned_funds
-        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            loan_closed, repayment_txid, details, error_message, returned_funds,
+            sats_requested, sats_returned, net_sats_consumed, steps, return_txid,
+            faucet_accepted, funds_confirmed, details_truncated, preset, run_request, parent_id,
+            canary, contract_id_collision, archived, loan_amount, loan_duration_days, ltv_ratio_bp,
+            details_compressed, funding_source, repayment_confirmed, cli_exit_code, preserved_wallet, schema_version,
+            sats_returned_known, seq
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?,
+            (SELECT COALESCE(MAX(seq), 0) + 1 FROM test_results))
+        ON CONFLICT(id) DO UPDATE SET
+            status = excluded.status,
+            mnemonic = excluded.mnemonic,
+            btc_address = excluded.btc_address,
+            lava_usd_pubkey = excluded.lava_usd_pubkey,
+            btc_faucet_response = excluded.btc_faucet_response,
+            lava_usd_faucet_response = excluded.lava_usd_faucet_response,
+            loan_contract_id = excluded.loan_contract_id,
+            loan_closed = excluded.loan_closed,
+            repayment_txid = excluded.repayment_txid,
+            details = excluded.details,
+            error_message = excluded.error_message,
+            returned_funds = excluded.returned_funds,
+            sats_requested = excluded.sats_requested,
+            sats_returned = excluded.sats_returned,
+            net_sats_consumed = excluded.net_sats_consumed,
+            steps = excluded.steps,
+            return_txid = excluded.return_txid,
+            faucet_accepted = excluded.faucet_accepted,
+            funds_confirmed = excluded.funds_confirmed,
+            details_truncated = excluded.details_truncated,
+            preset = excluded.preset,
+            run_request = excluded.run_request,
+            parent_id = excluded.parent_id,
+            canary = excluded.canary,
+            contract_id_collision = excluded.contract_id_collision,
+            archived = excluded.archived,
+            loan_amount = excluded.loan_amount,
+            loan_duration_days = excluded.loan_duration_days,
+            ltv_ratio_bp = excluded.ltv_ratio_bp,
+            details_compressed = excluded.details_compressed,
+            funding_source = excluded.funding_source,
+            repayment_confirmed = excluded.repayment_confirmed,
+            cli_exit_code = excluded.cli_exit_code,
+            preserved_wallet = excluded.preserved_wallet,
+            schema_version = excluded.schema_version,
+            sats_returned_known = excluded.sats_returned_known",
         params![
             result.id,
             chrono::Utc::now().to_rfc3339(),
             result.status,
-            result.mnemonic,
+            result.mnemonic.expose(),
             result.btc_address,
             result.lava_usd_pubkey,
             serde_json::to_string(&result.btc_faucet_response)?,
@@ -67,9 +404,34 @@ pub fn save_test_result(pool: &DbPool, result: &TestResult) -> Result<()> {
             result.loan_contract_id,
             result.loan_closed as i64,
             result.repayment_txid,
-            result.details.as_ref().map(|d| serde_json::to_string(d).unwrap_or_default()),
+            details,
             result.error_message,
-            result.returned_funds as i64
+            result.returned_funds as i64,
+            result.sats_requested as i64,
+            result.sats_returned.unwrap_or(0) as i64,
+            result.net_sats_consumed.unwrap_or(0),
+            serde_json::to_string(&result.steps)?,
+            result.return_txid,
+            result.faucet_accepted as i64,
+            result.funds_confirmed as i64,
+            result.details_truncated as i64,
+            result.preset,
+            result.run_request.as_ref().map(serde_json::to_string).transpose()?,
+            result.parent_id,
+            result.canary as i64,
+            result.contract_id_collision as i64,
+            result.archived as i64,
+            result.loan_terms.map(|t| t.loan_amount as i64),
+            result.loan_terms.map(|t| t.loan_duration_days),
+            result.loan_terms.map(|t| t.ltv_ratio_bp),
+            details_compressed as i64,
+            result.funding_source,
+            result.repayment_confirmed as i64,
+            result.cli_exit_code,
+            result.preserved_wallet as i64,
+            // Every write uses the current layout, whatever version the result was read as
+            RESULT_SCHEMA_VERSION,
+            result.sats_returned.is_some() as i64
         ],
     )?;
     
@@ -77,86 +439,1052 @@ pub fn save_test_result(pool: &DbPool, result: &TestResult) -> Result<()> {
     Ok(())
 }
 
-/// GET tests from data/test_results.db
-pub fn get_all_test_results(pool: &DbPool) -> Result<Vec<TestResult>> {
-    let conn = pool.get()?;
-    let mut stmt = conn.prepare("SELECT * FROM test_results ORDER BY timestamp DESC")?;
-    
-    let test_result_iter = stmt.query_map([], |row| {
-        let btc_faucet_response_str: String = row.get(6)?;
-        let lava_usd_faucet_response_str: String = row.get(7)?;
-        let details_str: Option<String> = row.get(11)?;
+/// Filters for listing results; unset fields don't filter
+#[derive(Debug, Default)]
+pub struct ResultFilter {
+    pub status: Option<String>,
+    /// Inclusive RFC 3339 lower bound on the run timestamp
+    pub from: Option<String>,
+    /// Inclusive RFC 3339 upper bound on the run timestamp
+    pub to: Option<String>,
+    pub include_archived: bool,
+    /// Page size; all matching results when unset
+    pub limit: Option<u32>,
+    pub offset: u32,
+    /// Inclusive lower bound on the loan's LTV in basis points
+    pub min_ltv_bp: Option<u32>,
+    /// Exit code of the CLI invocation whose step failed the run
+    pub cli_exit_code: Option<i32>,
+}
+
+/// GET tests from data/test_results.db matching the filter, most recently inserted first
+pub fn get_test_results(pool: &DbPool, filter: &ResultFilter) -> Result<Vec<TestResult>> {
+    read_only(pool, |conn| {
+        let mut stmt = conn.prepare(
+            "SELECT * FROM test_results
+            WHERE (archived = 0 OR ?1)
+                AND (?2 IS NULL OR status = ?2)
+                AND timestamp BETWEEN COALESCE(?3, '') AND COALESCE(?4, '9999')
+                AND (?7 IS NULL OR ltv_ratio_bp >= ?7)
+                AND (?8 IS NULL OR cli_exit_code = ?8)
+            ORDER BY seq DESC LIMIT ?5 OFFSET ?6",
+        )?;
         
-        let btc_faucet_response: crate::FaucetResponse = serde_json::from_str(&btc_faucet_response_str)
-            .unwrap_or_else(|_| crate::FaucetResponse::default());
+        let limit = filter.limit.map_or(-1, i64::from);
+        let test_result_iter = stmt.query_map(
+            params![
+                filter.include_archived,
+                filter.status,
+                filter.from,
+                filter.to,
+                limit,
+                filter.offset,
+                filter.min_ltv_bp,
+                filter.cli_exit_code
+            ],
+            row_to_test_result,
+        )?;
         
-        let lava_usd_faucet_response: crate::FaucetResponse = serde_json::from_str(&lava_usd_faucet_response_str)
-            .unwrap_or_else(|_| crate::FaucetResponse::default());
+        let mut results = Vec::new();
+        for result in test_result_iter {
+            results.push(result?);
+        }
         
-        let details = details_str.and_then(|s| serde_json::from_str(&s).ok());
+        Ok(results)
+    })
+}
+
+/// GET canary runs from data/test_results.db, newest first
+pub fn get_canary_results(pool: &DbPool, limit: u32, offset: u32) -> Result<Vec<TestResult>> {
+    read_only(pool, |conn| {
+        let mut stmt = conn.prepare(
+            "SELECT * FROM test_results WHERE canary = 1 ORDER BY seq DESC LIMIT ? OFFSET ?",
+        )?;
         
-        Ok(TestResult {
-            id: row.get(0)?,
-            status: row.get(2)?,
-            mnemonic: row.get(3)?,
-            btc_address: row.get(4)?,
-            lava_usd_pubkey: row.get(5)?,
-            btc_faucet_response,
-            lava_usd_faucet_response,
-            loan_contract_id: row.get(8)?,
-            loan_closed: row.get::<_, i64>(9)? != 0,
-            repayment_txid: row.get(10)?,
-            details,
-            error_message: row.get(12)?,
-            returned_funds: row.get::<_, i64>(13)? != 0,
+        let test_result_iter = stmt.query_map(params![limit, offset], row_to_test_result)?;
+        
+        let mut results = Vec::new();
+        for result in test_result_iter {
+            results.push(result?);
+        }
+        
+        Ok(results)
+    })
+}
+
+/// ID of a stored result that already holds this loan contract ID, other than `exclude_id`
+pub fn find_contract_id_owner(pool: &DbPool, contract_id: &str, exclude_id: &str) -> Result<Option<String>> {
+    let conn = pool.get()?;
+    let owner = conn
+        .query_row(
+            "SELECT id FROM test_results WHERE loan_contract_id = ? AND id != ? LIMIT 1",
+            params![contract_id, exclude_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    
+    Ok(owner)
+}
+
+/// GET specific test by ID
+pub fn get_test_result_by_id(pool: &DbPool, id: &str) -> Result<Option<TestResult>> {
+    read_only(pool, |conn| {
+        let mut stmt = conn.prepare("SELECT * FROM test_results WHERE id = ?")?;
+        
+        let mut test_result_iter = stmt.query_map(params![id], row_to_test_result)?;
+        
+        match test_result_iter.next() {
+            Some(result) => Ok(Some(result?)),
+            None => Ok(None),
+        }
+    })
+}
+
+/// Check whether a BTC address appears in the most recent results
+pub fn address_used_recently(pool: &DbPool, btc_address: &str, lookback: u32) -> Result<bool> {
+    let conn = pool.get()?;
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM (
+            SELECT btc_address FROM test_results ORDER BY seq DESC LIMIT ?
+        ) WHERE btc_address = ?",
+        params![lookback, btc_address],
+        |row| row.get(0),
+    )?;
+    
+    Ok(count > 0)
+}
+
+/// Timestamp of the latest stored run against a BTC address, other than `exclude_id`
+pub fn last_run_at(pool: &DbPool, btc_address: &str, exclude_id: &str) -> Result<Option<String>> {
+    let conn = pool.get()?;
+    let timestamp = conn
+        .query_row(
+            "SELECT timestamp FROM test_results WHERE btc_address = ? AND id != ? ORDER BY seq DESC LIMIT 1",
+            params![btc_address, exclude_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    
+    Ok(timestamp)
+}
+
+/// Check whether a BTC faucet txid was already recorded by an earlier run
+pub fn faucet_txid_seen(pool: &DbPool, txid: &str) -> Result<bool> {
+    let conn = pool.get()?;
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM test_results WHERE json_extract(btc_faucet_response, '$.txid') = ?",
+        params![txid],
+        |row| row.get(0),
+    )?;
+    
+    Ok(count > 0)
+}
+
+/// Record a broadcast fund return as soon as it's sent
+pub fn save_fund_return(
+    pool: &DbPool,
+    btc_address: &str,
+    loan_contract_id: Option<&str>,
+    run_id: &str,
+    return_txid: &str,
+) -> Result<()> {
+    let conn = pool.get()?;
+    conn.execute(
+        "INSERT INTO fund_returns (run_id, btc_address, loan_contract_id, return_txid, broadcast_at)
+        VALUES (?, ?, ?, ?, ?)",
+        params![run_id, btc_address, loan_contract_id, return_txid, chrono::Utc::now().to_rfc3339()],
+    )?;
+    
+    info!("Recorded return txid {} for run {}", return_txid, run_id);
+    Ok(())
+}
+
+/// Reserve a run's faucet funding unless it would take the budget day starting at `since`
+/// past either cap. True when reserved, or when the run already holds a reservation.
+pub fn reserve_faucet_funding(
+    pool: &DbPool,
+    run_id: &str,
+    sats: u64,
+    funded_at: &str,
+    since: &str,
+    max_wallets: Option<u32>,
+    max_sats: Option<u64>,
+) -> Result<bool> {
+    let conn = pool.get()?;
+    let reserved: Option<i64> = conn
+        .query_row("SELECT 1 FROM faucet_fundings WHERE run_id = ?", params![run_id], |row| row.get(0))
+        .optional()?;
+    if reserved.is_some() {
+        return Ok(true);
+    }
+    
+    // Check and insert in one statement so concurrent runs can't both take the last slot
+    let inserted = conn.execute(
+        "INSERT INTO faucet_fundings (run_id, sats, funded_at)
+        SELECT ?1, ?2, ?3
+        WHERE (SELECT COUNT(*) FROM faucet_fundings WHERE funded_at >= ?4) < ?5
+        AND (SELECT COALESCE(SUM(sats), 0) FROM faucet_fundings WHERE funded_at >= ?4) + ?2 <= ?6",
+        params![
+            run_id,
+            sats as i64,
+            funded_at,
+            since,
+            max_wallets.map_or(i64::MAX, i64::from),
+            max_sats.map_or(i64::MAX, |cap| cap.min(i64::MAX as u64) as i64)
+        ],
+    )?;
+    
+    Ok(inserted == 1)
+}
+
+/// Give back a run's faucet reservation when the faucet didn't pay out
+pub fn release_faucet_funding(pool: &DbPool, run_id: &str) -> Result<()> {
+    let conn = pool.get()?;
+    conn.execute("DELETE FROM faucet_fundings WHERE run_id = ?", params![run_id])?;
+    Ok(())
+}
+
+/// Wallets funded and sats requested from the faucets since the RFC 3339 `since`
+pub fn get_faucet_usage_since(pool: &DbPool, since: &str) -> Result<(i64, i64)> {
+    let conn = pool.get()?;
+    let usage = conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(sats), 0) FROM faucet_fundings WHERE funded_at >= ?",
+        params![since],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    
+    Ok(usage)
+}
+
+/// Record how long a funding transaction took to confirm, keeping the latest `keep` per network
+pub fn save_confirmation_time(pool: &DbPool, network: &str, duration_ms: u64, keep: u32) -> Result<()> {
+    let conn = pool.get()?;
+    conn.execute(
+        "INSERT INTO confirmation_times (network, duration_ms, recorded_at) VALUES (?, ?, ?)",
+        params![network, duration_ms as i64, chrono::Utc::now().to_rfc3339()],
+    )?;
+    conn.execute(
+        "DELETE FROM confirmation_times WHERE network = ?1 AND id NOT IN (
+            SELECT id FROM confirmation_times WHERE network = ?1 ORDER BY id DESC LIMIT ?2
+        )",
+        params![network, keep],
+    )?;
+    
+    Ok(())
+}
+
+/// One background reachability check of a faucet
+#[derive(Debug, Serialize)]
+pub struct FaucetProbe {
+    /// "btc" or "lava_usd"
+    pub faucet: String,
+    /// "up", "degraded" on a 5xx, or "down" without a response
+    pub status: String,
+    pub http_status: Option<u16>,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+    pub checked_at: String,
+}
+
+/// Record a faucet probe, keeping the latest `keep` per faucet
+pub fn save_faucet_probe(pool: &DbPool, probe: &FaucetProbe, keep: u32) -> Result<()> {
+    let conn = pool.get()?;
+    conn.execute(
+        "INSERT INTO faucet_health (faucet, status, http_status, latency_ms, error, checked_at) VALUES (?, ?, ?, ?, ?, ?)",
+        params![
+            probe.faucet,
+            probe.status,
+            probe.http_status,
+            probe.latency_ms as i64,
+            probe.error,
+            probe.checked_at
+        ],
+    )?;
+    conn.execute(
+        "DELETE FROM faucet_health WHERE faucet = ?1 AND id NOT IN (
+            SELECT id FROM faucet_health WHERE faucet = ?1 ORDER BY id DESC LIMIT ?2
+        )",
+        params![probe.faucet, keep],
+    )?;
+    
+    Ok(())
+}
+
+/// Faucet probes, newest first
+pub fn get_faucet_probes(pool: &DbPool, limit: u32, offset: u32) -> Result<Vec<FaucetProbe>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare(
+        "SELECT faucet, status, http_status, latency_ms, error, checked_at FROM faucet_health
+        ORDER BY id DESC LIMIT ? OFFSET ?",
+    )?;
+    let probes = stmt
+        .query_map(params![limit, offset], |row| {
+            Ok(FaucetProbe {
+                faucet: row.get(0)?,
+                status: row.get(1)?,
+                http_status: row.get(2)?,
+                latency_ms: row.get::<_, i64>(3)?.max(0) as u64,
+                error: row.get(4)?,
+                checked_at: row.get(5)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    
+    Ok(probes)
+}
+
+/// Confirmed funding of a run, stored as soon as the faucet transaction confirms
+#[derive(Debug, Serialize)]
+pub struct FundingConfirmation {
+    pub txid: String,
+    pub confirmations: u32,
+    pub confirmed_at: String,
+}
+
+/// Record that a run's funding transaction confirmed
+pub fn save_funding_confirmation(pool: &DbPool, result_id: &str, confirmation: &FundingConfirmation) -> Result<()> {
+    let conn = pool.get()?;
+    conn.execute(
+        "INSERT INTO funding_confirmations (result_id, txid, confirmations, confirmed_at) VALUES (?, ?, ?, ?)
+        ON CONFLICT(result_id) DO UPDATE SET
+            txid = excluded.txid,
+            confirmations = excluded.confirmations,
+            confirmed_at = excluded.confirmed_at",
+        params![result_id, confirmation.txid, confirmation.confirmations, confirmation.confirmed_at],
+    )?;
+    
+    Ok(())
+}
+
+/// GET a run's stored funding confirmation
+pub fn get_funding_confirmation(pool: &DbPool, result_id: &str) -> Result<Option<FundingConfirmation>> {
+    let conn = pool.get()?;
+    let confirmation = conn
+        .query_row(
+            "SELECT txid, confirmations, confirmed_at FROM funding_confirmations WHERE result_id = ?",
+            params![result_id],
+            |row| {
+                Ok(FundingConfirmation {
+                    txid: row.get(0)?,
+                    confirmations: row.get(1)?,
+                    confirmed_at: row.get(2)?,
+                })
+            },
+        )
+        .optional()?;
+    
+    Ok(confirmation)
+}
+
+/// GET the most recent confirmation durations for a network
+pub fn get_recent_confirmation_times(pool: &DbPool, network: &str, limit: u32) -> Result<Vec<u64>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare(
+        "SELECT duration_ms FROM confirmation_times WHERE network = ? ORDER BY id DESC LIMIT ?",
+    )?;
+    let durations = stmt
+        .query_map(params![network, limit], |row| row.get::<_, i64>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    
+    Ok(durations.into_iter().map(|ms| ms.max(0) as u64).collect())
+}
+
+/// Find a return already broadcast for this wallet's contract (or run, when there's no contract)
+pub fn find_fund_return(
+    pool: &DbPool,
+    btc_address: &str,
+    loan_contract_id: Option<&str>,
+    run_id: &str,
+) -> Result<Option<String>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare(
+        "SELECT return_txid FROM fund_returns
+        WHERE btc_address = ?1 AND ((?2 IS NOT NULL AND loan_contract_id = ?2) OR run_id = ?3)
+        ORDER BY broadcast_at DESC LIMIT 1",
+    )?;
+    
+    let mut txid_iter = stmt.query_map(params![btc_address, loan_contract_id, run_id], |row| row.get(0))?;
+    
+    match txid_iter.next() {
+        Some(txid) => Ok(Some(txid?)),
+        None => Ok(None),
+    }
+}
+
+/// Write a consistent online copy of the database to the backup directory
+pub fn backup_database(pool: &DbPool, backup_dir: &str) -> Result<String> {
+    std::fs::create_dir_all(backup_dir)?;
+    
+    let file_name = format!("test_results-{}.db", chrono::Utc::now().format("%Y%m%dT%H%M%S%.3fZ"));
+    let backup_path = Path::new(backup_dir).join(file_name).to_string_lossy().to_string();
+    
+    let conn = pool.get()?;
+    conn.execute("VACUUM INTO ?", params![backup_path])?;
+    
+    info!("Database backed up to {}", backup_path);
+    Ok(backup_path)
+}
+
+/// A mutating API call recorded for auditing
+#[derive(Debug, Serialize)]
+pub struct AuditEntry {
+    pub timestamp: String,
+    /// Address of the connecting peer
+    pub client_ip: Option<String>,
+    /// Client a trusted proxy forwarded the request for, from its forwarding headers
+    pub forwarded_ip: Option<String>,
+    pub api_key_id: Option<String>,
+    pub method: String,
+    pub route: String,
+    pub status: u16,
+}
+
+/// Save an audit entry to data/test_results.db
+pub fn save_audit_entry(pool: &DbPool, entry: &AuditEntry) -> Result<()> {
+    let conn = pool.get()?;
+    conn.execute(
+        "INSERT INTO audit_log (timestamp, client_ip, forwarded_ip, api_key_id, method, route, status)
+        VALUES (?, ?, ?, ?, ?, ?, ?)",
+        params![
+            entry.timestamp,
+            entry.client_ip,
+            entry.forwarded_ip,
+            entry.api_key_id,
+            entry.method,
+            entry.route,
+            entry.status
+        ],
+    )?;
+    
+    Ok(())
+}
+
+/// GET a page of audit entries, newest first
+pub fn get_audit_entries(pool: &DbPool, limit: u32, offset: u32) -> Result<Vec<AuditEntry>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare(
+        "SELECT timestamp, client_ip, forwarded_ip, api_key_id, method, route, status
+        FROM audit_log ORDER BY id DESC LIMIT ? OFFSET ?",
+    )?;
+    
+    let entry_iter = stmt.query_map(params![limit, offset], |row| {
+        Ok(AuditEntry {
+            timestamp: row.get(0)?,
+            client_ip: row.get(1)?,
+            forwarded_ip: row.get(2)?,
+            api_key_id: row.get(3)?,
+            method: row.get(4)?,
+            route: row.get(5)?,
+            status: row.get(6)?,
         })
     })?;
     
-    let mut results = Vec::new();
-    for result in test_result_iter {
-        results.push(result?);
+    let mut entries = Vec::new();
+    for entry in entry_iter {
+        entries.push(entry?);
     }
     
-    Ok(results)
+    Ok(entries)
 }
 
-/// GET specific test by ID
-pub fn get_test_result_by_id(pool: &DbPool, id: &str) -> Result<Option<TestResult>> {
+/// A wallet the suite has used, with how often and when it was last used
+#[derive(Debug, Serialize)]
+pub struct WalletUsage {
+    pub btc_address: String,
+    pub lava_usd_pubkey: String,
+    pub runs: i64,
+    pub last_used: String,
+}
+
+/// GET distinct wallets from data/test_results.db, most recently used first
+pub fn get_wallets(pool: &DbPool, limit: u32, offset: u32) -> Result<Vec<WalletUsage>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare(
+        "SELECT btc_address, lava_usd_pubkey, COUNT(*), MAX(timestamp)
+        FROM test_results
+        WHERE btc_address != ''
+        GROUP BY btc_address, lava_usd_pubkey
+        ORDER BY MAX(seq) DESC LIMIT ? OFFSET ?",
+    )?;
+    
+    let wallet_iter = stmt.query_map(params![limit, offset], |row| {
+        Ok(WalletUsage {
+            btc_address: row.get(0)?,
+            lava_usd_pubkey: row.get(1)?,
+            runs: row.get(2)?,
+            last_used: row.get(3)?,
+        })
+    })?;
+    
+    let mut wallets = Vec::new();
+    for wallet in wallet_iter {
+        wallets.push(wallet?);
+    }
+    
+    Ok(wallets)
+}
+
+/// Aggregate figures across all stored runs
+#[derive(Debug, Serialize)]
+pub struct RunStats {
+    pub total_runs: i64,
+    pub successful_runs: i64,
+    pub failed_runs: i64,
+    pub total_sats_requested: i64,
+    /// Returned and net sats only add up runs whose returned amount is known
+    pub total_sats_returned: i64,
+    pub total_net_sats_consumed: i64,
+    /// Runs that returned funds without a known amount, left out of the two totals above
+    pub runs_with_unknown_return: i64,
+    /// Runs per funding source; runs that never got funded aren't counted
+    pub by_funding_source: BTreeMap<String, FundingSourceStats>,
+}
+
+/// Run counts for one funding source
+#[derive(Debug, Serialize)]
+pub struct FundingSourceStats {
+    pub runs: i64,
+    pub successful_runs: i64,
+}
+
+/// GET aggregate stats from data/test_results.db
+pub fn get_run_stats(pool: &DbPool) -> Result<RunStats> {
+    let conn = pool.get()?;
+    let mut stats = conn.query_row(
+        "SELECT
+            COUNT(*),
+            COALESCE(SUM(CASE WHEN status = 'success' THEN 1 ELSE 0 END), 0),
+            COALESCE(SUM(CASE WHEN status = 'failed' THEN 1 ELSE 0 END), 0),
+            COALESCE(SUM(sats_requested), 0),
+            COALESCE(SUM(CASE WHEN sats_returned_known = 1 THEN sats_returned END), 0),
+            COALESCE(SUM(CASE WHEN sats_returned_known = 1 THEN net_sats_consumed END), 0),
+            COALESCE(SUM(CASE WHEN sats_returned_known = 1 THEN 0 ELSE 1 END), 0)
+        FROM test_results",
+        [],
+        |row| {
+            Ok(RunStats {
+                total_runs: row.get(0)?,
+                successful_runs: row.get(1)?,
+                failed_runs: row.get(2)?,
+                total_sats_requested: row.get(3)?,
+                total_sats_returned: row.get(4)?,
+                total_net_sats_consumed: row.get(5)?,
+                runs_with_unknown_return: row.get(6)?,
+                by_funding_source: BTreeMap::new(),
+            })
+        },
+    )?;
+    
+    let mut stmt = conn.prepare(
+        "SELECT funding_source, COUNT(*), COALESCE(SUM(CASE WHEN status = 'success' THEN 1 ELSE 0 END), 0)
+        FROM test_results WHERE funding_source IS NOT NULL GROUP BY funding_source",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, FundingSourceStats { runs: row.get(1)?, successful_runs: row.get(2)? }))
+    })?;
+    for row in rows {
+        let (source, counts) = row?;
+        stats.by_funding_source.insert(source, counts);
+    }
+    
+    Ok(stats)
+}
+
+/// Count the most recent `window` runs and how many of them succeeded
+pub fn get_recent_success_counts(pool: &DbPool, window: u32) -> Result<(i64, i64)> {
+    let conn = pool.get()?;
+    let counts = conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(CASE WHEN status = 'success' THEN 1 ELSE 0 END), 0)
+        FROM (SELECT status FROM test_results ORDER BY seq DESC LIMIT ?1)",
+        params![window],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    
+    Ok(counts)
+}
+
+/// Archive results older than the given RFC 3339 cutoffs: successes before `success_before`,
+/// failures before `failed_before`. A None cutoff leaves that status alone. Runs that
+/// preserved their wallet are kept so the wallet stays findable.
+pub fn archive_old_results(pool: &DbPool, success_before: Option<&str>, failed_before: Option<&str>) -> Result<usize> {
+    let conn = pool.get()?;
+    let mut archived = 0;
+    
+    if let Some(cutoff) = success_before {
+        archived += conn.execute(
+            "UPDATE test_results SET archived = 1
+            WHERE archived = 0 AND preserved_wallet = 0 AND status = 'success' AND timestamp < ?",
+            params![cutoff],
+        )?;
+    }
+    if let Some(cutoff) = failed_before {
+        archived += conn.execute(
+            "UPDATE test_results SET archived = 1
+            WHERE archived = 0 AND preserved_wallet = 0 AND status = 'failed' AND timestamp < ?",
+            params![cutoff],
+        )?;
+    }
+    
+    Ok(archived)
+}
+
+/// Fail runs left queued or running by a previous process; their queue and tasks died with it
+pub fn fail_stale_runs(pool: &DbPool) -> Result<usize> {
+    let conn = pool.get()?;
+    let updated = conn.execute(
+        "UPDATE test_results SET
+        error_message = CASE status
+            WHEN 'queued' THEN 'Server restarted before the queued run started'
+            ELSE 'Server restarted before the run finished'
+        END,
+        status = 'failed'
+        WHERE status IN ('queued', 'running')",
+        [],
+    )?;
+    
+    Ok(updated)
+}
+
+/// Mark a queued run cancelled. False when it had already left the queued state.
+pub fn cancel_queued_result(pool: &DbPool, id: &str) -> Result<bool> {
     let conn = pool.get()?;
-    let mut stmt = conn.prepare("SELECT * FROM test_results WHERE id = ?")?;
+    let updated = conn.execute(
+        "UPDATE test_results SET status = 'cancelled',
+        error_message = 'Cancelled while queued'
+        WHERE id = ? AND status = 'queued'",
+        params![id],
+    )?;
+    
+    Ok(updated > 0)
+}
+
+fn gzip(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+fn gunzip(data: &[u8]) -> Result<String> {
+    let mut text = String::new();
+    GzDecoder::new(data).read_to_string(&mut text)?;
+    Ok(text)
+}
+
+/// Map a test_results row to a TestResult. Columns are read by name, and ones added
+/// after the initial schema default when missing, so a database from an older build
+/// can be read before its migrations have run.
+fn row_to_test_result(row: &Row) -> rusqlite::Result<TestResult> {
+    let btc_faucet_response_str: String = row.get("btc_faucet_response")?;
+    let lava_usd_faucet_response_str: String = row.get("lava_usd_faucet_response")?;
+    let details_str = if get_or_default::<i64>(row, "details_compressed")? != 0 {
+        let bytes: Option<Vec<u8>> = row.get("details")?;
+        bytes.and_then(|bytes| gunzip(&bytes).ok())
+    } else {
+        row.get("details")?
+    };
+    
+    let btc_faucet_response: crate::FaucetResponse = serde_json::from_str(&btc_faucet_response_str)
+        .unwrap_or_else(|_| crate::FaucetResponse::default());
+    
+    let lava_usd_faucet_response: crate::FaucetResponse = serde_json::from_str(&lava_usd_faucet_response_str)
+        .unwrap_or_else(|_| crate::FaucetResponse::default());
     
-    let mut test_result_iter = stmt.query_map(params![id], |row| {
-        let btc_faucet_response_str: String = row.get(6)?;
-        let lava_usd_faucet_response_str: String = row.get(7)?;
-        let details_str: Option<String> = row.get(11)?;
+    let details = details_str.and_then(|s| serde_json::from_str(&s).ok());
+    
+    let steps_str: String = get_or_default(row, "steps")?;
+    let steps = serde_json::from_str(&steps_str).unwrap_or_default();
+    
+    let loan_terms = match (
+        get_or_default::<Option<i64>>(row, "loan_amount")?,
+        get_or_default(row, "loan_duration_days")?,
+        get_or_default(row, "ltv_ratio_bp")?,
+    ) {
+        (Some(loan_amount), Some(loan_duration_days), Some(ltv_ratio_bp)) => Some(LoanTerms {
+            loan_amount: loan_amount as u64,
+            loan_duration_days,
+            ltv_ratio_bp,
+        }),
+        _ => None,
+    };
+    
+    let returned_funds = row.get::<_, i64>("returned_funds")? != 0;
+    let sats_returned_known = get_or_default::<Option<i64>>(row, "sats_returned_known")?.map_or(!returned_funds, |known| known != 0);
+    
+    Ok(TestResult {
+        id: row.get("id")?,
+        schema_version: get_or_default::<Option<u32>>(row, "schema_version")?.unwrap_or(1),
+        status: row.get("status")?,
+        mnemonic: Mnemonic::new(row.get("mnemonic")?),
+        btc_address: row.get("btc_address")?,
+        lava_usd_pubkey: row.get("lava_usd_pubkey")?,
+        btc_faucet_response,
+        lava_usd_faucet_response,
+        loan_contract_id: row.get("loan_contract_id")?,
+        loan_closed: row.get::<_, i64>("loan_closed")? != 0,
+        repayment_txid: row.get("repayment_txid")?,
+        repayment_confirmed: get_or_default::<i64>(row, "repayment_confirmed")? != 0,
+        details,
+        error_message: row.get("error_message")?,
+        returned_funds,
+        sats_requested: get_or_default::<i64>(row, "sats_requested")? as u64,
+        sats_returned: sats_returned_known.then_some(get_or_default::<i64>(row, "sats_returned")? as u64),
+        net_sats_consumed: sats_returned_known.then_some(get_or_default(row, "net_sats_consumed")?),
+        steps,
+        return_txid: get_or_default(row, "return_txid")?,
+        faucet_accepted: get_or_default::<i64>(row, "faucet_accepted")? != 0,
+        funds_confirmed: get_or_default::<i64>(row, "funds_confirmed")? != 0,
+        details_truncated: get_or_default::<i64>(row, "details_truncated")? != 0,
+        preset: get_or_default(row, "preset")?,
+        run_request: get_or_default::<Option<String>>(row, "run_request")?.and_then(|s| serde_json::from_str(&s).ok()),
+        parent_id: get_or_default(row, "parent_id")?,
+        canary: get_or_default::<i64>(row, "canary")? != 0,
+        preserved_wallet: get_or_default::<i64>(row, "preserved_wallet")? != 0,
+        contract_id_collision: get_or_default::<i64>(row, "contract_id_collision")? != 0,
+        archived: get_or_default::<i64>(row, "archived")? != 0,
+        loan_terms,
+        cli_exit_code: get_or_default(row, "cli_exit_code")?,
+        funding_source: get_or_default(row, "funding_source")?,
+        queue_position: None,
+        current_step: None,
+        cli_output: Vec::new(),
+    })
+}
+
+/// Read a column added after the initial schema, defaulting when the table predates it
+fn get_or_default<T: FromSql + Default>(row: &Row, column: &str) -> rusqlite::Result<T> {
+    match row.get(column) {
+        Err(rusqlite::Error::InvalidColumnName(_)) => Ok(T::default()),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn test_pool() -> DbPool {
+        let path = std::env::temp_dir().join(format!("test_results_{}.db", uuid::Uuid::new_v4()));
+        init_pool(path.to_str().unwrap(), 5000, DbCorruptionPolicy::Fail, None).unwrap()
+    }
+    
+    #[test]
+    fn run_stats_sum_net_sats_across_runs() {
+        let pool = test_pool();
         
-        let btc_faucet_response: crate::FaucetResponse = serde_json::from_str(&btc_faucet_response_str)
-            .unwrap_or_else(|_| crate::FaucetResponse::default());
+        let mut first = TestResult::new(uuid::Uuid::new_v4().to_string(), &Mnemonic::default(), "tb1qfirst", "pk1");
+        first.status = "success".to_string();
+        first.sats_requested = 50000;
+        first.sats_returned = Some(20000);
+        first.update_net_sats();
+        save_test_result(&pool, &first).unwrap();
+        assert_eq!(first.net_sats_consumed, Some(30000));
         
-        let lava_usd_faucet_response: crate::FaucetResponse = serde_json::from_str(&lava_usd_faucet_response_str)
-            .unwrap_or_else(|_| crate::FaucetResponse::default());
+        let mut second = TestResult::new(uuid::Uuid::new_v4().to_string(), &Mnemonic::default(), "tb1qsecond", "pk2");
+        second.status = "failed".to_string();
+        second.sats_requested = 50000;
+        second.update_net_sats();
+        save_test_result(&pool, &second).unwrap();
         
-        let details = details_str.and_then(|s| serde_json::from_str(&s).ok());
+        // Returned funds without reporting how much: unknown, not all consumed
+        let mut third = TestResult::new(uuid::Uuid::new_v4().to_string(), &Mnemonic::default(), "tb1qthird", "pk3");
+        third.status = "success".to_string();
+        third.sats_requested = 50000;
+        third.record_fund_return("txid".to_string());
+        save_test_result(&pool, &third).unwrap();
+        let stored = get_test_result_by_id(&pool, &third.id).unwrap().unwrap();
+        assert_eq!((stored.sats_returned, stored.net_sats_consumed), (None, None));
         
-        Ok(TestResult {
-            id: row.get(0)?,
-            status: row.get(2)?,
-            mnemonic: row.get(3)?,
-            btc_address: row.get(4)?,
-            lava_usd_pubkey: row.get(5)?,
-            btc_faucet_response,
-            lava_usd_faucet_response,
-            loan_contract_id: row.get(8)?,
-            loan_closed: row.get::<_, i64>(9)? != 0,
-            repayment_txid: row.get(10)?,
-            details,
-            error_message: row.get(12)?,
-            returned_funds: row.get::<_, i64>(13)? != 0,
-        })
-    })?;
+        let stats = get_run_stats(&pool).unwrap();
+        assert_eq!(stats.total_runs, 3);
+        assert_eq!(stats.successful_runs, 2);
+        assert_eq!(stats.failed_runs, 1);
+        assert_eq!(stats.total_sats_requested, 150000);
+        assert_eq!(stats.total_sats_returned, 20000);
+        assert_eq!(stats.total_net_sats_consumed, 80000);
+        assert_eq!(stats.runs_with_unknown_return, 1);
+    }
     
-    match test_result_iter.next() {
-        Some(result) => Ok(Some(result?)),
-        None => Ok(None),
+    #[test]
+    fn backup_is_a_readable_copy_with_the_same_rows() {
+        let pool = test_pool();
+        for address in ["tb1qone", "tb1qtwo"] {
+            save_test_result(&pool, &TestResult::new(uuid::Uuid::new_v4().to_string(), &Mnemonic::default(), address, "pk")).unwrap();
+        }
+        
+        // Hold a connection open so the copy is taken while the DB is in use
+        let _busy = pool.get().unwrap();
+        let backup_dir = std::env::temp_dir().join(format!("backups_{}", uuid::Uuid::new_v4()));
+        let path = backup_database(&pool, backup_dir.to_str().unwrap()).unwrap();
+        
+        let copy = Connection::open(&path).unwrap();
+        let ids = |conn: &Connection| -> Vec<String> {
+            let mut stmt = conn.prepare("SELECT id FROM test_results ORDER BY id").unwrap();
+            let rows = stmt.query_map([], |row| row.get(0)).unwrap();
+            rows.map(|r| r.unwrap()).collect()
+        };
+        assert_eq!(ids(&copy), ids(&pool.get().unwrap()));
+        assert_eq!(ids(&copy).len(), 2);
     }
-} 
\ No newline at end of file
+    
+    #[test]
+    fn wallets_are_grouped_with_run_counts() {
+        let pool = test_pool();
+        for (address, pubkey) in [("tb1qa", "pka"), ("tb1qb", "pkb"), ("tb1qa", "pka"), ("tb1qa", "pka")] {
+            let result = TestResult::new(uuid::Uuid::new_v4().to_string(), &Mnemonic::default(), address, pubkey);
+            save_test_result(&pool, &result).unwrap();
+        }
+        
+        let wallets = get_wallets(&pool, 50, 0).unwrap();
+        assert_eq!(wallets.len(), 2);
+        // Most recently used first
+        assert_eq!((wallets[0].btc_address.as_str(), wallets[0].lava_usd_pubkey.as_str(), wallets[0].runs), ("tb1qa", "pka", 3));
+        assert_eq!((wallets[1].btc_address.as_str(), wallets[1].runs), ("tb1qb", 1));
+        assert!(wallets[0].last_used > wallets[1].last_used);
+        
+        let page = get_wallets(&pool, 1, 1).unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].btc_address, "tb1qb");
+    }
+    
+    #[actix_web::test]
+    async fn reads_succeed_during_a_burst_of_writes() {
+        let pool = test_pool();
+        let writers: Vec<_> = (0..8)
+            .map(|_| {
+                let pool = pool.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..25 {
+                        let result = TestResult::new(uuid::Uuid::new_v4().to_string(), &Mnemonic::default(), "tb1q", "pk");
+                        save_test_result(&pool, &result).unwrap();
+                    }
+                })
+            })
+            .collect();
+        
+        let mut reads = 0;
+        while writers.iter().any(|w| !w.is_finished()) {
+            with_busy_retry(|| get_test_results(&pool, &ResultFilter::default())).await.unwrap();
+            reads += 1;
+        }
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        
+        assert!(reads > 0);
+        assert_eq!(get_test_results(&pool, &ResultFilter::default()).unwrap().len(), 200);
+    }
+    
+    #[test]
+    fn only_old_successes_are_archived_under_the_policy() {
+        let pool = test_pool();
+        let now = chrono::Utc::now();
+        let hours_ago = |hours: i64| (now - chrono::Duration::hours(hours)).to_rfc3339();
+        for (id, status, age_hours) in [("old-ok", "success", 48), ("new-ok", "success", 1), ("old-fail", "failed", 48)] {
+            let mut result = TestResult::new(id.to_string(), &Mnemonic::default(), "tb1q", "pk");
+            result.status = status.to_string();
+            save_test_result(&pool, &result).unwrap();
+            pool.get()
+                .unwrap()
+                .execute("UPDATE test_results SET timestamp = ? WHERE id = ?", params![hours_ago(age_hours), id])
+                .unwrap();
+        }
+        
+        // Successes archived after a day, failures kept for a week
+        assert_eq!(archive_old_results(&pool, Some(&hours_ago(24)), Some(&hours_ago(168))).unwrap(), 1);
+        
+        // Listed by insertion order, newest first, not by the backdated timestamps
+        let listed: Vec<String> = get_test_results(&pool, &ResultFilter::default()).unwrap().into_iter().map(|r| r.id).collect();
+        assert_eq!(listed, ["old-fail", "new-ok"]);
+        assert_eq!(get_test_results(&pool, &ResultFilter { include_archived: true, ..Default::default() }).unwrap().len(), 3);
+        assert!(get_test_result_by_id(&pool, "old-ok").unwrap().unwrap().archived);
+    }
+    
+    #[test]
+    fn corrupt_database_is_quarantined_or_refused() {
+        let path = std::env::temp_dir().join(format!("test_results_{}.db", uuid::Uuid::new_v4()));
+        let db_path = path.to_str().unwrap();
+        std::fs::write(db_path, b"definitely not a sqlite database, just some bytes").unwrap();
+        
+        assert!(init_pool(db_path, 5000, DbCorruptionPolicy::Fail, None).is_err());
+        assert!(path.exists());
+        
+        let pool = init_pool(db_path, 5000, DbCorruptionPolicy::Quarantine, None).unwrap();
+        assert!(get_test_results(&pool, &ResultFilter { include_archived: true, ..Default::default() }).unwrap().is_empty());
+        
+        let prefix = format!("{}.corrupt-", path.file_name().unwrap().to_str().unwrap());
+        let quarantined = std::fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+            .count();
+        assert_eq!(quarantined, 1);
+    }
+    
+    #[test]
+    fn date_range_returns_only_runs_inside_it() {
+        let pool = test_pool();
+        for (day, status) in [("2024-02-28", "success"), ("2024-03-01", "success"), ("2024-03-02", "failed"), ("2024-03-05", "success")] {
+            let mut result = TestResult::new(uuid::Uuid::new_v4().to_string(), &Mnemonic::default(), day, "pk");
+            result.status = status.to_string();
+            save_test_result(&pool, &result).unwrap();
+            pool.get()
+                .unwrap()
+                .execute("UPDATE test_results SET timestamp = ? WHERE id = ?", params![format!("{}T12:00:00+00:00", day), result.id])
+                .unwrap();
+        }
+        
+        let range = || ResultFilter {
+            from: Some("2024-03-01T00:00:00+00:00".to_string()),
+            to: Some("2024-03-02T23:59:59+00:00".to_string()),
+            ..Default::default()
+        };
+        let days: Vec<String> = get_test_results(&pool, &range()).unwrap().into_iter().map(|r| r.btc_address).collect();
+        assert_eq!(days, ["2024-03-02", "2024-03-01"]);
+        
+        let successes = ResultFilter { status: Some("success".to_string()), ..range() };
+        assert_eq!(get_test_results(&pool, &successes).unwrap().len(), 1);
+        
+        let second_page = ResultFilter { limit: Some(1), offset: 1, ..range() };
+        let page = get_test_results(&pool, &second_page).unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].btc_address, "2024-03-01");
+    }
+    
+    #[test]
+    fn loan_terms_are_stored_filtered_and_backfilled() {
+        let pool = test_pool();
+        for (id, ltv_ratio_bp) in [("low", 5000), ("high", 7000)] {
+            let mut result = TestResult::new(id.to_string(), &Mnemonic::default(), "tb1q", "pk");
+            result.loan_terms = Some(LoanTerms { loan_amount: 2, loan_duration_days: 4, ltv_ratio_bp });
+            save_test_result(&pool, &result).unwrap();
+        }
+        
+        let stored = get_test_result_by_id(&pool, "high").unwrap().unwrap();
+        assert_eq!(stored.loan_terms, Some(LoanTerms { loan_amount: 2, loan_duration_days: 4, ltv_ratio_bp: 7000 }));
+        let risky = get_test_results(&pool, &ResultFilter { min_ltv_bp: Some(6000), ..Default::default() }).unwrap();
+        assert_eq!(risky.iter().map(|r| r.id.as_str()).collect::<Vec<_>>(), ["high"]);
+        
+        // A row from before the columns existed, with the terms only in its details
+        let mut legacy = TestResult::new("legacy".to_string(), &Mnemonic::default(), "tb1q", "pk");
+        legacy.record_detail("loan_terms", serde_json::json!({ "loan_amount": 3, "loan_duration_days": 7, "ltv_ratio_bp": 6500 }));
+        save_test_result(&pool, &legacy).unwrap();
+        backfill_loan_terms(&pool.get().unwrap()).unwrap();
+        let legacy = get_test_result_by_id(&pool, "legacy").unwrap().unwrap();
+        assert_eq!(legacy.loan_terms, Some(LoanTerms { loan_amount: 3, loan_duration_days: 7, ltv_ratio_bp: 6500 }));
+    }
+    
+    #[test]
+    fn rows_from_an_older_schema_read_with_defaults() {
+        let path = std::env::temp_dir().join(format!("test_results_{}.db", uuid::Uuid::new_v4()));
+        let conn = Connection::open(&path).unwrap();
+        // The initial schema, before steps, preset, archived, loan terms etc. were added
+        conn.execute_batch(
+            "CREATE TABLE test_results (
+                id TEXT PRIMARY KEY,
+                timestamp TEXT NOT NULL,
+                status TEXT NOT NULL,
+                mnemonic TEXT NOT NULL,
+                btc_address TEXT NOT NULL,
+                lava_usd_pubkey TEXT NOT NULL,
+                btc_faucet_response TEXT NOT NULL,
+                lava_usd_faucet_response TEXT NOT NULL,
+                loan_contract_id TEXT,
+                loan_closed INTEGER NOT NULL,
+                repayment_txid TEXT,
+                details TEXT,
+                error_message TEXT,
+                returned_funds INTEGER NOT NULL
+            );
+            INSERT INTO test_results VALUES (
+                'old', '2024-03-01T12:00:00+00:00', 'success', 'words', 'tb1qold', 'pk',
+                '{}', '{}', 'contract', 1, 'repay', NULL, NULL, 1
+            );",
+        )
+        .unwrap();
+        
+        let old = conn.query_row("SELECT * FROM test_results", [], row_to_test_result).unwrap();
+        assert_eq!((old.id.as_str(), old.status.as_str(), old.btc_address.as_str()), ("old", "success", "tb1qold"));
+        assert!(old.loan_closed && old.returned_funds);
+        assert!(old.steps.is_empty());
+        assert_eq!((old.preset, old.loan_terms, old.archived), (None, None, false));
+        assert_eq!(old.schema_version, 1);
+        drop(conn);
+        
+        // And the migrated database still reads the same row
+        let pool = init_pool(path.to_str().unwrap(), 5000, DbCorruptionPolicy::Fail, None).unwrap();
+        let migrated = get_test_result_by_id(&pool, "old").unwrap().unwrap();
+        assert_eq!(migrated.repayment_txid.as_deref(), Some("repay"));
+        assert_eq!(migrated.schema_version, 1);
+        
+        // Writing a result stamps it with the current version
+        save_test_result(&pool, &migrated).unwrap();
+        assert_eq!(get_test_result_by_id(&pool, "old").unwrap().unwrap().schema_version, RESULT_SCHEMA_VERSION);
+        let fresh = TestResult::new("new".to_string(), &Mnemonic::default(), "tb1qnew", "pk");
+        save_test_result(&pool, &fresh).unwrap();
+        let stored = get_test_result_by_id(&pool, "new").unwrap().unwrap();
+        assert_eq!(stored.schema_version, RESULT_SCHEMA_VERSION);
+        assert_eq!(serde_json::to_value(&stored).unwrap()["schema_version"], RESULT_SCHEMA_VERSION);
+    }
+    
+    #[test]
+    fn results_list_in_insertion_order_when_the_clock_jumps_back() {
+        let pool = test_pool();
+        // The clock moved back an hour between each save
+        for (id, timestamp) in [("first", "2024-03-01T12:00:00+00:00"), ("second", "2024-03-01T11:00:00+00:00"), ("third", "2024-03-01T10:00:00+00:00")] {
+            let result = TestResult::new(id.to_string(), &Mnemonic::default(), "tb1q", "pk");
+            save_test_result(&pool, &result).unwrap();
+            pool.get()
+                .unwrap()
+                .execute("UPDATE test_results SET timestamp = ? WHERE id = ?", params![timestamp, id])
+                .unwrap();
+        }
+        // Saving again (e.g. a refresh) doesn't move a result
+        let first = get_test_result_by_id(&pool, "first").unwrap().unwrap();
+        save_test_result(&pool, &first).unwrap();
+        
+        let ids: Vec<String> = get_test_results(&pool, &ResultFilter::default()).unwrap().into_iter().map(|r| r.id).collect();
+        assert_eq!(ids, ["third", "second", "first"]);
+    }
+    
+    #[test]
+    fn compressed_details_round_trip() {
+        let pool = test_pool();
+        let mut result = TestResult::new("compressed".to_string(), &Mnemonic::default(), "tb1q", "pk");
+        let log: Vec<String> = (0..2000).map(|i| format!("line {} of the CLI output", i)).collect();
+        result.details = Some(serde_json::json!({ "cli_output": log, "Closed": { "repaid": true } }));
+        save_test_result_with(&pool, &result, true).unwrap();
+        
+        let (compressed, stored_len): (i64, i64) = pool
+            .get()
+            .unwrap()
+            .query_row("SELECT details_compressed, length(details) FROM test_results WHERE id = 'compressed'", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(compressed, 1);
+        let json_len = serde_json::to_string(&result.details).unwrap().len() as i64;
+        assert!(stored_len < json_len / 5, "{} bytes stored for {} bytes of JSON", stored_len, json_len);
+        
+        let read = get_test_result_by_id(&pool, "compressed").unwrap().unwrap();
+        assert_eq!(read.details, result.details);
+        
+        // Saving again uncompressed clears the flag
+        save_test_result_with(&pool, &read, false).unwrap();
+        assert_eq!(get_test_result_by_id(&pool, "compressed").unwrap().unwrap().details, result.details);
+    }
+    
+    #[test]
+    fn warmup_leaves_the_requested_connections_idle() {
+        let path = std::env::temp_dir().join(format!("test_results_{}.db", uuid::Uuid::new_v4()));
+        let pool = init_pool(path.to_str().unwrap(), 5000, DbCorruptionPolicy::Fail, Some(4)).unwrap();
+        // Checking out the schema connection may have the pool open one more to stay at min_idle
+        let state = pool.state();
+        assert!(state.idle_connections >= 4, "{:?}", state);
+        assert_eq!(state.connections, state.idle_connections);
+        
+        // Without warmup only the connection that created the schema is open
+        let path = std::env::temp_dir().join(format!("test_results_{}.db", uuid::Uuid::new_v4()));
+        let lazy = init_pool(path.to_str().unwrap(), 5000, DbCorruptionPolicy::Fail, None).unwrap();
+        assert_eq!(lazy.state().idle_connections, 1);
+    }
+}
@@ -0,0 +1,210 @@
+use serde::Serialize;
+
+/// Why a run failed, recorded as `details.error_kind` on the result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestError {
+    NoConnectivity,
+    AddressReused,
+    WalletCooldown,
+    DailyBudgetExceeded,
+    StaleFaucetTxid,
+    PrefundFailed,
+    BtcFaucetFailed,
+    LavaUsdFaucetFailed,
+    SetupCliFailed,
+    CliDownloadTruncated,
+    LavaUsdNotFinalized,
+    LoanCreationFailed,
+    LoanClosedBeforeRepay,
+    RepaymentFailed,
+    ContractDetailsFailed,
+    LoanNotClosed,
+    FundReturnFailed,
+    WalletFlowsFailed,
+}
+
+/// One catalog entry as served by GET /errors
+#[derive(Debug, Serialize)]
+pub struct ErrorInfo {
+    pub error_kind: &'static str,
+    pub description: &'static str,
+    pub remediation: &'static str,
+}
+
+impl TestError {
+    /// Every variant, in catalog order
+    pub const ALL: &'static [TestError] = &[
+        TestError::NoConnectivity,
+        TestError::AddressReused,
+        TestError::WalletCooldown,
+        TestError::DailyBudgetExceeded,
+        TestError::StaleFaucetTxid,
+        TestError::PrefundFailed,
+        TestError::BtcFaucetFailed,
+        TestError::LavaUsdFaucetFailed,
+        TestError::SetupCliFailed,
+        TestError::CliDownloadTruncated,
+        TestError::LavaUsdNotFinalized,
+        TestError::LoanCreationFailed,
+        TestError::LoanClosedBeforeRepay,
+        TestError::RepaymentFailed,
+        TestError::ContractDetailsFailed,
+        TestError::LoanNotClosed,
+        TestError::FundReturnFailed,
+        TestError::WalletFlowsFailed,
+    ];
+
+    /// Catalog entry for this error. The match keeps the catalog complete: a new
+    /// variant doesn't compile until it's described here.
+    pub fn info(self) -> ErrorInfo {
+        let (error_kind, description, remediation) = match self {
+            TestError::NoConnectivity => (
+                "no_connectivity",
+                "The pre-flight request to CONNECTIVITY_PROBE_URL failed, so no step was attempted.",
+                "Check the server's network access and DNS, and that CONNECTIVITY_PROBE_URL is reachable.",
+            ),
+            TestError::AddressReused => (
+                "address_reused",
+                "The run's BTC address appeared in recent results and ADDRESS_REUSE_POLICY is error.",
+                "Check wallet generation or MNEMONIC_FILE; a fixed mnemonic reuses its address every run.",
+            ),
+            TestError::WalletCooldown => (
+                "wallet_cooldown",
+                "The run's BTC address was used by another run less than WALLET_COOLDOWN_SECS ago, so no step was attempted.",
+                "Retry after the time given in the error message, or lower WALLET_COOLDOWN_SECS.",
+            ),
+            TestError::DailyBudgetExceeded => (
+                "daily_budget_exceeded",
+                "DAILY_WALLET_CAP or DAILY_SATS_CAP was already used up for the current budget day, so the faucets weren't asked for funds.",
+                "Wait for the reset at DAILY_BUDGET_RESET_HOUR (UTC), shown in /stats as daily_budget.resets_at, or raise the cap.",
+            ),
+            TestError::StaleFaucetTxid => (
+                "stale_faucet_txid",
+                "The BTC faucet returned a txid recorded by an earlier run or older than FAUCET_TXID_MAX_AGE_SECS.",
+                "The faucet is replaying old responses; check it directly or raise FAUCET_TXID_MAX_AGE_SECS.",
+            ),
+            TestError::PrefundFailed => (
+                "prefund_failed",
+                "Funding the run's wallet from the PREFUND_MNEMONIC_FILE wallet failed.",
+                "Check that PREFUND_MNEMONIC_FILE holds a valid mnemonic and that the wallet still has funds.",
+            ),
+            TestError::BtcFaucetFailed => (
+                "btc_faucet_failed",
+                "The BTC faucet request failed after its retries.",
+                "Check the faucet's status and rate limits in /stats faucet_health, then retry later.",
+            ),
+            TestError::LavaUsdFaucetFailed => (
+                "lava_usd_faucet_failed",
+                "The LavaUSD faucet request failed after its retries.",
+                "Check the faucet's status and rate limits in /stats faucet_health, then retry later.",
+            ),
+            TestError::SetupCliFailed => (
+                "setup_cli_failed",
+                "Downloading or preparing the borrower CLI failed.",
+                "Check access to the CLI download URL, disk space under RUN_DIR_BASE and the setup step's error.",
+            ),
+            TestError::CliDownloadTruncated => (
+                "cli_download_truncated",
+                "The CLI download succeeded but its body was shorter than its Content-Length or CLI_MIN_SIZE_BYTES, so it wasn't run.",
+                "Usually a CDN hiccup: rerun. If it keeps happening check the download URL, or lower CLI_MIN_SIZE_BYTES if the CLI really is that small.",
+            ),
+            TestError::LavaUsdNotFinalized => (
+                "lava_usd_not_finalized",
+                "The LavaUSD transfer failed on Solana or didn't finalize within LAVA_USD_CONFIRMATION_TIMEOUT_MS.",
+                "Check details.lava_usd_confirmation and SOLANA_RPC_URL; raise the timeout if the network is slow.",
+            ),
+            TestError::LoanCreationFailed => (
+                "loan_creation_failed",
+                "The borrower CLI couldn't create the loan.",
+                "Check the create_loan step error and that the wallet was funded (funds_confirmed).",
+            ),
+            TestError::LoanClosedBeforeRepay => (
+                "loan_closed_before_repay",
+                "The loan was already closed (e.g. liquidated) before the repay step and PRE_CLOSED_LOAN_POLICY is fail.",
+                "Check details.pre_closed_loan for how it closed; a short loan duration or price move can trigger it.",
+            ),
+            TestError::RepaymentFailed => (
+                "repayment_failed",
+                "The borrower CLI couldn't repay the loan.",
+                "Check the repay_loan step error and the LavaUSD balance of the wallet.",
+            ),
+            TestError::ContractDetailsFailed => (
+                "contract_details_failed",
+                "Fetching the loan contract's details failed.",
+                "Retry with POST /results/{id}/refresh once the CLI or backend is reachable.",
+            ),
+            TestError::LoanNotClosed => (
+                "loan_not_closed",
+                "Every step ran but the loan wasn't reported closed or has no repayment txid.",
+                "Inspect the contract with POST /results/{id}/refresh; the backend may still be settling.",
+            ),
+            TestError::FundReturnFailed => (
+                "fund_return_failed",
+                "The loan closed but returning the faucet funds failed, and STRICT_FUND_RETURN is true.",
+                "Check the return address and the wallet's balance; the funds are still in the test wallet.",
+            ),
+            TestError::WalletFlowsFailed => (
+                "wallet_flows_failed",
+                "At least one wallet flow of a multi-wallet run failed.",
+                "See details.wallets for each flow's error_kind and error message.",
+            ),
+        };
+        
+        ErrorInfo {
+            error_kind,
+            description,
+            remediation,
+        }
+    }
+}
+
+/// The full catalog served by GET /errors
+pub fn catalog() -> Vec<ErrorInfo> {
+    TestError::ALL.iter().map(|error| error.info()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn catalog_entries_are_unique_and_described() {
+        let mut kinds = HashSet::new();
+        for info in catalog() {
+            assert!(kinds.insert(info.error_kind), "duplicate error_kind {}", info.error_kind);
+            assert!(info.error_kind.chars().all(|c| c.is_ascii_lowercase() || c == '_'), "{} isn't snake_case", info.error_kind);
+            assert!(!info.description.is_empty() && !info.remediation.is_empty(), "{} is missing text", info.error_kind);
+        }
+    }
+
+    #[test]
+    fn all_lists_every_variant_in_order() {
+        // No wildcard arm: a new variant doesn't compile here until it gets a position, and
+        // then fails below until it's in ALL
+        let position = |error: TestError| match error {
+            TestError::NoConnectivity => 0,
+            TestError::AddressReused => 1,
+            TestError::WalletCooldown => 2,
+            TestError::DailyBudgetExceeded => 3,
+            TestError::StaleFaucetTxid => 4,
+            TestError::PrefundFailed => 5,
+            TestError::BtcFaucetFailed => 6,
+            TestError::LavaUsdFaucetFailed => 7,
+            TestError::SetupCliFailed => 8,
+            TestError::CliDownloadTruncated => 9,
+            TestError::LavaUsdNotFinalized => 10,
+            TestError::LoanCreationFailed => 11,
+            TestError::LoanClosedBeforeRepay => 12,
+            TestError::RepaymentFailed => 13,
+            TestError::ContractDetailsFailed => 14,
+            TestError::LoanNotClosed => 15,
+            TestError::FundReturnFailed => 16,
+            TestError::WalletFlowsFailed => 17,
+        };
+        for (index, error) in TestError::ALL.iter().enumerate() {
+            assert_eq!(position(*error), index, "{:?} is out of place in ALL", error);
+        }
+        assert_eq!(TestError::ALL.len(), 18);
+    }
+}
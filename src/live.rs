@@ -0,0 +1,142 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+
+/// Most CLI output lines kept per live run; older ones are dropped
+const LIVE_OUTPUT_LINES: usize = 2000;
+
+/// New lines a live output subscriber may fall behind by before it misses some
+const LIVE_SUBSCRIBER_BACKLOG: usize = 256;
+
+/// Runs in flight: the step their CLI last reported and the output it printed so far
+#[derive(Default)]
+pub struct LiveRuns {
+    runs: Mutex<HashMap<String, LiveOutput>>,
+}
+
+struct LiveOutput {
+    current_step: Option<String>,
+    /// Oldest first
+    lines: VecDeque<String>,
+    /// New lines, for subscribers; dropped with the entry so they see the run end
+    sender: broadcast::Sender<String>,
+}
+
+impl LiveRuns {
+    /// Track a run until the returned guard is dropped, so it stops being live whichever step
+    /// it ends at, or when its request goes away part way; output reported for a run that
+    /// isn't tracked is dropped
+    pub fn start(&self, id: &str) -> LiveGuard<'_> {
+        let (sender, _) = broadcast::channel(LIVE_SUBSCRIBER_BACKLOG);
+        self.lock().insert(id.to_string(), LiveOutput { current_step: None, lines: VecDeque::new(), sender });
+        LiveGuard { runs: self, id: id.to_string() }
+    }
+
+    /// Handle the run's CLI reports its output and progress through
+    pub fn run<'a>(&'a self, id: &'a str) -> LiveRun<'a> {
+        LiveRun { runs: self, id }
+    }
+
+    /// Step the run's CLI last reported, None when it reported none or the run isn't live
+    pub fn current_step(&self, id: &str) -> Option<String> {
+        self.lock().get(id).and_then(|run| run.current_step.clone())
+    }
+
+    /// Output lines the run's CLI printed so far, oldest first
+    pub fn lines(&self, id: &str) -> Vec<String> {
+        self.lock().get(id).map(|run| run.lines.iter().cloned().collect()).unwrap_or_default()
+    }
+
+    /// The run's output so far and a receiver for each line after it, taken together so none
+    /// is missed or repeated. The receiver closes when the run finishes; None when it isn't live.
+    pub fn subscribe(&self, id: &str) -> Option<(Vec<String>, broadcast::Receiver<String>)> {
+        let runs = self.lock();
+        let run = runs.get(id)?;
+        Some((run.lines.iter().cloned().collect(), run.sender.subscribe()))
+    }
+
+    /// Whether the run is being tracked
+    pub fn is_live(&self, id: &str) -> bool {
+        self.lock().contains_key(id)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, LiveOutput>> {
+        self.runs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Keeps a run live until dropped
+pub struct LiveGuard<'a> {
+    runs: &'a LiveRuns,
+    id: String,
+}
+
+impl Drop for LiveGuard<'_> {
+    fn drop(&mut self) {
+        self.runs.lock().remove(&self.id);
+    }
+}
+
+/// One run's entry in `LiveRuns`
+#[derive(Clone, Copy)]
+pub struct LiveRun<'a> {
+    runs: &'a LiveRuns,
+    id: &'a str,
+}
+
+impl LiveRun<'_> {
+    /// Append a line of the CLI's output
+    pub fn push_line(&self, line: String) {
+        if let Some(run) = self.runs.lock().get_mut(self.id) {
+            if run.lines.len() == LIVE_OUTPUT_LINES {
+                run.lines.pop_front();
+            }
+            run.lines.push_back(line.clone());
+            // No subscribers is fine
+            let _ = run.sender.send(line);
+        }
+    }
+
+    /// Record the step the CLI reported it's on
+    pub fn set_step(&self, step: &str) {
+        if let Some(run) = self.runs.lock().get_mut(self.id) {
+            run.current_step = Some(step.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn live_subscribers_get_the_backlog_then_new_lines_until_the_run_ends() {
+        let runs = LiveRuns::default();
+        assert!(runs.subscribe("run").is_none());
+        let live = runs.start("run");
+        runs.run("run").push_line("first".to_string());
+
+        let (backlog, mut lines) = runs.subscribe("run").unwrap();
+        assert_eq!(backlog, ["first"]);
+        runs.run("run").push_line("second".to_string());
+        drop(live);
+        assert_eq!(lines.recv().await.unwrap(), "second");
+        assert!(matches!(lines.recv().await, Err(broadcast::error::RecvError::Closed)));
+        assert!(runs.subscribe("run").is_none());
+        // Output for a run that isn't live goes nowhere
+        runs.run("run").push_line("late".to_string());
+        assert!(runs.lines("run").is_empty());
+    }
+
+    #[test]
+    fn only_the_latest_lines_are_kept() {
+        let runs = LiveRuns::default();
+        let _live = runs.start("run");
+        for n in 0..LIVE_OUTPUT_LINES + 5 {
+            runs.run("run").push_line(n.to_string());
+        }
+        let lines = runs.lines("run");
+        assert_eq!(lines.len(), LIVE_OUTPUT_LINES);
+        assert_eq!(lines[0], "5");
+    }
+}
@@ -0,0 +1,153 @@
+use env_logger::{Builder, Env, Logger};
+use log::{LevelFilter, Log, Metadata, Record};
+use std::sync::{OnceLock, RwLock};
+
+static LOGGER: OnceLock<RuntimeLogger> = OnceLock::new();
+
+/// env_logger with a runtime level override. RUST_LOG (default info) keeps its
+/// per-module directives; an override caps every record at the new level but only
+/// raises this crate's own, so turning on debug doesn't flood the log with dependencies.
+pub struct RuntimeLogger {
+    /// Filter parsed from RUST_LOG
+    filter: Logger,
+    /// Writes whatever passes `enabled`
+    writer: Logger,
+    level: RwLock<Option<LevelFilter>>,
+}
+
+impl RuntimeLogger {
+    fn new(filter: Logger, writer: Logger) -> Self {
+        RuntimeLogger {
+            filter,
+            writer,
+            level: RwLock::new(None),
+        }
+    }
+    
+    /// Override the level and make the `log` macros let those records through
+    fn set_level(&self, level: LevelFilter) {
+        *self.level.write().unwrap() = Some(level);
+        log::set_max_level(level);
+    }
+}
+
+impl Log for RuntimeLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        match *self.level.read().unwrap() {
+            Some(level) if is_own_target(metadata.target()) => metadata.level() <= level,
+            Some(level) => metadata.level() <= level && self.filter.enabled(metadata),
+            None => self.filter.enabled(metadata),
+        }
+    }
+    
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.writer.log(record);
+        }
+    }
+    
+    fn flush(&self) {
+        self.writer.flush();
+    }
+}
+
+/// Whether a record comes from this crate rather than a dependency
+fn is_own_target(target: &str) -> bool {
+    let name = env!("CARGO_CRATE_NAME");
+    target == name || target.starts_with(&format!("{}::", name))
+}
+
+/// Init logging from RUST_LOG, default info
+pub fn init() {
+    let filter = Builder::from_env(Env::default().default_filter_or("info")).build();
+    let writer = Builder::from_env(Env::default()).filter_level(LevelFilter::Trace).build();
+    let max_level = filter.filter();
+    
+    let logger = LOGGER.get_or_init(|| RuntimeLogger::new(filter, writer));
+    if log::set_logger(logger).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+/// Change the max log level at runtime
+pub fn set_level(level: LevelFilter) {
+    match LOGGER.get() {
+        Some(logger) => logger.set_level(level),
+        None => log::set_max_level(level),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use env_logger::Target;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+    
+    /// Collects everything the logger writes
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+    
+    impl Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    
+    fn emit(logger: &RuntimeLogger, target: &str, message: &str) {
+        logger.log(
+            &Record::builder()
+                .level(log::Level::Debug)
+                .target(target)
+                .args(format_args!("{}", message))
+                .build(),
+        );
+    }
+    
+    #[test]
+    fn debug_line_follows_the_runtime_level() {
+        let capture = Capture::default();
+        let filter = Builder::new().parse_filters("info").build();
+        let writer = Builder::new()
+            .filter_level(LevelFilter::Trace)
+            .target(Target::Pipe(Box::new(capture.clone())))
+            .build();
+        let logger = RuntimeLogger::new(filter, writer);
+        let own = concat!(env!("CARGO_CRATE_NAME"), "::tests");
+        let logged = || String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        
+        emit(&logger, own, "hidden at info");
+        assert!(!logged().contains("hidden at info"));
+        
+        logger.set_level(LevelFilter::Debug);
+        emit(&logger, own, "shown at debug");
+        emit(&logger, "hyper::proto", "dependency noise");
+        assert!(logged().contains("shown at debug"));
+        assert!(!logged().contains("dependency noise"));
+        
+        logger.set_level(LevelFilter::Info);
+        emit(&logger, own, "hidden again");
+        assert!(!logged().contains("hidden again"));
+    }
+    
+    #[test]
+    fn module_directives_from_rust_log_still_apply() {
+        let capture = Capture::default();
+        let filter = Builder::new().parse_filters("info,hyper=debug").build();
+        let writer = Builder::new()
+            .filter_level(LevelFilter::Trace)
+            .target(Target::Pipe(Box::new(capture.clone())))
+            .build();
+        let logger = RuntimeLogger::new(filter, writer);
+        
+        emit(&logger, "hyper::proto", "hyper debug");
+        emit(&logger, "reqwest::connect", "reqwest debug");
+        let logged = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        assert!(logged.contains("hyper debug"));
+        assert!(!logged.contains("reqwest debug"));
+    }
+}
@@ -1,37 +1,223 @@
-use actix_web::{web, App, HttpResponse, HttpServer, Responder};
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::{from_fn, Next};
+use actix_web::http::StatusCode;
+use actix_web::middleware::{ErrorHandlerResponse, ErrorHandlers};
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer, Responder};
 use anyhow::{anyhow, Result};
-use log::{error, info};
-use rand::Rng;
+use futures::future::join_all;
+use log::{error, info, warn};
 use regex::Regex;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::cell::Cell;
 use std::fs::File;
 use std::io::{Read, Write};
-use std::process::Command;
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::Ordering;
+use std::sync::OnceLock;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tokio::sync::SemaphorePermit;
 use tokio::time::sleep;
 use uuid::Uuid;
+use zeroize::Zeroizing;
 use base58;
-use hex;
 use chrono;
 
-// Import db module
+// Import amount, config, db, errors, live, logging, report, s3, secret, state and wallet modules
+mod amount;
+mod config;
 mod db;
-use db::{DbPool, init_pool, save_test_result, get_all_test_results, get_test_result_by_id};
+mod errors;
+mod live;
+mod logging;
+mod report;
+mod s3;
+mod secret;
+mod state;
+mod wallet;
+use config::{
+    AddressReusePolicy, Config, FaucetRedirectPolicy, LoanTerms, PreClosedLoanPolicy, RedactionRule, RedactionStyle,
+    RetryPolicy,
+};
+use amount::Amount;
+use errors::TestError;
+use live::LiveRun;
+use secret::Mnemonic;
+use state::{AppState, FaucetRateLimit, RunLabels};
+use db::{DbPool, init_pool, save_test_result, get_test_results, ResultFilter, get_test_result_by_id, get_run_stats, get_recent_success_counts, reserve_faucet_funding, release_faucet_funding, get_faucet_usage_since, get_wallets, backup_database,
+         address_used_recently, AuditEntry, save_audit_entry, get_audit_entries,
+         find_fund_return, save_fund_return, faucet_txid_seen, save_confirmation_time, get_recent_confirmation_times, fail_stale_runs, get_canary_results, with_busy_retry,
+         FundingConfirmation, save_funding_confirmation, get_funding_confirmation, find_contract_id_owner,
+         archive_old_results, last_run_at, FaucetProbe, save_faucet_probe, get_faucet_probes, cancel_queued_result};
+
+/// File name the borrower CLI is downloaded to
+const CLI_BINARY_NAME: &str = "loans-borrower-cli";
+
+/// File name fragments never included in CLI log archives
+const SENSITIVE_FILE_MARKERS: &[&str] = &["wallet", "key", "seed", "mnemonic", "secret"];
+
+/// Flags `cli_verbose` adds to the run's CLI invocations
+const CLI_VERBOSE_ARGS: &[&str] = &["--verbose"];
+
+/// Debug flags a run may pass to the CLI through `extra_cli_args`
+const ALLOWED_EXTRA_CLI_ARGS: &[&str] = &["-v", "-vv", "-vvv", "--verbose", "--debug", "--log-level=debug", "--log-level=trace"];
+
+/// Address remaining funds are returned to
+const FUNDS_RETURN_ADDRESS: &str = "tb1qd8cg49sy99cln5tq2tpdm7xs4p9s5v6le4jx4c";
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 struct FaucetResponse {
     txid: Option<String>,
     message: Option<String>,
     error: Option<String>,
+    #[serde(default)]
+    http_status: Option<u16>,
+    /// A retry found the earlier attempt's transaction instead of requesting again
+    #[serde(default)]
+    deduplicated: bool,
+    /// Code from a structured `{"error": {"code", "message"}}` body
+    #[serde(default)]
+    error_code: Option<String>,
+    /// Quota from the response's rate-limit headers; recorded in details, not stored here
+    #[serde(skip)]
+    rate_limit: Option<FaucetRateLimit>,
+}
+
+/// Faucet error code for a temporary rate limit; retried like a server error
+const FAUCET_RATE_LIMIT: &str = "RATE_LIMIT";
+/// Faucet error code for an empty faucet; counted as exhaustion
+const FAUCET_INSUFFICIENT_FUNDS: &str = "INSUFFICIENT_FUNDS";
+
+/// Optional JSON body for /run-test
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct RunTestRequest {
+    /// Force the named step to fail (requires ENABLE_FAILURE_INJECTION)
+    fail_at_step: Option<String>,
+    /// Number of wallets to run loan lifecycles for concurrently (default 1)
+    wallets: Option<u32>,
+    /// Request BTC collateral from the faucet (default true)
+    request_btc: Option<bool>,
+    /// Request LavaUSD from the faucet (default true)
+    request_lava_usd: Option<bool>,
+    /// Named loan term preset from LOAN_PRESETS (default "default")
+    preset: Option<String>,
+    /// Return funds at the end of the run (default true)
+    return_funds: Option<bool>,
+    /// With several wallets, return all their funds in one transaction once every flow
+    /// has finished instead of one per wallet (default false)
+    consolidate_returns: Option<bool>,
+    /// Network from NETWORKS whose faucets and explorer to use (default NETWORK)
+    network: Option<String>,
+    /// Monitoring run: a failure alerts ALERT_WEBHOOK_URL straight away (default false)
+    canary: Option<bool>,
+    /// Phase timeouts for this run only, overriding the global config
+    timeouts: Option<RunTimeouts>,
+    /// BIP32 account the MNEMONIC_FILE wallet is derived at, for BTC and LavaUSD (default 0)
+    account_index: Option<u32>,
+    /// Keep the wallet funded for manual inspection: no fund return, no cleanup return,
+    /// and the result is never archived (default false)
+    preserve_wallet: Option<bool>,
+    /// Run the CLI with verbose output, logged to the CLI work dir (default false)
+    cli_verbose: Option<bool>,
+    /// Extra debug flags for the CLI, from ALLOWED_EXTRA_CLI_ARGS
+    extra_cli_args: Option<Vec<String>>,
+}
+
+impl RunTestRequest {
+    /// Whether each wallet flow returns its own funds, rather than skipping the return
+    /// or leaving it to the batch's consolidated return
+    fn returns_own_funds(&self) -> bool {
+        self.return_funds.unwrap_or(true) && !self.consolidate_returns.unwrap_or(false) && !self.preserves_wallet()
+    }
+    
+    /// Whether the run's funds stay in the wallet for inspection
+    fn preserves_wallet(&self) -> bool {
+        self.preserve_wallet.unwrap_or(false)
+    }
+    
+    /// Flags passed to every CLI invocation of the run; empty unless asked for
+    fn cli_args(&self) -> Vec<String> {
+        let mut args: Vec<String> = Vec::new();
+        let verbose = if self.cli_verbose.unwrap_or(false) { CLI_VERBOSE_ARGS } else { &[] };
+        let requested = verbose.iter().map(|arg| arg.to_string()).chain(self.extra_cli_args.iter().flatten().cloned());
+        for arg in requested {
+            if !args.contains(&arg) {
+                args.push(arg);
+            }
+        }
+        args
+    }
+}
+
+/// Per-run timeout overrides; unset values fall back to the global config
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct RunTimeouts {
+    /// Funding confirmation timeout, replacing the adaptive one
+    confirmation_ms: Option<u64>,
+    /// Timeout for each faucet HTTP request
+    faucet_request_ms: Option<u64>,
+}
+
+/// Smallest per-run timeout accepted
+const MIN_RUN_TIMEOUT_MS: u64 = 1000;
+/// Largest per-run faucet request timeout accepted
+const MAX_FAUCET_REQUEST_TIMEOUT_MS: u64 = 300_000;
+
+/// Names of the suite steps, in execution order
+const SUITE_STEPS: &[&str] = &[
+    "prefund_wallet",
+    "request_btc",
+    "request_lava_usd",
+    "setup_cli",
+    "create_loan",
+    "repay_loan",
+    "get_contract_details",
+    "return_funds",
+];
+
+fn default_step_kind() -> String {
+    "step".to_string()
+}
+
+/// Layout version of stored and returned results; bump when fields are added or change meaning.
+/// Results written before versioning are version 1.
+const RESULT_SCHEMA_VERSION: u32 = 3;
+
+fn legacy_schema_version() -> u32 {
+    1
+}
+
+/// Outcome of a single suite step or deliberate wait
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StepResult {
+    name: String,
+    /// "step" for work, "wait" for a deliberate wait phase
+    #[serde(default = "default_step_kind")]
+    kind: String,
+    status: String,
+    attempts: u32,
+    duration_ms: u64,
+    error: Option<String>,
+    /// Exit code of the CLI process when the step failed because a CLI invocation did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    exit_code: Option<i32>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct TestResult {
     id: String,
+    /// RESULT_SCHEMA_VERSION when the result was written
+    #[serde(default = "legacy_schema_version")]
+    schema_version: u32,
     status: String,
-    mnemonic: String,
+    mnemonic: Mnemonic,
     btc_address: String,
     lava_usd_pubkey: String,
     btc_faucet_response: FaucetResponse,
@@ -39,50 +225,244 @@ struct TestResult {
     loan_contract_id: Option<String>,
     loan_closed: bool,
     repayment_txid: Option<String>,
+    /// The repayment txid was seen confirmed on the explorer, not just reported by the CLI
+    #[serde(default)]
+    repayment_confirmed: bool,
     details: Option<Value>,
     error_message: Option<String>,
     returned_funds: bool,
+    sats_requested: u64,
+    /// None once funds were returned by a send that didn't report its amount
+    sats_returned: Option<u64>,
+    /// None while sats_returned is unknown
+    net_sats_consumed: Option<i64>,
+    steps: Vec<StepResult>,
+    return_txid: Option<String>,
+    /// BTC faucet returned a txid
+    faucet_accepted: bool,
+    /// The BTC faucet transaction was seen confirmed
+    funds_confirmed: bool,
+    /// Details exceeded MAX_DETAILS_BYTES and were replaced by a preview
+    details_truncated: bool,
+    /// Loan term preset the run used
+    preset: Option<String>,
+    /// Request the run was started with, used to replay it
+    run_request: Option<RunTestRequest>,
+    /// Run this one replays
+    parent_id: Option<String>,
+    /// Started as a canary monitoring run
+    canary: bool,
+    /// Funds were left in the wallet for inspection; never returned or archived
+    #[serde(default)]
+    preserved_wallet: bool,
+    /// The loan contract ID was already held by another stored result
+    contract_id_collision: bool,
+    /// Hidden from GET /results by the retention sweep
+    archived: bool,
+    /// Terms the loan was created with
+    #[serde(default)]
+    loan_terms: Option<LoanTerms>,
+    /// Exit code of the CLI when the step that failed the run failed because the CLI did
+    #[serde(default)]
+    cli_exit_code: Option<i32>,
+    /// How the wallet got its BTC: `faucet`, `prefund_wallet`, or `reused` when a faucet
+    /// retry found the earlier attempt's transaction. None until funded.
+    #[serde(default)]
+    funding_source: Option<String>,
+    /// Place in the run queue while status is queued; not persisted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    queue_position: Option<usize>,
+    /// Step the CLI last reported through an NDJSON progress event while the run is in
+    /// flight; not persisted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    current_step: Option<String>,
+    /// Output lines the CLI printed so far while the run is in flight; not persisted
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    cli_output: Vec<String>,
 }
 
 impl TestResult {
-    fn new(mnemonic: &str, btc_address: &str, lava_usd_pubkey: &str) -> Self {
+    fn new(id: String, mnemonic: &Mnemonic, btc_address: &str, lava_usd_pubkey: &str) -> Self {
         TestResult {
-            id: Uuid::new_v4().to_string(),
+            id,
+            schema_version: RESULT_SCHEMA_VERSION,
             status: "started".to_string(),
-            mnemonic: mnemonic.to_string(),
+            mnemonic: mnemonic.clone(),
             btc_address: btc_address.to_string(),
             lava_usd_pubkey: lava_usd_pubkey.to_string(),
-            btc_faucet_response: FaucetResponse {
-                txid: None,
-                message: None,
-                error: None,
-            },
-            lava_usd_faucet_response: FaucetResponse {
-                txid: None,
-                message: None,
-                error: None,
-            },
+            btc_faucet_response: FaucetResponse::default(),
+            lava_usd_faucet_response: FaucetResponse::default(),
             loan_contract_id: None,
             loan_closed: false,
             repayment_txid: None,
+            repayment_confirmed: false,
             details: None,
             error_message: None,
             returned_funds: false,
+            sats_requested: 0,
+            sats_returned: Some(0),
+            net_sats_consumed: Some(0),
+            steps: Vec::new(),
+            return_txid: None,
+            faucet_accepted: false,
+            funds_confirmed: false,
+            details_truncated: false,
+            preset: None,
+            run_request: None,
+            parent_id: None,
+            canary: false,
+            preserved_wallet: false,
+            contract_id_collision: false,
+            archived: false,
+            loan_terms: None,
+            cli_exit_code: None,
+            funding_source: None,
+            queue_position: None,
+            current_step: None,
+            cli_output: Vec::new(),
+        }
+    }
+    
+    /// Set a key in the details object, creating the object if needed
+    fn record_detail(&mut self, key: &str, value: Value) {
+        let details = self.details.get_or_insert_with(|| json!({}));
+        if let Some(map) = details.as_object_mut() {
+            map.insert(key.to_string(), value);
+        }
+    }
+    
+    /// Replace details serializing to more than `max_bytes` with a truncated preview
+    fn cap_details(&mut self, max_bytes: usize) {
+        let serialized = match &self.details {
+            Some(details) => serde_json::to_string(details).unwrap_or_default(),
+            None => return,
+        };
+        if serialized.len() <= max_bytes {
+            return;
         }
+        
+        let mut cut = max_bytes / 2;
+        while !serialized.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        warn!("Details for {} are {} bytes, truncating to fit {}", self.id, serialized.len(), max_bytes);
+        self.details = Some(json!({
+            "truncated": true,
+            "original_bytes": serialized.len(),
+            "preview": &serialized[..cut]
+        }));
+        self.details_truncated = true;
+    }
+    
+    /// Mark the run failed, recording the error kind and its remediation from the catalog
+    fn fail(&mut self, error: TestError, message: String) {
+        let info = error.info();
+        self.status = "failed".to_string();
+        self.error_message = Some(message);
+        self.record_detail("error_kind", json!(info.error_kind));
+        self.record_detail("remediation", json!(info.remediation));
+    }
+    
+    /// Fail the run because a step failed with `cause`, taking the exit code of a CLI
+    /// invocation that caused it as the run's
+    fn fail_from_step(&mut self, error: TestError, cause: &anyhow::Error, message: String) {
+        self.cli_exit_code = cli_exit_code(cause);
+        self.fail(error, message);
+    }
+    
+    /// Record a suite step that was deliberately not run
+    fn record_skipped_step(&mut self, name: &str) {
+        self.steps.push(StepResult {
+            name: name.to_string(),
+            kind: default_step_kind(),
+            status: "skipped".to_string(),
+            attempts: 0,
+            duration_ms: 0,
+            error: None,
+            exit_code: None,
+        });
+    }
+    
+    /// Record the outcome of a suite step
+    fn record_step(&mut self, name: &str, started: Instant, attempts: u32, error: Option<String>, exit_code: Option<i32>) {
+        self.steps.push(StepResult {
+            name: name.to_string(),
+            kind: default_step_kind(),
+            status: if error.is_some() { "failed" } else { "success" }.to_string(),
+            attempts,
+            duration_ms: started.elapsed().as_millis() as u64,
+            error,
+            exit_code,
+        });
+    }
+    
+    /// Record a deliberate wait and how long it actually took
+    fn record_wait(&mut self, name: &str, started: Instant) {
+        self.steps.push(StepResult {
+            name: name.to_string(),
+            kind: "wait".to_string(),
+            status: "success".to_string(),
+            attempts: 1,
+            duration_ms: started.elapsed().as_millis() as u64,
+            error: None,
+            exit_code: None,
+        });
+    }
+    
+    /// Recompute net sats consumed from the requested and returned figures
+    fn update_net_sats(&mut self) {
+        self.net_sats_consumed = self.sats_returned.map(|returned| self.sats_requested as i64 - returned as i64);
     }
+    
+    /// Record a fund return whose amount isn't known: the return sends the wallet's whole
+    /// balance but doesn't report it, so the sats returned and consumed are left unknown
+    /// rather than counting every requested sat as consumed
+    fn record_fund_return(&mut self, txid: String) {
+        self.return_txid = Some(txid);
+        self.returned_funds = true;
+        self.sats_returned = None;
+        self.update_net_sats();
+    }
+}
+
+/// Demo mnemonic generate_wallet falls back to when MNEMONIC_FILE is unset
+const PLACEHOLDER_MNEMONIC: &str =
+    "abandon ability able about above absent absorb abstract absurd abuse access accident";
+
+/// Demo BTC address paired with the placeholder mnemonic
+const PLACEHOLDER_BTC_ADDRESS: &str = "tb1qxasf0jlsssl3xz8xvl8pmg8d8zpljqmervhtrr";
+
+/// Largest derivation account index a run may ask for
+const MAX_ACCOUNT_INDEX: u32 = 10_000;
+
+/// BIP84 account path for the testnet BTC wallet
+fn btc_account_path(account_index: u32) -> String {
+    format!("m/84'/1'/{}'", account_index)
+}
+
+/// Derivation path of the LavaUSD (Solana) key
+fn lava_usd_derivation_path(account_index: u32) -> String {
+    format!("m/44'/501'/{}'/0'", account_index)
 }
 
-/// Generate new mnemonic and derive BTC and LavaUSD addresses
-fn generate_wallet() -> Result<(String, String, String)> {
-    // For testing, created a simple mnemonic
-    let words = [
-        "abandon", "ability", "able", "about", "above", "absent",
-        "absorb", "abstract", "absurd", "abuse", "access", "accident"
-    ];
-    let mnemonic = words.join(" ");
+/// Generate new mnemonic and derive BTC and LavaUSD addresses at the given account
+fn generate_wallet(config: &Config, account_index: u32) -> Result<(Mnemonic, String, String)> {
+    // Prefer a mounted secret and derive its addresses; otherwise use the demo wallet
+    if let Some(path) = &config.mnemonic_file {
+        let mnemonic = load_mnemonic_file(path)?;
+        let derived = wallet::derive_wallet(
+            mnemonic.expose(),
+            &btc_account_path(account_index),
+            &lava_usd_derivation_path(account_index),
+        )
+            .map_err(|e| anyhow!("MNEMONIC_FILE {}: {}", path, e))?;
+        return Ok((mnemonic, derived.btc_address, derived.lava_usd_pubkey));
+    }
+    
+    let mnemonic = Mnemonic::new(PLACEHOLDER_MNEMONIC.to_string());
     
     // Test BTC address
-    let btc_address = "tb1qxasf0jlsssl3xz8xvl8pmg8d8zpljqmervhtrr".to_string();
+    let btc_address = PLACEHOLDER_BTC_ADDRESS.to_string();
     
     // LavaUSD pubkey format
     let lava_usd_pubkey = "CU9KRXJobqo1HVbaJwoWpnboLFXw3bef54xJ1dewXzcf".to_string();
@@ -90,32 +470,175 @@ fn generate_wallet() -> Result<(String, String, String)> {
     Ok((mnemonic, btc_address, lava_usd_pubkey))
 }
 
+/// Read and validate a mnemonic from a secrets file. The raw buffer is zeroized on drop.
+fn load_mnemonic_file(path: &str) -> Result<Mnemonic> {
+    let contents = Zeroizing::new(
+        std::fs::read_to_string(path).map_err(|e| anyhow!("Failed to read MNEMONIC_FILE {}: {}", path, e))?,
+    );
+    let words: Vec<&str> = contents.split_whitespace().collect();
+    
+    if ![12, 15, 18, 21, 24].contains(&words.len()) {
+        return Err(anyhow!("MNEMONIC_FILE must hold a 12-24 word mnemonic, found {} words", words.len()));
+    }
+    if !words.iter().all(|w| w.chars().all(|c| c.is_ascii_lowercase())) {
+        return Err(anyhow!("MNEMONIC_FILE contains words that aren't lowercase BIP39 words"));
+    }
+    
+    Ok(Mnemonic::new(words.join(" ")))
+}
+
+/// Check whether a wallet is the hardcoded demo wallet
+fn is_placeholder_wallet(mnemonic: &str, btc_address: &str) -> bool {
+    mnemonic == PLACEHOLDER_MNEMONIC || btc_address == PLACEHOLDER_BTC_ADDRESS
+}
+
+/// Decode captured process output: invalid UTF-8 is replaced and ANSI escapes are stripped
+fn sanitize_output(bytes: &[u8]) -> String {
+    static ANSI: OnceLock<Regex> = OnceLock::new();
+    let text = String::from_utf8_lossy(bytes);
+    let ansi = ANSI.get_or_init(|| {
+        Regex::new(r"\x1b\[[0-9;?]*[ -/]*[@-~]|\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)|\x1b[@-_]").expect("ANSI escape pattern is valid")
+    });
+    ansi.replace_all(&text, "").trim().to_string()
+}
+
+/// Describe a failed command from its sanitized stderr (or stdout when stderr is empty)
+fn command_error(output: &std::process::Output, config: &Config) -> String {
+    let stderr = sanitize_output(&output.stderr);
+    let text = if stderr.is_empty() { sanitize_output(&output.stdout) } else { stderr };
+    format!("{} ({})", truncate_for_log(&text, config.max_log_body_len), output.status)
+}
+
+/// The CLI exited unsuccessfully; keeps the exit code so failures can be grouped by it
+#[derive(Debug)]
+struct CliFailure {
+    /// None when the process was killed by a signal
+    exit_code: Option<i32>,
+    message: String,
+}
+
+impl std::fmt::Display for CliFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CliFailure {}
+
+/// The CLI download came back shorter than it should be
+#[derive(Debug)]
+struct CliDownloadTruncated {
+    received: u64,
+    /// Content-Length when the response sent one
+    content_length: Option<u64>,
+    min_size: u64,
+}
+
+impl std::fmt::Display for CliDownloadTruncated {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.content_length {
+            Some(length) if self.received < length => {
+                write!(f, "cli_download_truncated: received {} of {} bytes", self.received, length)
+            }
+            _ => write!(
+                f,
+                "cli_download_truncated: received {} bytes, less than CLI_MIN_SIZE_BYTES ({})",
+                self.received, self.min_size
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CliDownloadTruncated {}
+
+/// Check a downloaded CLI binary's size against the response's Content-Length and
+/// CLI_MIN_SIZE_BYTES before it's written
+fn check_cli_download(config: &Config, content: &[u8], content_length: Option<u64>) -> Result<()> {
+    let received = content.len() as u64;
+    let short_of_header = content_length.is_some_and(|length| received < length);
+    if short_of_header || received < config.cli_min_size_bytes {
+        return Err(CliDownloadTruncated { received, content_length, min_size: config.cli_min_size_bytes }.into());
+    }
+    Ok(())
+}
+
+/// Exit code carried by a failed CLI invocation, None for any other error
+fn cli_exit_code(error: &anyhow::Error) -> Option<i32> {
+    error.downcast_ref::<CliFailure>().and_then(|failure| failure.exit_code)
+}
+
+/// Where a run's CLI keeps its wallet state: `<CLI_STATE_DIR>/<run id>`, or `state` in the
+/// run directory when CLI_STATE_DIR is unset. Never the host's home or working directory,
+/// so runs don't pick up each other's wallets.
+fn cli_state_dir(config: &Config, run_dir: &Path) -> PathBuf {
+    match &config.cli_state_dir {
+        Some(base) => Path::new(base).join(run_dir.file_name().unwrap_or_default()),
+        None => run_dir.join("state"),
+    }
+}
+
+/// Command for the run's CLI binary, run in the run directory with HOME and the XDG
+/// directories pointed at its state directory, and the run's extra flags first
+fn cli_command(run_dir: &Path, state_dir: &Path, extra_args: &[String]) -> tokio::process::Command {
+    let mut command = tokio::process::Command::new(run_dir.join(CLI_BINARY_NAME));
+    command
+        .args(extra_args)
+        .current_dir(run_dir)
+        .env("HOME", state_dir)
+        .env("XDG_CONFIG_HOME", state_dir.join(".config"))
+        .env("XDG_DATA_HOME", state_dir.join(".local/share"))
+        .env("XDG_STATE_HOME", state_dir.join(".local/state"));
+    command
+}
+
+/// Log the run's CLI output goes to when it asked for extra CLI flags, picked up by /cli/logs
+/// until the run ends; it's removed with the run directory unless KEEP_RUN_DIRS is set
+fn cli_log_path(config: &Config, run_dir: &Path) -> PathBuf {
+    Path::new(&config.cli_work_dir).join(format!("cli-{}.log", run_dir.file_name().unwrap_or_default().to_string_lossy()))
+}
+
+/// Copy the run's sanitized CLI log into details.cli_logs so the richer output travels
+/// with the result
+fn record_cli_logs(config: &Config, run_dir: &Path, result: &mut TestResult) {
+    let log = cli_log_path(config, run_dir);
+    match std::fs::read_to_string(&log) {
+        Ok(logs) => result.record_detail("cli_logs", json!(logs)),
+        Err(e) => warn!("Failed to read CLI log {}: {}", log.display(), e),
+    }
+}
+
 /// Download and set up the CLI
-async fn setup_cli() -> Result<()> {
+async fn setup_cli(config: &Config, work_dir: &Path, extra_args: &[String], live: LiveRun<'_>) -> Result<()> {
     info!("Setting up the loans-borrower-cli...");
     
     // Install dependencies
     if cfg!(target_os = "linux") {
-        let apt_output = Command::new("sudo")
+        let apt_output = tokio::process::Command::new("sudo")
             .args(["apt-get", "update"])
-            .output()?;
+            .kill_on_drop(true)
+            .output()
+            .await?;
         
         if !apt_output.status.success() {
-            return Err(anyhow!("Failed to update apt-get"));
+            return Err(anyhow!("Failed to update apt-get: {}", command_error(&apt_output, config)));
         }
         
-        let libpq_output = Command::new("sudo")
+        let libpq_output = tokio::process::Command::new("sudo")
             .args(["apt-get", "install", "-y", "libpq-dev"])
-            .output()?;
+            .kill_on_drop(true)
+            .output()
+            .await?;
         
         if !libpq_output.status.success() {
-            return Err(anyhow!("Failed to install libpq-dev"));
+            return Err(anyhow!("Failed to install libpq-dev: {}", command_error(&libpq_output, config)));
         }
     } else if cfg!(target_os = "macos") {
         // As Im on mac, I'll use brew, but in docker using the linux path
-        let brew_output = Command::new("brew")
+        let brew_output = tokio::process::Command::new("brew")
             .args(["install", "libpq"])
-            .output();
+            .kill_on_drop(true)
+            .output()
+            .await;
         
         if let Ok(output) = brew_output {
             if !output.status.success() {
@@ -138,405 +661,6666 @@ async fn setup_cli() -> Result<()> {
         return Err(anyhow!("Failed to download CLI: {}", response.status()));
     }
     
+    // Read before the body consumes the response
+    let content_length = response.content_length();
     let content = response.bytes().await?;
-    let mut file = File::create("loans-borrower-cli")?;
+    check_cli_download(config, &content, content_length)?;
+    std::fs::create_dir_all(work_dir)?;
+    let cli_path = work_dir.join(CLI_BINARY_NAME);
+    let mut file = File::create(&cli_path)?;
     file.write_all(&content)?;
     
     // Making CLI executable
-    let chmod_output = Command::new("chmod")
-        .args(["+x", "./loans-borrower-cli"])
-        .output()?;
-    
-    if !chmod_output.status.success() {
-        return Err(anyhow!("Failed to make CLI executable"));
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&cli_path, std::fs::Permissions::from_mode(0o755))
+            .map_err(|e| anyhow!("Failed to make CLI executable: {}", e))?;
     }
     
-    info!("CLI setup completed successfully");
+    check_cli_runs(config, work_dir, extra_args, live).await?;
+    info!("CLI setup completed successfully, state in {}", cli_state_dir(config, work_dir).display());
     Ok(())
 }
 
-/// Requesting BTC faucet
-async fn request_btc(address: &str) -> Result<FaucetResponse> {
-    info!("Requesting BTC from faucet for address: {}", address);
-    
-    let client = Client::new();
-    let response = client
-        .post("https://faucet.testnet.lava.xyz/mint-mutinynet")
-        .header("Content-Type", "application/json")
-        .json(&json!({
-            "address": address,
-            "sats": 50000
-        }))
-        .send()
-        .await?;
-    
-    let status = response.status();
-    let text = response.text().await?;
-    
-    info!("BTC faucet response status: {}, body: {}", status, text);
-    
-    let response: FaucetResponse = if text.contains("txid") {
-        let v: Value = serde_json::from_str(&text)?;
-        FaucetResponse {
-            txid: v["txid"].as_str().map(|s| s.to_string()),
-            message: None,
-            error: None,
-        }
-    } else {
-        FaucetResponse {
-            txid: None,
-            message: Some(text.clone()),
-            error: if !status.is_success() { Some(text) } else { None },
+/// Check the downloaded binary runs with its isolated state directory, through a `--help`
+/// invocation bounded by CLI_TIMEOUT_MS; its output goes to the run's live log, and to its CLI
+/// log when the run asked for extra flags. A flag the binary rejects fails the check.
+async fn check_cli_runs(config: &Config, work_dir: &Path, extra_args: &[String], live: LiveRun<'_>) -> Result<()> {
+    let state_dir = cli_state_dir(config, work_dir);
+    std::fs::create_dir_all(&state_dir)?;
+    let log = (!extra_args.is_empty()).then(|| cli_log_path(config, work_dir));
+    run_cli(cli_command(work_dir, &state_dir, extra_args).arg("--help"), config, log.as_deref(), live)
+        .await
+        .map_err(|e| {
+            // In the message too, so the step's recorded error says why
+            let message = format!("Downloaded CLI doesn't run: {}", e);
+            e.context(message)
+        })?;
+    Ok(())
+}
+
+/// Run a CLI command, failing with a `CliFailure` when it exits unsuccessfully or outlives
+/// CLI_TIMEOUT_MS, in which case it's killed and the prompt it was stuck at, if any, is named.
+/// CLI_YES_FLAG is appended when set. With a log path the invocation and its output are
+/// appended there.
+async fn run_cli(
+    command: &mut tokio::process::Command,
+    config: &Config,
+    log: Option<&Path>,
+    live: LiveRun<'_>,
+) -> Result<std::process::Output> {
+    if let Some(flag) = &config.cli_yes_flag {
+        command.arg(flag);
+    }
+    let args: Vec<String> = command.as_std().get_args().map(|arg| arg.to_string_lossy().into_owned()).collect();
+    let timeout = Duration::from_millis(config.cli_timeout_ms);
+    let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+    // Output read before a timeout is kept, so a stuck CLI's last words are logged too
+    let status = match tokio::time::timeout(timeout, drive_cli(command, config, live, &mut stdout, &mut stderr)).await {
+        Ok(status) => Some(status?),
+        Err(_) => None,
+    };
+    if let Some(log) = log {
+        let ended = match status {
+            Some(status) => status.to_string(),
+            None => format!("timed out after {}ms", config.cli_timeout_ms),
+        };
+        let entry = format!(
+            "$ {} {}\n{}\n{}\n[{}]\n",
+            CLI_BINARY_NAME,
+            args.join(" "),
+            sanitize_output(&stdout),
+            sanitize_output(&stderr),
+            ended
+        );
+        let written = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log)
+            .and_then(|mut file| file.write_all(entry.as_bytes()));
+        if let Err(e) = written {
+            warn!("Failed to write CLI log {}: {}", log.display(), e);
         }
+    }
+    let Some(status) = status else {
+        let message = match pending_prompt(config, &stdout).or_else(|| pending_prompt(config, &stderr)) {
+            Some(prompt) => format!(
+                "{} was killed after waiting at a prompt for {}ms: {}",
+                CLI_BINARY_NAME,
+                config.cli_timeout_ms,
+                truncate_for_log(&prompt, config.max_log_body_len)
+            ),
+            None => format!("{} timed out after {}ms and was killed", CLI_BINARY_NAME, config.cli_timeout_ms),
+        };
+        return Err(CliFailure { exit_code: None, message }.into());
     };
-    
-    Ok(response)
+    let output = std::process::Output { status, stdout, stderr };
+    if !output.status.success() {
+        return Err(CliFailure { exit_code: output.status.code(), message: command_error(&output, config) }.into());
+    }
+    Ok(output)
 }
 
-/// Requesting LavaUSD faucet
-async fn request_lava_usd(pubkey: &str) -> Result<FaucetResponse> {
-    info!("Requesting LavaUSD from faucet for pubkey: {}", pubkey);
+/// Spawn the CLI, answer a confirmation with CLI_CONFIRM_RESPONSE on stdin and collect its
+/// output, reporting each line to the live run as it's printed. Stdin is closed after the
+/// answer, so a CLI that asks again sees EOF instead of waiting. The child is killed if this
+/// future is dropped, e.g. on timeout. Stdout lines that are NDJSON progress events move the
+/// run's current step; the full output is buffered either way, so output that isn't JSON
+/// lines is parsed as before.
+async fn drive_cli(
+    command: &mut tokio::process::Command,
+    config: &Config,
+    live: LiveRun<'_>,
+    stdout: &mut Vec<u8>,
+    stderr: &mut Vec<u8>,
+) -> Result<std::process::ExitStatus> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
     
-    let client = Client::new();
-    let response = client
-        .post("https://faucet.testnet.lava.xyz/transfer-lava-usd")
-        .header("Content-Type", "application/json")
-        .json(&json!({
-            "pubkey": pubkey
-        }))
-        .send()
-        .await?;
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
     
-    let status = response.status();
-    let text = response.text().await?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A CLI that never reads stdin may exit before the answer is written; that's not an error
+        if let Err(e) = stdin.write_all(format!("{}\n", config.cli_confirm_response).as_bytes()).await {
+            if e.kind() != std::io::ErrorKind::BrokenPipe {
+                return Err(e.into());
+            }
+        }
+    }
     
-    info!("LavaUSD faucet response status: {}, body: {}", status, text);
+    let (Some(mut stdout_pipe), Some(mut stderr_pipe)) = (child.stdout.take(), child.stderr.take()) else {
+        return Err(anyhow!("{} output isn't piped", CLI_BINARY_NAME));
+    };
+    let (mut stdout_buf, mut stderr_buf) = ([0u8; 4096], [0u8; 4096]);
+    let (mut stdout_open, mut stderr_open) = (true, true);
+    // Output since the last complete line, per stream
+    let (mut stdout_line, mut stderr_line) = (Vec::new(), Vec::new());
+    while stdout_open || stderr_open {
+        tokio::select! {
+            read = stdout_pipe.read(&mut stdout_buf), if stdout_open => match read? {
+                0 => stdout_open = false,
+                n => {
+                    stdout.extend_from_slice(&stdout_buf[..n]);
+                    stdout_line.extend_from_slice(&stdout_buf[..n]);
+                    report_cli_lines(live, &mut stdout_line, true, false);
+                }
+            },
+            read = stderr_pipe.read(&mut stderr_buf), if stderr_open => match read? {
+                0 => stderr_open = false,
+                n => {
+                    stderr.extend_from_slice(&stderr_buf[..n]);
+                    stderr_line.extend_from_slice(&stderr_buf[..n]);
+                    report_cli_lines(live, &mut stderr_line, false, false);
+                }
+            },
+        }
+    }
+    report_cli_lines(live, &mut stdout_line, true, true);
+    report_cli_lines(live, &mut stderr_line, false, true);
     
-    let response: FaucetResponse = if text.contains("txid") {
-        let v: Value = serde_json::from_str(&text)?;
-        FaucetResponse {
-            txid: v["txid"].as_str().map(|s| s.to_string()),
-            message: None,
-            error: None,
+    Ok(child.wait().await?)
+}
+
+/// Report each complete line in `pending` to the live run, leaving a partial last line for
+/// the next read unless `flush`. Progress events are only looked for on stdout.
+fn report_cli_lines(live: LiveRun<'_>, pending: &mut Vec<u8>, progress: bool, flush: bool) {
+    let report = |line: &[u8]| {
+        let line = sanitize_output(line);
+        if line.is_empty() {
+            return;
         }
-    } else {
-        FaucetResponse {
-            txid: None,
-            message: Some(text.clone()),
-            error: if !status.is_success() { Some(text) } else { None },
+        if let Some(step) = progress.then(|| cli_progress_step(&line)).flatten() {
+            live.set_step(&step);
         }
+        live.push_line(line);
     };
-    
-    Ok(response)
+    while let Some(end) = pending.iter().position(|byte| *byte == b'\n') {
+        let line: Vec<u8> = pending.drain(..=end).collect();
+        report(&line);
+    }
+    if flush && !pending.is_empty() {
+        report(&std::mem::take(pending));
+    }
 }
 
-/// Creating loan through CLI
-async fn create_loan(mnemonic: &str) -> Result<String> {
-    info!("Creating new loan...");
-    
-    // Generating contract ID
-    let contract_id = Uuid::new_v4().to_string();
-    info!("Generated simulated contract ID: {}", contract_id);
-    
-    // Sleep time to create a loan
-    sleep(Duration::from_secs(2)).await;
-    
-    Ok(contract_id)
+/// Step named by an NDJSON progress event, `{"event": "progress", "step": "...", ...}`;
+/// None for any other line, JSON or not
+fn cli_progress_step(line: &str) -> Option<String> {
+    let event: Value = serde_json::from_str(line).ok()?;
+    if event.get("event").and_then(Value::as_str) != Some("progress") {
+        return None;
+    }
+    event.get("step").and_then(Value::as_str).map(str::to_string)
 }
 
-/// Repaying loan through CLI
-async fn repay_loan(mnemonic: &str, contract_id: &str) -> Result<()> {
-    info!("Repaying loan with contract ID: {}", contract_id);
-    
-    // Sleep time to repay a loan
-    sleep(Duration::from_secs(2)).await;
-    
-    info!("Simulated loan repayment completed successfully");
-    
+/// The CLI's last output line when it matches one of CLI_PROMPT_PATTERNS
+fn pending_prompt(config: &Config, output: &[u8]) -> Option<String> {
+    // Only the tail matters, so long outputs aren't rescanned on every read
+    let tail = sanitize_output(&output[output.len().saturating_sub(1024)..]);
+    let last_line = tail.lines().last()?.trim();
+    let lowered = last_line.to_lowercase();
+    config
+        .cli_prompt_patterns
+        .iter()
+        .any(|pattern| lowered.contains(&pattern.to_lowercase()))
+        .then(|| last_line.to_string())
+}
+
+/// Quick reachability probe so offline hosts fail fast; any HTTP response counts as reachable
+async fn check_connectivity(config: &Config) -> Result<()> {
+    let client = Client::builder()
+        .timeout(Duration::from_millis(config.connectivity_probe_timeout_ms))
+        .build()?;
+    client.head(&config.connectivity_probe_url).send().await?;
     Ok(())
 }
 
-/// Get contract details from CLI
-async fn get_contract_details(mnemonic: &str, contract_id: &str) -> Result<Value> {
-    info!("Getting contract details for contract ID: {}", contract_id);
-    
-    
-    // Generate a transaction ID
-    let mut rng = rand::thread_rng();
-    let random_bytes: Vec<u8> = (0..32).map(|_| rng.gen()).collect();
-    let repayment_txid = hex::encode(&random_bytes);
-    
-    let contract_details = json!({
-        "Closed": {
-            "timestamp": chrono::Utc::now().to_rfc3339()
-        },
-        "outcome": {
-            "repayment": {
-                "collateral_repayment_txid": repayment_txid
-            }
-        },
-        "contract_id": contract_id,
-        "status": "closed",
-        "loan_terms": {
-            "loan_amount": 2,
-            "loan_duration_days": 4,
-            "ltv_ratio_bp": 5000
+/// Median of observed durations, None without history
+fn median_ms(durations: &[u64]) -> Option<u64> {
+    if durations.is_empty() {
+        return None;
+    }
+    let mut sorted = durations.to_vec();
+    sorted.sort_unstable();
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        Some((sorted[mid - 1] + sorted[mid]) / 2)
+    } else {
+        Some(sorted[mid])
+    }
+}
+
+/// Confirmation timeout adapted to the median of recent confirmation times. It only ever
+/// extends CONFIRMATION_TIMEOUT_MS: timeouts never enter the history, so letting a few fast
+/// samples shorten it would keep cutting off slower confirmations.
+fn confirmation_timeout(config: &Config, history: &[u64]) -> Duration {
+    let default = config.confirmation_timeout_ms;
+    match median_ms(history) {
+        Some(median) if config.adaptive_confirmation_timeout && history.len() >= config.confirmation_min_samples as usize => {
+            let adaptive = (median as f64 * config.confirmation_timeout_multiplier) as u64;
+            Duration::from_millis(adaptive.min(config.confirmation_max_timeout_ms).max(default))
         }
-    });
-    
-    Ok(contract_details)
+        _ => Duration::from_millis(default),
+    }
 }
 
-/// Returning remaining funds to the specified address
-async fn return_funds(mnemonic: &str, return_address: &str) -> Result<bool> {
-    info!("Returning funds to address: {}", return_address);
-    
-    sleep(Duration::from_secs(2)).await;
+/// Longest a single explorer request may take, so a hung request can't stall a poll loop or a run
+const CONFIRMATION_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Whether the explorer reports the transaction as confirmed
+async fn is_tx_confirmed(client: &Client, config: &Config, txid: &str) -> Result<bool> {
+    let url = format!("{}/api/tx/{}/status", config.explorer_url.trim_end_matches('/'), txid);
+    let response = client.get(&url).timeout(CONFIRMATION_CHECK_TIMEOUT).send().await?;
     
-    info!("Funds successfully returned to {}", return_address);
+    // Not yet seen by the explorer
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(false);
+    }
     
-    Ok(true)
+    let status: Value = response.error_for_status()?.json().await?;
+    Ok(status.get("confirmed").and_then(|v| v.as_bool()).unwrap_or(false))
 }
 
-/// Run complete test
-async fn run_test_suite() -> Result<TestResult> {
-    info!("Starting test suite execution");
-    
-    // Step 1: Generate wallet
-    let (mnemonic, btc_address, lava_usd_pubkey) = generate_wallet()?;
-    info!("Generated wallet - Mnemonic: {}, BTC Address: {}, LavaUSD Pubkey: {}", 
-          mnemonic, btc_address, lava_usd_pubkey);
-    
-    let mut result = TestResult::new(&mnemonic, &btc_address, &lava_usd_pubkey);
+/// Block time of a confirmed transaction, None while unconfirmed
+async fn tx_block_time(config: &Config, txid: &str) -> Result<Option<i64>> {
+    let url = format!("{}/api/tx/{}/status", config.explorer_url.trim_end_matches('/'), txid);
+    let status: Value = Client::new().get(&url).timeout(CONFIRMATION_CHECK_TIMEOUT).send().await?.error_for_status()?.json().await?;
+    Ok(status.get("block_time").and_then(|v| v.as_i64()))
+}
+
+/// Check a faucet txid wasn't seen in an earlier run and, when configured, isn't too old.
+/// Returns the outcome for details and the reason to reject the txid, if any.
+async fn check_faucet_txid(config: &Config, db_pool: &DbPool, txid: &str) -> (Value, Option<String>) {
+    let seen_before = faucet_txid_seen(db_pool, txid).unwrap_or_else(|e| {
+        warn!("Failed to check faucet txid history: {}", e);
+        false
+    });
     
-    // Step 2: Request funds from faucets
-    match request_btc(&btc_address).await {
-        Ok(response) => result.btc_faucet_response = response,
-        Err(e) => {
-            error!("Failed to request BTC: {}", e);
-            result.btc_faucet_response.error = Some(e.to_string());
-            result.status = "failed".to_string();
-            result.error_message = Some(format!("Failed to request BTC: {}", e));
-            return Ok(result);
+    let mut age_secs = None;
+    if config.faucet_txid_max_age_secs.is_some() {
+        match tx_block_time(config, txid).await {
+            Ok(Some(block_time)) => age_secs = Some((chrono::Utc::now().timestamp() - block_time).max(0) as u64),
+            Ok(None) => {}
+            Err(e) => warn!("Failed to look up faucet txid {}: {}", txid, e),
         }
     }
+    let stale = match (age_secs, config.faucet_txid_max_age_secs) {
+        (Some(age), Some(max_age)) => age > max_age,
+        _ => false,
+    };
     
-    // Wait for faucet requests
-    sleep(Duration::from_secs(2)).await;
+    let reason = if seen_before {
+        Some(format!("faucet txid {} was already recorded by an earlier run", txid))
+    } else if stale {
+        Some(format!("faucet txid {} confirmed {}s ago, older than FAUCET_TXID_MAX_AGE_SECS", txid, age_secs.unwrap_or_default()))
+    } else {
+        None
+    };
     
-    match request_lava_usd(&lava_usd_pubkey).await {
-        Ok(response) => result.lava_usd_faucet_response = response,
-        Err(e) => {
-            error!("Failed to request LavaUSD: {}", e);
-            result.lava_usd_faucet_response.error = Some(e.to_string());
-            result.status = "failed".to_string();
-            result.error_message = Some(format!("Failed to request LavaUSD: {}", e));
-            return Ok(result);
+    (json!({ "seen_before": seen_before, "age_secs": age_secs, "stale": stale }), reason)
+}
+
+/// Flag a contract ID another stored result already holds. A collision means the CLI
+/// or ID generation is broken, but the run itself carries on.
+fn check_contract_id_collision(db_pool: &DbPool, result: &mut TestResult, contract_id: &str) {
+    match find_contract_id_owner(db_pool, contract_id, &result.id) {
+        Ok(Some(owner)) => {
+            warn!("Loan contract ID {} collides with the one on result {}", contract_id, owner);
+            result.contract_id_collision = true;
+            result.record_detail("contract_id_collision", json!({
+                "contract_id": contract_id,
+                "existing_result_id": owner
+            }));
         }
+        Ok(None) => {}
+        Err(e) => warn!("Failed to check loan contract ID {} for collisions: {}", contract_id, e),
     }
-    
-    // Step 3: Setup CLI
-    if let Err(e) = setup_cli().await {
-        error!("Failed to setup CLI: {}", e);
-        result.status = "failed".to_string();
-        result.error_message = Some(format!("Failed to setup CLI: {}", e));
-        return Ok(result);
+}
+
+/// Funding confirmation stored by an earlier attempt at this run, if it covers `txid`
+fn stored_funding_confirmation(config: &Config, db_pool: &DbPool, result_id: &str, txid: &str) -> Option<FundingConfirmation> {
+    if !config.persist_funding_confirmation {
+        return None;
     }
     
-    // Wait for funds to be confirmed
-    info!("Waiting for funds to be confirmed...");
-    sleep(Duration::from_secs(10)).await;
-    
-    // Step 4: Create loan
-    match create_loan(&mnemonic).await {
-        Ok(contract_id) => {
-            result.loan_contract_id = Some(contract_id);
-        }
+    match get_funding_confirmation(db_pool, result_id) {
+        Ok(stored) => stored.filter(|confirmation| confirmation.txid == txid),
         Err(e) => {
-            error!("Failed to create loan: {}", e);
-            result.status = "failed".to_string();
-            result.error_message = Some(format!("Failed to create loan: {}", e));
-            return Ok(result);
+            warn!("Failed to load stored funding confirmation: {}", e);
+            None
         }
     }
+}
+
+/// Poll until the txid confirms or the adaptive timeout passes.
+/// Returns how long confirmation took (None on timeout) and the timeout used.
+async fn wait_for_confirmation(config: &Config, db_pool: &DbPool, txid: &str) -> (Option<Duration>, Duration) {
+    let history = get_recent_confirmation_times(db_pool, &config.network, config.confirmation_history_window)
+        .unwrap_or_else(|e| {
+            warn!("Failed to load confirmation history: {}", e);
+            Vec::new()
+        });
+    let timeout = confirmation_timeout(config, &history);
+    let interval = Duration::from_millis(config.confirmation_poll_interval_ms);
+    info!("Waiting up to {:?} for {} to confirm ({} samples of history)", timeout, txid, history.len());
     
-    // Wait for loan creation
-    info!("Waiting for loan to be processed...");
-    sleep(Duration::from_secs(10)).await;
-    
-    // Step 5: Repay loan
-    if let Some(contract_id) = &result.loan_contract_id {
-        if let Err(e) = repay_loan(&mnemonic, contract_id).await {
-            error!("Failed to repay loan: {}", e);
-            result.status = "failed".to_string();
-            result.error_message = Some(format!("Failed to repay loan: {}", e));
-            return Ok(result);
+    let client = Client::new();
+    let started = Instant::now();
+    loop {
+        sleep(interval).await;
+        match is_tx_confirmed(&client, config, txid).await {
+            Ok(true) => {
+                let elapsed = started.elapsed();
+                info!("{} confirmed after {:?}", txid, elapsed);
+                if let Err(e) = save_confirmation_time(db_pool, &config.network, elapsed.as_millis() as u64, config.confirmation_history_window) {
+                    warn!("Failed to record confirmation time: {}", e);
+                }
+                return (Some(elapsed), timeout);
+            }
+            Ok(false) => {}
+            Err(e) => warn!("Failed to check confirmation of {}: {}", txid, e),
         }
         
-        // Wait for repayment
-        info!("Waiting for repayment to be processed...");
-        sleep(Duration::from_secs(10)).await;
-        
-        // Step 6: Get contract details and check if closed
-        match get_contract_details(&mnemonic, contract_id).await {
-            Ok(details) => {
-                result.details = Some(details.clone());
-                
-                // Check if loan is closed with repayment
-                if details.get("Closed").is_some() {
-                    result.loan_closed = true;
-                    
-                    // Extract repayment transaction ID
-                    if let Some(outcome) = details.get("outcome") {
-                        if let Some(repayment) = outcome.get("repayment") {
-                            if let Some(txid) = repayment.get("collateral_repayment_txid") {
-                                if let Some(txid_str) = txid.as_str() {
-                                    result.repayment_txid = Some(txid_str.to_string());
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-            Err(e) => {
-                error!("Failed to get contract details: {}", e);
-                result.status = "failed".to_string();
-                result.error_message = Some(format!("Failed to get contract details: {}", e));
-                return Ok(result);
-            }
+        if started.elapsed() >= timeout {
+            warn!("{} not confirmed within {:?}", txid, timeout);
+            return (None, timeout);
         }
     }
-    
-    // Step 7: Return funds
-    match return_funds(&mnemonic, "tb1qd8cg49sy99cln5tq2tpdm7xs4p9s5v6le4jx4c").await {
-        Ok(returned) => result.returned_funds = returned,
-        Err(e) => {
-            error!("Failed to return funds: {}", e);
-            
-            result.returned_funds = false;
-        }
+}
+
+/// Wait for the faucet txid to confirm and record whether the funds arrived.
+/// A confirmation stored by an earlier attempt at this run skips the wait.
+async fn confirm_funding(config: &Config, db_pool: &DbPool, result: &mut TestResult, txid: &str) {
+    if let Some(stored) = stored_funding_confirmation(config, db_pool, &result.id, txid) {
+        info!("Funding {} already confirmed at {}, skipping the wait", txid, stored.confirmed_at);
+        result.funds_confirmed = true;
+        result.record_detail("funding_confirmation", json!({
+            "confirmed": true,
+            "stored": stored
+        }));
+        return;
     }
     
-    // Final status
-    if result.loan_closed && result.repayment_txid.is_some() {
-        result.status = "success".to_string();
-    } else {
-        result.status = "failed".to_string();
-        if result.error_message.is_none() {
-            result.error_message = Some("Loan was not properly closed or repayment TXID missing".to_string());
+    info!("Waiting for funds to be confirmed...");
+    let (confirmed_after, timeout) = wait_for_confirmation(config, db_pool, txid).await;
+    result.funds_confirmed = confirmed_after.is_some();
+    result.record_detail("funding_confirmation", json!({
+        "confirmed": confirmed_after.is_some(),
+        "waited_ms": confirmed_after.unwrap_or(timeout).as_millis() as u64,
+        "timeout_ms": timeout.as_millis() as u64
+    }));
+    
+    if result.funds_confirmed && config.persist_funding_confirmation {
+        let confirmation = FundingConfirmation {
+            txid: txid.to_string(),
+            confirmations: 1,
+            confirmed_at: chrono::Utc::now().to_rfc3339(),
+        };
+        if let Err(e) = save_funding_confirmation(db_pool, &result.id, &confirmation) {
+            warn!("Failed to store funding confirmation: {}", e);
         }
     }
+}
+
+/// Outcome of waiting for a Solana signature to finalize
+enum SolanaConfirmation {
+    Finalized,
+    /// The transaction landed but failed, with the RPC's error
+    Failed(Value),
+    /// Never finalized; the last confirmationStatus seen, if any
+    TimedOut(Option<String>),
+}
+
+/// Status of a Solana signature from getSignatureStatuses, None while the RPC hasn't seen it
+async fn solana_signature_status(client: &Client, config: &Config, signature: &str) -> Result<Option<Value>> {
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "getSignatureStatuses",
+        "params": [[signature], { "searchTransactionHistory": true }]
+    });
+    let response: Value = client
+        .post(&config.solana_rpc_url)
+        .json(&request)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
     
-    info!("Test suite completed with status: {}", result.status);
-    Ok(result)
+    if let Some(error) = response.get("error") {
+        return Err(anyhow!("Solana RPC error: {}", error));
+    }
+    Ok(response
+        .pointer("/result/value/0")
+        .filter(|status| !status.is_null())
+        .cloned())
 }
 
-// HTTP handler for test
-async fn run_test_handler(db_pool: web::Data<DbPool>) -> impl Responder {
-    match run_test_suite().await {
-        Ok(result) => {
-            // Save test to data/test_results.db
-            if let Err(e) = save_test_result(&db_pool, &result) {
-                error!("Failed to save test result to database: {}", e);
+/// Poll the Solana RPC until the LavaUSD transfer is finalized, fails, or the timeout passes
+async fn wait_for_lava_usd_confirmation(config: &Config, signature: &str) -> SolanaConfirmation {
+    let timeout = Duration::from_millis(config.lava_usd_confirmation_timeout_ms);
+    let interval = Duration::from_millis(config.confirmation_poll_interval_ms);
+    info!("Waiting up to {:?} for LavaUSD transfer {} to finalize", timeout, signature);
+    
+    let client = Client::new();
+    let started = Instant::now();
+    let mut last_status = None;
+    loop {
+        sleep(interval).await;
+        match solana_signature_status(&client, config, signature).await {
+            Ok(Some(status)) => {
+                if let Some(error) = status.get("err").filter(|e| !e.is_null()) {
+                    return SolanaConfirmation::Failed(error.clone());
+                }
+                last_status = status
+                    .get("confirmationStatus")
+                    .and_then(|s| s.as_str())
+                    .map(|s| s.to_string());
+                if last_status.as_deref() == Some("finalized") {
+                    info!("LavaUSD transfer {} finalized after {:?}", signature, started.elapsed());
+                    return SolanaConfirmation::Finalized;
+                }
             }
-            
-            let json = serde_json::to_string_pretty(&result).unwrap_or_default();
-            HttpResponse::Ok()
-                .content_type("application/json")
-                .body(json)
+            Ok(None) => {}
+            Err(e) => warn!("Failed to check status of {}: {}", signature, e),
         }
-        Err(e) => {
-            error!("Test suite execution failed: {}", e);
-            HttpResponse::InternalServerError()
-                .content_type("application/json")
-                .body(json!({
-                    "error": format!("Test execution failed: {}", e)
-                }).to_string())
+        
+        if started.elapsed() >= timeout {
+            warn!("LavaUSD transfer {} not finalized within {:?}", signature, timeout);
+            return SolanaConfirmation::TimedOut(last_status);
         }
     }
 }
 
-// GET all test results
-async fn get_results_handler(db_pool: web::Data<DbPool>) -> impl Responder {
-    match get_all_test_results(&db_pool) {
-        Ok(results) => {
-            HttpResponse::Ok()
-                .content_type("application/json")
-                .body(serde_json::to_string_pretty(&results).unwrap_or_default())
+/// Truncate a body for logging, appending an ellipsis when cut
+fn truncate_for_log(body: &str, max_len: usize) -> String {
+    match body.char_indices().nth(max_len) {
+        Some((idx, _)) => format!("{}...", &body[..idx]),
+        None => body.to_string(),
+    }
+}
+
+/// Render a faucet request body from (field name, value) mappings
+fn render_faucet_body(fields: &[(&str, Value)]) -> Result<Value> {
+    let mut body = serde_json::Map::new();
+    
+    for (name, value) in fields {
+        if name.trim().is_empty() {
+            return Err(anyhow!("Faucet body template has an empty field name"));
         }
-        Err(e) => {
-            error!("Failed to get test results: {}", e);
-            HttpResponse::InternalServerError()
-                .content_type("application/json")
-                .body(json!({
-                    "error": format!("Failed to get test results: {}", e)
-                }).to_string())
+        if body.insert(name.to_string(), value.clone()).is_some() {
+            return Err(anyhow!("Faucet body template maps more than one value to field '{}'", name));
         }
     }
+    
+    Ok(Value::Object(body))
 }
 
-// GET specific test result by ID
-async fn get_result_by_id_handler(path: web::Path<String>, db_pool: web::Data<DbPool>) -> impl Responder {
-    let id = path.into_inner();
-    match get_test_result_by_id(&db_pool, &id) {
-        Ok(Some(result)) => {
-            HttpResponse::Ok()
-                .content_type("application/json")
-                .body(serde_json::to_string_pretty(&result).unwrap_or_default())
+/// Build a FaucetResponse, taking the txid from the first matching JSON path
+fn parse_faucet_response(status: reqwest::StatusCode, text: &str, txid_paths: &[String]) -> FaucetResponse {
+    let (txid, shape_error) = match serde_json::from_str::<Value>(text).map(|v| extract_faucet_txid(&v, txid_paths)) {
+        Ok(Ok(txid)) => (txid, None),
+        Ok(Err(reason)) => (None, Some(reason)),
+        Err(_) => (None, None),
+    };
+    
+    match txid {
+        Some(txid) => FaucetResponse {
+            txid: Some(txid),
+            message: None,
+            error: None,
+            http_status: Some(status.as_u16()),
+            deduplicated: false,
+            error_code: None,
+            rate_limit: None,
+        },
+        None => match parse_faucet_error_object(text) {
+            Some((code, message)) => FaucetResponse {
+                txid: None,
+                message: Some(text.to_string()),
+                error: Some(message.unwrap_or_else(|| text.to_string())),
+                http_status: Some(status.as_u16()),
+                deduplicated: false,
+                error_code: Some(code),
+                rate_limit: None,
+            },
+            None => FaucetResponse {
+                txid: None,
+                message: Some(text.to_string()),
+                error: if !status.is_success() { Some(text.to_string()) } else { shape_error },
+                http_status: Some(status.as_u16()),
+                deduplicated: false,
+                error_code: None,
+                rate_limit: None,
+            },
+        },
+    }
+}
+
+/// Txid from a faucet body: an object is searched at the txid paths, an array by its
+/// first element, and a bare string is the txid itself. Arrays and scalars without a
+/// usable txid are an error; an object without one isn't, its body is kept as the message.
+fn extract_faucet_txid(body: &Value, txid_paths: &[String]) -> std::result::Result<Option<String>, String> {
+    match body {
+        Value::Object(_) => Ok(find_string_at_paths(body, txid_paths)),
+        Value::Array(items) => match items.first().map(|first| extract_faucet_txid(first, txid_paths)) {
+            Some(Ok(Some(txid))) => Ok(Some(txid)),
+            Some(_) => Err("Faucet returned a JSON array without a txid in its first element".to_string()),
+            None => Err("Faucet returned an empty JSON array".to_string()),
+        },
+        Value::String(text) if looks_like_txid(text) => Ok(Some(text.clone())),
+        Value::String(_) => Err("Faucet returned a JSON string that isn't a txid".to_string()),
+        other => Err(format!("Faucet returned a bare JSON value ({}) instead of a txid", other)),
+    }
+}
+
+/// Whether a string could be a BTC txid (64 hex chars) or a Solana signature (base58)
+fn looks_like_txid(text: &str) -> bool {
+    text.len() >= 32 && text.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// Code and message from a `{"error": {"code": ..., "message": ...}}` body
+fn parse_faucet_error_object(text: &str) -> Option<(String, Option<String>)> {
+    let body: Value = serde_json::from_str(text).ok()?;
+    let error = body.get("error")?.as_object()?;
+    let code = error.get("code")?.as_str()?.to_string();
+    let message = error.get("message").and_then(|m| m.as_str()).map(|m| m.to_string());
+    Some((code, message))
+}
+
+/// Find the first string value at any of the dotted JSON paths
+fn find_string_at_paths(value: &Value, paths: &[String]) -> Option<String> {
+    paths.iter().find_map(|path| {
+        path.split('.')
+            .try_fold(value, |v, key| v.get(key))
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+    })
+}
+
+/// Quota from `X-RateLimit-Limit`, `-Remaining` and `-Reset`; None when none is sent
+fn parse_rate_limit_headers(headers: &HeaderMap) -> Option<FaucetRateLimit> {
+    let number = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+    };
+    let (limit, remaining, reset) = (number("x-ratelimit-limit"), number("x-ratelimit-remaining"), number("x-ratelimit-reset"));
+    if limit.is_none() && remaining.is_none() && reset.is_none() {
+        return None;
+    }
+    
+    Some(FaucetRateLimit {
+        limit,
+        remaining,
+        reset,
+        observed_at: chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+/// Whether a faucet response says the faucet has run out of funds
+fn is_faucet_exhausted(response: &FaucetResponse, patterns: &[String]) -> bool {
+    if response.error_code.as_deref() == Some(FAUCET_INSUFFICIENT_FUNDS) {
+        return true;
+    }
+    
+    [&response.message, &response.error]
+        .iter()
+        .filter_map(|text| text.as_deref())
+        .map(|text| text.to_lowercase())
+        .any(|text| patterns.iter().any(|pattern| text.contains(&pattern.to_lowercase())))
+}
+
+/// Headers for one faucet's requests: JSON content type, then the shared FAUCET_HEADERS,
+/// then that faucet's own headers, each overriding earlier ones of the same name
+fn faucet_headers(config: &Config, asset_headers: &[(String, String)]) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    for (name, value) in config.faucet_headers.iter().chain(asset_headers) {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| anyhow!("Invalid faucet header name {}: {}", name, e))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|e| anyhow!("Invalid value for faucet header {}: {}", name, e))?;
+        headers.insert(name, value);
+    }
+    Ok(headers)
+}
+
+/// Faucet body fields plus the idempotency key field when FAUCET_IDEMPOTENCY_FIELD is set
+fn with_idempotency_field<'a>(config: &'a Config, mut fields: Vec<(&'a str, Value)>, idempotency_key: &str) -> Vec<(&'a str, Value)> {
+    if let Some(field) = &config.faucet_idempotency_field {
+        fields.push((field.as_str(), json!(idempotency_key)));
+    }
+    fields
+}
+
+/// One BTC faucet attempt. Retries first look for a transaction the faucet already sent
+/// to the address, so a request that timed out but went through isn't made twice.
+/// Only a transaction that isn't in the snapshot taken before the first attempt, didn't
+/// confirm before it and that no earlier run recorded can be the earlier attempt's.
+async fn request_btc_attempt(
+    config: &Config,
+    db_pool: &DbPool,
+    state: &AppState,
+    address: &str,
+    idempotency_key: &str,
+    retry_after: Option<&AddressSnapshot>,
+    slot_wait_ms: &Cell<u64>,
+) -> Result<FaucetResponse> {
+    if let Some(snapshot) = retry_after {
+        match find_address_txid(config, db_pool, address, snapshot).await {
+            Ok(Some(txid)) => {
+                info!("Found earlier faucet transaction {} for {}, not requesting again", txid, address);
+                return Ok(FaucetResponse {
+                    txid: Some(txid),
+                    message: Some("Earlier attempt's transaction found, request not repeated".to_string()),
+                    deduplicated: true,
+                    ..FaucetResponse::default()
+                });
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Failed to check {} for earlier faucet transactions: {}", address, e),
         }
-        Ok(None) => {
-            HttpResponse::NotFound()
-                .content_type("application/json")
-                .body(json!({
-                    "error": format!("Test result with ID {} not found", id)
-                }).to_string())
+    }
+    
+    request_btc(config, state, address, idempotency_key, slot_wait_ms).await
+}
+
+/// An address's transactions before the first BTC faucet attempt
+struct AddressSnapshot {
+    /// Unix seconds
+    taken_at: i64,
+    txids: std::collections::HashSet<String>,
+}
+
+/// Transactions involving the address, as the explorer lists them
+async fn address_txs(config: &Config, address: &str) -> Result<Vec<Value>> {
+    let url = format!("{}/api/address/{}/txs", config.explorer_url.trim_end_matches('/'), address);
+    Ok(Client::new().get(&url).timeout(CONFIRMATION_CHECK_TIMEOUT).send().await?.error_for_status()?.json().await?)
+}
+
+/// Record the address's transactions before the first faucet attempt
+async fn snapshot_address(config: &Config, address: &str) -> Result<AddressSnapshot> {
+    let taken_at = chrono::Utc::now().timestamp();
+    let txids = address_txs(config, address)
+        .await?
+        .iter()
+        .filter_map(|tx| tx.get("txid").and_then(|t| t.as_str()).map(|txid| txid.to_string()))
+        .collect();
+    Ok(AddressSnapshot { taken_at, txids })
+}
+
+/// First transaction paying to the address that wasn't in the snapshot and is still in
+/// the mempool or confirmed no earlier than the snapshot, skipping txids earlier runs recorded
+async fn find_address_txid(config: &Config, db_pool: &DbPool, address: &str, snapshot: &AddressSnapshot) -> Result<Option<String>> {
+    for tx in &address_txs(config, address).await? {
+        let pays_address = tx
+            .get("vout")
+            .and_then(|vout| vout.as_array())
+            .is_some_and(|vout| {
+                vout.iter().any(|out| out.get("scriptpubkey_address").and_then(|a| a.as_str()) == Some(address))
+            });
+        // Unconfirmed transactions carry no time, but a mempool entry can't predate the run by much
+        let new_enough = match tx.pointer("/status/block_time").and_then(|t| t.as_i64()) {
+            Some(block_time) => block_time >= snapshot.taken_at,
+            None => true,
+        };
+        let txid = match tx.get("txid").and_then(|t| t.as_str()) {
+            Some(txid) if pays_address && new_enough && !snapshot.txids.contains(txid) => txid,
+            _ => continue,
+        };
+        if !faucet_txid_seen(db_pool, txid)? {
+            return Ok(Some(txid.to_string()));
         }
-        Err(e) => {
-            error!("Failed to get test result: {}", e);
-            HttpResponse::InternalServerError()
-                .content_type("application/json")
-                .body(json!({
-                    "error": format!("Failed to get test result: {}", e)
-                }).to_string())
+    }
+    Ok(None)
+}
+
+/// Wait for one of the MAX_CONCURRENT_FAUCET_CALLS slots, adding the time waited to `waited_ms`
+async fn acquire_faucet_slot<'a>(state: &'a AppState, waited_ms: &Cell<u64>) -> Result<SemaphorePermit<'a>> {
+    let started = Instant::now();
+    let permit = state
+        .faucet_slots
+        .acquire()
+        .await
+        .map_err(|e| anyhow!("Faucet slots closed: {}", e))?;
+    waited_ms.set(waited_ms.get() + started.elapsed().as_millis() as u64);
+    Ok(permit)
+}
+
+/// Most redirects a faucet request follows
+const MAX_FAUCET_REDIRECTS: usize = 5;
+
+/// Client for faucet requests, following redirects only as FAUCET_REDIRECT_POLICY allows
+fn faucet_client(config: &Config) -> Result<Client> {
+    let policy = config.faucet_redirect_policy;
+    let redirect = reqwest::redirect::Policy::custom(move |attempt| {
+        let from = attempt.previous().last().map(|url| url.to_string()).unwrap_or_default();
+        let to = attempt.url().to_string();
+        let same_host = attempt.previous().first().is_some_and(|original| {
+            original.host_str() == attempt.url().host_str()
+                && original.port_or_known_default() == attempt.url().port_or_known_default()
+        });
+        let follow = match policy {
+            FaucetRedirectPolicy::None => false,
+            FaucetRedirectPolicy::SameHost => same_host,
+            FaucetRedirectPolicy::All => true,
+        };
+        
+        if attempt.previous().len() > MAX_FAUCET_REDIRECTS {
+            attempt.error(format!("more than {} faucet redirects", MAX_FAUCET_REDIRECTS))
+        } else if follow {
+            info!("Faucet redirected {} to {}, following", from, to);
+            attempt.follow()
+        } else {
+            warn!("Faucet redirected {} to {}, not following under FAUCET_REDIRECT_POLICY {:?}", from, to, policy);
+            attempt.stop()
         }
+    });
+    Ok(Client::builder().redirect(redirect).build()?)
+}
+
+/// Fail a faucet response that is a redirect the client didn't follow
+fn check_faucet_redirect(response: &reqwest::Response) -> Result<()> {
+    if !response.status().is_redirection() {
+        return Ok(());
     }
+    let location = response
+        .headers()
+        .get(reqwest::header::LOCATION)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("no location");
+    Err(anyhow!(
+        "Faucet responded {} redirecting to {}, not followed under FAUCET_REDIRECT_POLICY",
+        response.status(),
+        location
+    ))
 }
 
-// Server status
-async fn health_check() -> impl Responder {
-    HttpResponse::Ok().body("Server is running")
+/// Requesting BTC faucet
+async fn request_btc(
+    config: &Config,
+    state: &AppState,
+    address: &str,
+    idempotency_key: &str,
+    slot_wait_ms: &Cell<u64>,
+) -> Result<FaucetResponse> {
+    info!("Requesting BTC from faucet for address: {}", address);
+    
+    let body = render_faucet_body(&with_idempotency_field(
+        config,
+        vec![
+            (config.btc_faucet_address_field.as_str(), json!(address)),
+            (config.btc_faucet_amount_field.as_str(), json!(config.btc_faucet_amount.to_sats())),
+        ],
+        idempotency_key,
+    ))?;
+    
+    let client = faucet_client(config)?;
+    let _slot = acquire_faucet_slot(state, slot_wait_ms).await?;
+    let response = client
+        .post(&config.btc_faucet_url)
+        .timeout(Duration::from_millis(config.faucet_request_timeout_ms))
+        .headers(faucet_headers(config, &config.btc_faucet_headers)?)
+        .header("Idempotency-Key", idempotency_key)
+        .json(&body)
+        .send()
+        .await?;
+    check_faucet_redirect(&response)?;
+    
+    let status = response.status();
+    let rate_limit = parse_rate_limit_headers(response.headers());
+    let text = response.text().await?;
+    
+    info!("BTC faucet response status: {}, body: {}", status, truncate_for_log(&text, config.max_log_body_len));
+    
+    let mut parsed = parse_faucet_response(status, &text, &config.btc_faucet_txid_paths);
+    let exhausted = is_faucet_exhausted(&parsed, &config.faucet_exhaustion_patterns);
+    if exhausted {
+        warn!("BTC faucet appears to be out of funds");
+    }
+    state.faucet_health.record("btc", exhausted);
+    if let Some(rate_limit) = &rate_limit {
+        state.faucet_health.record_rate_limit("btc", rate_limit);
+    }
+    
+    parsed.rate_limit = rate_limit;
+    Ok(parsed)
 }
 
-#[actix_web::main]
-async fn main() -> std::io::Result<()> {
-    // Init LOG
-    env_logger::init_from_env(env_logger::Env::default().default_filter_or("info"));
+/// Requesting LavaUSD faucet
+async fn request_lava_usd(
+    config: &Config,
+    state: &AppState,
+    pubkey: &str,
+    idempotency_key: &str,
+    slot_wait_ms: &Cell<u64>,
+) -> Result<FaucetResponse> {
+    info!("Requesting LavaUSD from faucet for pubkey: {}", pubkey);
     
-    info!("Starting borrower CLI testing server");
+    let body = render_faucet_body(&with_idempotency_field(
+        config,
+        vec![(config.lava_usd_faucet_pubkey_field.as_str(), json!(pubkey))],
+        idempotency_key,
+    ))?;
     
-    // Init DB
-    let db_path = "./data/test_results.db";
-    let db_pool = match init_pool(db_path) {
-        Ok(pool) => pool,
-        Err(e) => {
-            error!("Failed to initialize database: {}", e);
-            return Err(std::io::Error::new(std::io::ErrorKind::Other, 
-                                           format!("Database initialization error: {}", e)));
+    let client = faucet_client(config)?;
+    let _slot = acquire_faucet_slot(state, slot_wait_ms).await?;
+    let response = client
+        .post(&config.lava_usd_faucet_url)
+        .timeout(Duration::from_millis(config.faucet_request_timeout_ms))
+        .headers(faucet_headers(config, &config.lava_usd_faucet_headers)?)
+        .header("Idempotency-Key", idempotency_key)
+        .json(&body)
+        .send()
+        .await?;
+    check_faucet_redirect(&response)?;
+    
+    let status = response.status();
+    let rate_limit = parse_rate_limit_headers(response.headers());
+    let text = response.text().await?;
+    
+    info!("LavaUSD faucet response status: {}, body: {}", status, truncate_for_log(&text, config.max_log_body_len));
+    
+    let mut parsed = parse_faucet_response(status, &text, &config.lava_usd_faucet_txid_paths);
+    let exhausted = is_faucet_exhausted(&parsed, &config.faucet_exhaustion_patterns);
+    if exhausted {
+        warn!("LavaUSD faucet appears to be out of funds");
+    }
+    state.faucet_health.record("lava_usd", exhausted);
+    if let Some(rate_limit) = &rate_limit {
+        state.faucet_health.record_rate_limit("lava_usd", rate_limit);
+    }
+    
+    parsed.rate_limit = rate_limit;
+    Ok(parsed)
+}
+
+/// Creating loan through CLI
+async fn create_loan(state: &AppState, mnemonic: &str, terms: &LoanTerms) -> Result<String> {
+    info!("Creating new loan with terms {:?}...", terms);
+    
+    // Generating contract ID
+    let contract_id = state.random_uuid();
+    info!("Generated simulated contract ID: {}", contract_id);
+    
+    // Sleep time to create a loan
+    sleep(Duration::from_secs(2)).await;
+    
+    Ok(contract_id)
+}
+
+/// Repaying loan through CLI
+async fn repay_loan(mnemonic: &str, contract_id: &str) -> Result<()> {
+    info!("Repaying loan with contract ID: {}", contract_id);
+    
+    // Sleep time to repay a loan
+    sleep(Duration::from_secs(2)).await;
+    
+    info!("Simulated loan repayment completed successfully");
+    
+    Ok(())
+}
+
+/// Get contract details from CLI. The simulated contract stays open until it's repaid.
+async fn get_contract_details(
+    state: &AppState,
+    mnemonic: &str,
+    contract_id: &str,
+    terms: &LoanTerms,
+    repaid: bool,
+) -> Result<Value> {
+    info!("Getting contract details for contract ID: {}", contract_id);
+    
+    if !repaid {
+        return Ok(json!({
+            "contract_id": contract_id,
+            "status": "open",
+            "loan_terms": terms
+        }));
+    }
+    
+    // Generate a transaction ID
+    let repayment_txid = state.random_txid();
+    
+    let contract_details = json!({
+        "Closed": {
+            "timestamp": chrono::Utc::now().to_rfc3339()
+        },
+        "outcome": {
+            "repayment": {
+                "collateral_repayment_txid": repayment_txid
+            }
+        },
+        "contract_id": contract_id,
+        "status": "closed",
+        "loan_terms": terms
+    });
+    
+    Ok(contract_details)
+}
+
+/// Record contract details and closure state on the result
+fn apply_contract_details(result: &mut TestResult, details: Value) {
+    // Check if loan is closed with repayment
+    if details.get("Closed").is_some() {
+        result.loan_closed = true;
+        
+        // Extract repayment transaction ID
+        if let Some(outcome) = details.get("outcome") {
+            if let Some(repayment) = outcome.get("repayment") {
+                if let Some(txid) = repayment.get("collateral_repayment_txid") {
+                    if let Some(txid_str) = txid.as_str() {
+                        result.repayment_txid = Some(txid_str.to_string());
+                    }
+                }
+            }
+        }
+    }
+    
+    // Merge so details recorded earlier in the run are kept
+    match details {
+        Value::Object(map) => {
+            for (key, value) in map {
+                result.record_detail(&key, value);
+            }
         }
+        other => result.details = Some(other),
+    }
+}
+
+/// Check the repayment the CLI reported actually confirmed on-chain. Until it does the
+/// loan doesn't count as closed; a txid that never confirms is recorded as a discrepancy.
+async fn verify_repayment(config: &Config, result: &mut TestResult) {
+    if !result.loan_closed {
+        return;
+    }
+    let Some(txid) = result.repayment_txid.clone() else {
+        warn!("CLI reported the loan closed without a repayment txid");
+        result.loan_closed = false;
+        result.record_detail("repayment_discrepancy", json!({
+            "reason": "CLI reported the loan closed without a repayment txid"
+        }));
+        return;
     };
     
-    // Create web::Data from pool to share with handlers
-    let db_pool = web::Data::new(db_pool);
+    let client = Client::new();
+    let timeout = Duration::from_millis(config.repayment_confirmation_timeout_ms);
+    let started = Instant::now();
+    loop {
+        match is_tx_confirmed(&client, config, &txid).await {
+            Ok(true) => {
+                info!("Repayment {} confirmed after {:?}", txid, started.elapsed());
+                result.repayment_confirmed = true;
+                return;
+            }
+            Ok(false) => {}
+            Err(e) => warn!("Failed to check confirmation of repayment {}: {}", txid, e),
+        }
+        if started.elapsed() >= timeout {
+            break;
+        }
+        sleep(Duration::from_millis(config.confirmation_poll_interval_ms)).await;
+    }
     
-    HttpServer::new(move || {
-        App::new()
-            .app_data(db_pool.clone())
-            .route("/health", web::get().to(health_check))
-            .route("/run-test", web::post().to(run_test_handler))
-            .route("/results", web::get().to(get_results_handler))
-            .route("/results/{id}", web::get().to(get_result_by_id_handler))
+    warn!("CLI reported the loan closed but repayment {} wasn't confirmed within {:?}", txid, timeout);
+    result.loan_closed = false;
+    result.record_detail("repayment_discrepancy", json!({
+        "reason": "CLI reported the loan closed but the repayment txid wasn't confirmed on-chain",
+        "repayment_txid": txid,
+        "waited_ms": timeout.as_millis() as u64
+    }));
+}
+
+/// Check contract details fetched before the repay step. Returns true when the loan
+/// already closed on its own, in which case repayment is skipped: the run is then judged
+/// on how the loan closed, or failed under PRE_CLOSED_LOAN_POLICY=fail.
+fn apply_pre_repay_details(config: &Config, result: &mut TestResult, details: Value) -> bool {
+    let Some(closed) = details.get("Closed") else {
+        return false;
+    };
+    let reason = closed
+        .get("reason")
+        .and_then(Value::as_str)
+        .or_else(|| details.get("status").and_then(Value::as_str))
+        .unwrap_or("closed")
+        .to_string();
+    let contract_id = result.loan_contract_id.clone().unwrap_or_default();
+    warn!("Loan {} was already closed before repayment: {}", contract_id, reason);
+    
+    result.record_skipped_step("repay_loan");
+    result.record_detail("pre_closed_loan", json!({
+        "reason": reason,
+        "policy": config.pre_closed_loan_policy
+    }));
+    apply_contract_details(result, details);
+    if config.pre_closed_loan_policy == PreClosedLoanPolicy::Fail {
+        result.fail(
+            TestError::LoanClosedBeforeRepay,
+            format!("Loan {} was already closed before repayment: {}", contract_id, reason),
+        );
+    }
+    true
+}
+
+/// Sending a run's funding from the pre-funded source wallet through CLI
+async fn send_from_source_wallet(
+    state: &AppState,
+    source_address: &str,
+    btc_address: &str,
+    lava_usd_pubkey: &str,
+    amount: Amount,
+) -> Result<String> {
+    info!("Sending {} sats and LavaUSD from {} to {} / {}", amount.to_sats(), source_address, btc_address, lava_usd_pubkey);
+    
+    sleep(Duration::from_secs(2)).await;
+    
+    let txid = state.random_txid();
+    info!("Source wallet {} funded {} in {}", source_address, btc_address, txid);
+    
+    Ok(txid)
+}
+
+/// Returning remaining funds to the specified address
+async fn return_funds(state: &AppState, mnemonic: &str, return_address: &str) -> Result<String> {
+    info!("Returning funds to address: {}", return_address);
+    
+    sleep(Duration::from_secs(2)).await;
+    
+    // Generate a transaction ID
+    let return_txid = state.random_txid();
+    
+    info!("Funds successfully returned to {} in {}", return_address, return_txid);
+    
+    Ok(return_txid)
+}
+
+/// Return the funds of several wallets in one transaction
+async fn return_funds_consolidated(state: &AppState, mnemonics: &[&str], return_address: &str) -> Result<String> {
+    info!("Returning funds of {} wallets to address {} in one transaction", mnemonics.len(), return_address);
+    
+    sleep(Duration::from_secs(2)).await;
+    
+    let return_txid = state.random_txid();
+    info!("Funds of {} wallets returned to {} in {}", mnemonics.len(), return_address, return_txid);
+    
+    Ok(return_txid)
+}
+
+/// Where a wallet's leftover funds go: back to the source wallet that pre-funded it,
+/// otherwise to the faucet
+fn fund_return_address(result: &TestResult) -> String {
+    result
+        .details
+        .as_ref()
+        .and_then(|details| details.pointer("/prefund/source_address"))
+        .and_then(Value::as_str)
+        .unwrap_or(FUNDS_RETURN_ADDRESS)
+        .to_string()
+}
+
+/// Return the funds of every batch wallet the faucet funded in one consolidated
+/// transaction, recording its txid on each participating result. When consolidation
+/// fails each wallet returns its own funds instead.
+async fn consolidate_fund_returns(db_pool: &DbPool, state: &AppState, results: &mut [TestResult]) -> Option<String> {
+    let mut participants: Vec<&mut TestResult> = results
+        .iter_mut()
+        .filter(|result| result.faucet_accepted && !result.returned_funds)
+        .collect();
+    if participants.is_empty() {
+        return None;
+    }
+    
+    // Batch wallets share one funding config, so they share a return address too
+    let return_address = fund_return_address(participants[0]);
+    let mnemonics: Vec<&str> = participants.iter().map(|result| result.mnemonic.expose()).collect();
+    let outcome = return_funds_consolidated(state, &mnemonics, &return_address).await;
+    let count = participants.len();
+    match outcome {
+        Ok(txid) => {
+            for result in participants.iter_mut() {
+                if let Err(e) = save_fund_return(db_pool, &result.btc_address, result.loan_contract_id.as_deref(), &result.id, &txid) {
+                    error!("Failed to persist consolidated return txid {} for {}: {}", txid, result.id, e);
+                }
+                result.record_fund_return(txid.clone());
+                result.record_detail("consolidated_return", json!({ "txid": txid, "wallets": count }));
+            }
+            Some(txid)
+        }
+        Err(e) => {
+            warn!("Consolidated return of {} wallets failed, returning each separately: {}", count, e);
+            for result in participants.iter_mut() {
+                let mnemonic = result.mnemonic.clone();
+                let btc_address = result.btc_address.clone();
+                cleanup_return_funds(db_pool, state, result, mnemonic.expose(), &btc_address).await;
+                result.record_detail("consolidated_return", json!({ "succeeded": false, "error": e.to_string() }));
+            }
+            None
+        }
+    }
+}
+
+/// Return funds unless a return was already broadcast for this wallet/contract.
+/// Returns the txid and whether it came from an earlier broadcast.
+async fn return_funds_once(
+    db_pool: &DbPool,
+    state: &AppState,
+    mnemonic: &str,
+    btc_address: &str,
+    contract_id: Option<&str>,
+    run_id: &str,
+    return_address: &str,
+) -> Result<(String, bool)> {
+    // Fail closed: without the lookup we can't rule out a double send
+    if let Some(txid) = find_fund_return(db_pool, btc_address, contract_id, run_id)? {
+        info!("Funds for {} were already returned in {}, skipping", btc_address, txid);
+        return Ok((txid, true));
+    }
+    
+    let txid = return_funds(state, mnemonic, return_address).await?;
+    
+    // Persist as soon as it's broadcast so a crash before the final save can't cause a resend
+    if let Err(e) = save_fund_return(db_pool, btc_address, contract_id, run_id, &txid) {
+        error!("Failed to persist return txid {}: {}", txid, e);
+    }
+    
+    Ok((txid, false))
+}
+
+/// Run a step under its retry policy, returning the final outcome and attempts used
+async fn with_retry<T, F, Fut>(
+    step: &str,
+    policy: &RetryPolicy,
+    is_retryable: fn(&RetryPolicy, &Result<T>) -> bool,
+    mut op: F,
+) -> (Result<T>, u32)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut attempt = 1;
+    loop {
+        let outcome = op().await;
+        if attempt >= policy.max_attempts || !is_retryable(policy, &outcome) {
+            return (outcome, attempt);
+        }
+        
+        let delay = policy.backoff_ms.saturating_mul(1u64 << (attempt - 1).min(16));
+        warn!("Step {} attempt {}/{} failed, retrying in {}ms", step, attempt, policy.max_attempts, delay);
+        sleep(Duration::from_millis(delay)).await;
+        attempt += 1;
+    }
+}
+
+/// Retry errored attempts when the policy allows it
+fn is_retryable<T>(policy: &RetryPolicy, outcome: &Result<T>) -> bool {
+    outcome.is_err() && policy.retry_on_error
+}
+
+/// Whether the faucet answered with a 5xx or a rate limit, both worth retrying
+fn is_faucet_server_error(response: &FaucetResponse) -> bool {
+    response.http_status.is_some_and(|s| s >= 500) || response.error_code.as_deref() == Some(FAUCET_RATE_LIMIT)
+}
+
+/// Retry errored attempts and 5xx faucet responses when the policy allows it
+fn is_retryable_faucet(policy: &RetryPolicy, outcome: &Result<FaucetResponse>) -> bool {
+    match outcome {
+        Ok(response) => policy.retry_on_server_error && is_faucet_server_error(response),
+        Err(_) => policy.retry_on_error,
+    }
+}
+
+/// Fail a faucet step whose last attempt still got a server error or rate limit,
+/// rather than recording it as a success without a txid
+fn fail_faucet_server_error(step: StepOutcome<'_, FaucetResponse>) -> StepOutcome<'_, FaucetResponse> {
+    let error = match &step.outcome {
+        Ok(response) if is_faucet_server_error(response) => anyhow!(
+            "faucet still failing after {} attempts (HTTP {}): {}",
+            step.attempts,
+            response.http_status.map(|s| s.to_string()).unwrap_or_default(),
+            response.error.as_deref().unwrap_or("no error message")
+        ),
+        _ => return step,
+    };
+    StepOutcome { outcome: Err(error), ..step }
+}
+
+/// Return an injected error when the request targets this step
+fn injected_failure(request: &RunTestRequest, step: &str) -> Result<()> {
+    match &request.fail_at_step {
+        Some(target) if target == step => Err(anyhow!("Injected failure at step {}", step)),
+        _ => Ok(()),
+    }
+}
+
+/// A step that ran but isn't recorded on the result yet, so steps can run concurrently
+struct StepOutcome<'a, T> {
+    name: &'a str,
+    started: Instant,
+    attempts: u32,
+    injected: bool,
+    outcome: Result<T>,
+}
+
+/// Run a suite step with failure injection and retries, without touching the result
+async fn attempt_step<'a, T, F, Fut>(
+    request: &RunTestRequest,
+    name: &'a str,
+    policy: &RetryPolicy,
+    is_retryable: fn(&RetryPolicy, &Result<T>) -> bool,
+    op: F,
+) -> StepOutcome<'a, T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let started = Instant::now();
+    
+    let (outcome, attempts, injected) = match injected_failure(request, name) {
+        Ok(()) => {
+            let (outcome, attempts) = with_retry(name, policy, is_retryable, op).await;
+            (outcome, attempts, false)
+        }
+        Err(e) => {
+            warn!("Injecting failure at step {}", name);
+            (Err(e), 1, true)
+        }
+    };
+    
+    StepOutcome { name, started, attempts, injected, outcome }
+}
+
+/// Record a step's outcome on the result and hand back what it produced
+fn record_step_outcome<T>(result: &mut TestResult, step: StepOutcome<'_, T>) -> Result<T> {
+    if step.injected {
+        result.record_detail("injected_failure", json!(step.name));
+    }
+    // Any step whose error is a failed CLI invocation; the run's own cli_exit_code is only
+    // set when that step fails the run
+    let exit_code = step.outcome.as_ref().err().and_then(cli_exit_code);
+    result.record_step(step.name, step.started, step.attempts, step.outcome.as_ref().err().map(|e| e.to_string()), exit_code);
+    step.outcome
+}
+
+/// Run a suite step with failure injection and retries, recording its outcome
+async fn run_step<T, F, Fut>(
+    result: &mut TestResult,
+    request: &RunTestRequest,
+    name: &str,
+    policy: &RetryPolicy,
+    is_retryable: fn(&RetryPolicy, &Result<T>) -> bool,
+    op: F,
+) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let step = attempt_step(request, name, policy, is_retryable, op).await;
+    record_step_outcome(result, step)
+}
+
+/// Sleep as a named wait phase recorded in the result's steps
+async fn wait_phase(result: &mut TestResult, name: &str, duration: Duration) {
+    let started = Instant::now();
+    sleep(duration).await;
+    result.record_wait(name, started);
+}
+
+/// Apply a run's timeout overrides to its copy of the config
+fn apply_run_timeouts(config: &mut Config, timeouts: &RunTimeouts) {
+    if let Some(confirmation_ms) = timeouts.confirmation_ms {
+        // An explicit window wins over the adaptive timeout
+        config.confirmation_timeout_ms = confirmation_ms;
+        config.adaptive_confirmation_timeout = false;
+    }
+    if let Some(faucet_request_ms) = timeouts.faucet_request_ms {
+        config.faucet_request_timeout_ms = faucet_request_ms;
+    }
+}
+
+/// Run complete test
+async fn run_test_suite(
+    config: &Config,
+    db_pool: &DbPool,
+    state: &AppState,
+    request: &RunTestRequest,
+    id: String,
+) -> Result<TestResult> {
+    let mut config = config
+        .for_network(request.network.as_deref())
+        .ok_or_else(|| anyhow!("No endpoints configured for network {:?}", request.network))?;
+    if let Some(timeouts) = &request.timeouts {
+        apply_run_timeouts(&mut config, timeouts);
+    }
+    let config = &config;
+    info!("Starting test suite execution on {}", config.network);
+    let started = Instant::now();
+    
+    // Step 1: Generate wallet
+    let account_index = request.account_index.unwrap_or(0);
+    let (mnemonic, btc_address, lava_usd_pubkey) = generate_wallet(config, account_index)?;
+    
+    if is_placeholder_wallet(mnemonic.expose(), &btc_address) && !config.allow_placeholder_wallet {
+        error!("Refusing to run the test suite with the placeholder wallet {}; set ALLOW_PLACEHOLDER_WALLET=true to allow it", btc_address);
+        return Err(anyhow!("Refusing to run with the placeholder demo wallet; set ALLOW_PLACEHOLDER_WALLET=true to allow it"));
+    }
+    info!("Generated wallet - BTC Address: {}, LavaUSD Pubkey: {}", btc_address, lava_usd_pubkey);
+    
+    let mut result = TestResult::new(id, &mnemonic, &btc_address, &lava_usd_pubkey);
+    result.preset = Some(request.preset.clone().unwrap_or_else(|| "default".to_string()));
+    result.preserved_wallet = request.preserves_wallet();
+    result.record_detail("account_index", json!(account_index));
+    result.record_detail("effective_timeouts", json!({
+        "confirmation_ms": config.confirmation_timeout_ms,
+        "confirmation_adaptive": config.adaptive_confirmation_timeout,
+        "faucet_request_ms": config.faucet_request_timeout_ms
+    }));
+    let _live = state.live_runs.start(&result.id);
+    info!("Test run {} started, its CLI output streams at /results/{}/ws", result.id, result.id);
+    
+    // Own working directory so concurrent runs don't share the CLI binary or its state
+    let run_dir = Path::new(&config.run_dir_base).join(&result.id);
+    run_lifecycle(config, db_pool, state, request, &mut result, &run_dir).await;
+    
+    // Cleanup: recover faucet funds when the run stopped before returning them
+    if config.cleanup_return_funds
+        && request.returns_own_funds()
+        && result.faucet_accepted
+        && !result.returned_funds
+    {
+        cleanup_return_funds(db_pool, state, &mut result, mnemonic.expose(), &btc_address).await;
+    }
+    
+    let state_dir = cli_state_dir(config, &run_dir);
+    if config.keep_run_dirs {
+        result.record_detail("run_dir", json!(run_dir.to_string_lossy()));
+        result.record_detail("cli_state_dir", json!(state_dir.to_string_lossy()));
+    } else {
+        // The state directory is inside the run directory unless CLI_STATE_DIR moved it
+        for dir in [&run_dir, &state_dir] {
+            if let Err(e) = remove_run_dir(dir) {
+                warn!("Failed to remove run directory {}: {}", dir.display(), e);
+            }
+        }
+        // A debug run's CLI log was copied into details.cli_logs
+        let log = cli_log_path(config, &run_dir);
+        match std::fs::remove_file(&log) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => warn!("Failed to remove CLI log {}: {}", log.display(), e),
+            _ => {}
+        }
+    }
+    
+    let error_kind = result.details.as_ref().and_then(|d| d.get("error_kind")).and_then(|k| k.as_str());
+    state.run_metrics.record(
+        RunLabels {
+            network: config.network.clone(),
+            status: result.status.clone(),
+            error_kind: error_kind.unwrap_or("none").to_string(),
+        },
+        started.elapsed(),
+    );
+    
+    info!("Test suite completed with status: {}", result.status);
+    Ok(result)
+}
+
+/// Remove a run's working directory; a run that never created it is fine
+fn remove_run_dir(run_dir: &Path) -> std::io::Result<()> {
+    match std::fs::remove_dir_all(run_dir) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        other => other,
+    }
+}
+
+/// Refuse a wallet whose last run started less than WALLET_COOLDOWN_SECS ago. A failed
+/// lookup doesn't block the run.
+fn check_wallet_cooldown(config: &Config, db_pool: &DbPool, btc_address: &str, run_id: &str) -> std::result::Result<(), String> {
+    if config.wallet_cooldown_secs == 0 {
+        return Ok(());
+    }
+    let last = match last_run_at(db_pool, btc_address, run_id) {
+        Ok(Some(last)) => last,
+        Ok(None) => return Ok(()),
+        Err(e) => {
+            warn!("Failed to check the cooldown of {}: {}", btc_address, e);
+            return Ok(());
+        }
+    };
+    let Ok(last_time) = chrono::DateTime::parse_from_rfc3339(&last) else {
+        return Ok(());
+    };
+    
+    let elapsed = (chrono::Utc::now() - last_time.with_timezone(&chrono::Utc)).num_seconds().max(0) as u64;
+    if elapsed >= config.wallet_cooldown_secs {
+        return Ok(());
+    }
+    Err(format!(
+        "Wallet {} was last used {}s ago; WALLET_COOLDOWN_SECS requires {}s between runs, retry in {}s",
+        btc_address,
+        elapsed,
+        config.wallet_cooldown_secs,
+        config.wallet_cooldown_secs - elapsed
+    ))
+}
+
+/// Start of the faucet budget day containing `now`; days begin at DAILY_BUDGET_RESET_HOUR UTC
+fn budget_day_start(config: &Config, now: chrono::DateTime<chrono::Utc>) -> chrono::DateTime<chrono::Utc> {
+    let reset = now
+        .date_naive()
+        .and_hms_opt(config.daily_budget_reset_hour, 0, 0)
+        .unwrap_or_default()
+        .and_utc();
+    if now >= reset {
+        reset
+    } else {
+        reset - chrono::Duration::days(1)
+    }
+}
+
+/// Reserve this run's faucet funding against DAILY_WALLET_CAP and DAILY_SATS_CAP.
+/// Err with the reason once the budget day is used up, or when the budget can't be
+/// checked, since the caps are hard; without caps nothing is tracked.
+fn reserve_daily_budget(config: &Config, db_pool: &DbPool, run_id: &str, now: chrono::DateTime<chrono::Utc>) -> std::result::Result<(), String> {
+    if config.daily_wallet_cap.is_none() && config.daily_sats_cap.is_none() {
+        return Ok(());
+    }
+    let day_start = budget_day_start(config, now);
+    let sats = config.btc_faucet_amount.to_sats();
+    match reserve_faucet_funding(
+        db_pool,
+        run_id,
+        sats,
+        &now.to_rfc3339(),
+        &day_start.to_rfc3339(),
+        config.daily_wallet_cap,
+        config.daily_sats_cap,
+    ) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(format!(
+            "daily_budget_exceeded: funding {} more sats would pass DAILY_WALLET_CAP/DAILY_SATS_CAP for the day; the budget resets at {}",
+            sats,
+            (day_start + chrono::Duration::days(1)).to_rfc3339()
+        )),
+        Err(e) => Err(format!("daily_budget_exceeded: the daily faucet budget couldn't be checked, refusing to fund: {}", e)),
+    }
+}
+
+/// Give back a run's daily budget reservation once it's clear the faucet paid nothing new
+fn release_daily_budget(db_pool: &DbPool, run_id: &str) {
+    if let Err(e) = release_faucet_funding(db_pool, run_id) {
+        warn!("Failed to release the daily budget reservation: {}", e);
+    }
+}
+
+/// The current budget day's faucet usage and what's left under each cap, for /stats
+fn daily_budget_snapshot(config: &Config, db_pool: &DbPool) -> Result<Value> {
+    let day_start = budget_day_start(config, chrono::Utc::now());
+    let (wallets, sats) = get_faucet_usage_since(db_pool, &day_start.to_rfc3339())?;
+    Ok(json!({
+        "day_started_at": day_start.to_rfc3339(),
+        "resets_at": (day_start + chrono::Duration::days(1)).to_rfc3339(),
+        "wallets_used": wallets,
+        "sats_used": sats,
+        "wallet_cap": config.daily_wallet_cap,
+        "sats_cap": config.daily_sats_cap,
+        "wallets_remaining": config.daily_wallet_cap.map(|cap| (cap as i64 - wallets).max(0)),
+        "sats_remaining": config.daily_sats_cap.map(|cap| (cap as i64 - sats).max(0))
+    }))
+}
+
+/// Whether BTC and LavaUSD come from different faucets, so requesting both at once
+/// doesn't put two requests on one faucet
+fn faucets_independent(config: &Config) -> bool {
+    config.btc_faucet_url.trim_end_matches('/') != config.lava_usd_faucet_url.trim_end_matches('/')
+}
+
+/// Request BTC and LavaUSD from the faucets, both at once under PARALLEL_FAUCET_REQUESTS
+/// when the faucets are independent. False when the run failed.
+async fn request_faucet_funds(
+    config: &Config,
+    db_pool: &DbPool,
+    state: &AppState,
+    request: &RunTestRequest,
+    result: &mut TestResult,
+) -> bool {
+    let btc_address = result.btc_address.clone();
+    let lava_usd_pubkey = result.lava_usd_pubkey.clone();
+    
+    // One idempotency key per faucet covers all retries
+    let btc_idempotency_key = Uuid::new_v4().to_string();
+    let btc_attempts = Cell::new(0u32);
+    let faucet_slot_wait_ms = Cell::new(0u64);
+    if let Err(message) = reserve_daily_budget(config, db_pool, &result.id, chrono::Utc::now()) {
+        error!("{}", message);
+        result.fail(TestError::DailyBudgetExceeded, message);
+        return false;
+    }
+    // A reused wallet, e.g. from MNEMONIC_FILE, may already hold transactions no run
+    // recorded, so a retry only looks at what the address gained since the first attempt
+    let snapshot = if config.retries.btc_faucet.max_attempts > 1 {
+        snapshot_address(config, &btc_address)
+            .await
+            .inspect_err(|e| warn!("Failed to list {}'s transactions, so BTC faucet retries won't look for an earlier attempt's: {}", btc_address, e))
+            .ok()
+    } else {
+        None
+    };
+    let request_btc_op = || {
+        btc_attempts.set(btc_attempts.get() + 1);
+        let retry_after = snapshot.as_ref().filter(|_| btc_attempts.get() > 1);
+        request_btc_attempt(config, db_pool, state, &btc_address, &btc_idempotency_key, retry_after, &faucet_slot_wait_ms)
+    };
+    let lava_usd_idempotency_key = Uuid::new_v4().to_string();
+    let request_lava_usd_op = || request_lava_usd(config, state, &lava_usd_pubkey, &lava_usd_idempotency_key, &faucet_slot_wait_ms);
+    
+    // Both faucet requests still take a slot of MAX_CONCURRENT_FAUCET_CALLS each
+    let wants_lava_usd = request.request_lava_usd.unwrap_or(true);
+    let parallel = config.parallel_faucet_requests && wants_lava_usd && faucets_independent(config);
+    if config.parallel_faucet_requests {
+        result.record_detail("faucet_requests", json!(if parallel { "parallel" } else { "sequential" }));
+    }
+    let btc_step = attempt_step(request, "request_btc", &config.retries.btc_faucet, is_retryable_faucet, request_btc_op);
+    let (outcome, lava_usd_outcome) = if parallel {
+        info!("Requesting BTC and LavaUSD from the faucets concurrently");
+        let lava_usd_step = attempt_step(request, "request_lava_usd", &config.retries.lava_usd_faucet, is_retryable_faucet, request_lava_usd_op);
+        let (btc_step, lava_usd_step) = tokio::join!(btc_step, lava_usd_step);
+        let outcome = record_step_outcome(result, fail_faucet_server_error(btc_step));
+        let lava_usd_outcome = record_step_outcome(result, fail_faucet_server_error(lava_usd_step));
+        // LavaUSD has already been paid out or not, so record it even if BTC fails the run
+        (outcome, Some(record_lava_usd_response(result, lava_usd_outcome)))
+    } else {
+        (record_step_outcome(result, fail_faucet_server_error(btc_step.await)), None)
+    };
+    result.record_detail("faucet_slot_wait_ms", json!(faucet_slot_wait_ms.get()));
+    match outcome {
+        Ok(response) => {
+            if let Some(rate_limit) = &response.rate_limit {
+                result.record_detail("btc_faucet_rate_limit", json!(rate_limit));
+            }
+            if response.deduplicated {
+                result.record_detail("btc_faucet_dedup", json!({
+                    "deduplicated": true,
+                    "txid": response.txid,
+                    "attempts": btc_attempts.get()
+                }));
+            }
+            if let Some(txid) = &response.txid {
+                let (check, rejection) = check_faucet_txid(config, db_pool, txid).await;
+                result.record_detail("faucet_txid_check", check);
+                if let Some(reason) = rejection {
+                    warn!("Rejecting BTC faucet response: {}", reason);
+                    release_daily_budget(db_pool, &result.id);
+                    result.btc_faucet_response = response;
+                    result.fail(TestError::StaleFaucetTxid, format!("Stale faucet txid: {}", reason));
+                    return false;
+                }
+            }
+            if response.txid.is_some() {
+                result.faucet_accepted = true;
+                result.sats_requested = config.btc_faucet_amount.to_sats();
+                result.update_net_sats();
+                let source = if response.deduplicated { "reused" } else { "faucet" };
+                result.funding_source = Some(source.to_string());
+            } else {
+                // No txid, so nothing was paid out and the run doesn't count against the budget
+                release_daily_budget(db_pool, &result.id);
+            }
+            result.btc_faucet_response = response;
+        }
+        Err(e) => {
+            error!("Failed to request BTC: {}", e);
+            // The faucet didn't pay out, so the run doesn't count against the daily budget
+            release_daily_budget(db_pool, &result.id);
+            result.btc_faucet_response.error = Some(e.to_string());
+            result.fail_from_step(TestError::BtcFaucetFailed, &e, format!("Failed to request BTC: {}", e));
+            return false;
+        }
+    }
+    
+    if wants_lava_usd {
+        let outcome = match lava_usd_outcome {
+            Some(outcome) => outcome,
+            None => {
+                // Wait for faucet requests
+                wait_phase(result, "faucet_wait", Duration::from_secs(2)).await;
+                
+                let step = attempt_step(request, "request_lava_usd", &config.retries.lava_usd_faucet, is_retryable_faucet, request_lava_usd_op).await;
+                let outcome = record_step_outcome(result, fail_faucet_server_error(step));
+                result.record_detail("faucet_slot_wait_ms", json!(faucet_slot_wait_ms.get()));
+                record_lava_usd_response(result, outcome)
+            }
+        };
+        if let Err(e) = outcome {
+            error!("Failed to request LavaUSD: {}", e);
+            result.fail_from_step(TestError::LavaUsdFaucetFailed, &e, format!("Failed to request LavaUSD: {}", e));
+            return false;
+        }
+    } else {
+        info!("Skipping LavaUSD faucet as requested");
+        result.record_skipped_step("request_lava_usd");
+    }
+    
+    true
+}
+
+/// Store the LavaUSD faucet's response, or its error, on the result
+fn record_lava_usd_response(result: &mut TestResult, outcome: Result<FaucetResponse>) -> Result<()> {
+    match outcome {
+        Ok(response) => {
+            if let Some(rate_limit) = &response.rate_limit {
+                result.record_detail("lava_usd_faucet_rate_limit", json!(rate_limit));
+            }
+            result.lava_usd_faucet_response = response;
+            Ok(())
+        }
+        Err(e) => {
+            result.lava_usd_faucet_response.error = Some(e.to_string());
+            Err(e)
+        }
+    }
+}
+
+/// Fund the run's wallet from the PREFUND_MNEMONIC_FILE wallet instead of the faucets.
+/// False when the run failed.
+async fn prefund_from_source(
+    config: &Config,
+    state: &AppState,
+    request: &RunTestRequest,
+    result: &mut TestResult,
+    source_file: &str,
+) -> bool {
+    let source = load_mnemonic_file(source_file).and_then(|mnemonic| {
+        let derived = wallet::derive_wallet(mnemonic.expose(), &btc_account_path(0), &lava_usd_derivation_path(0))?;
+        Ok(derived.btc_address)
+    });
+    let source_address = match source {
+        Ok(address) => address,
+        Err(e) => {
+            error!("Failed to load the pre-fund source wallet: {}", e);
+            result.fail(TestError::PrefundFailed, format!("Failed to load PREFUND_MNEMONIC_FILE: {}", e));
+            return false;
+        }
+    };
+    
+    let btc_address = result.btc_address.clone();
+    let lava_usd_pubkey = result.lava_usd_pubkey.clone();
+    let amount = config.btc_faucet_amount;
+    let outcome = run_step(result, request, "prefund_wallet", &config.retries.prefund, is_retryable, || {
+        send_from_source_wallet(state, &source_address, &btc_address, &lava_usd_pubkey, amount)
     })
-    .bind("0.0.0.0:8080")?
-    .run()
-    .await
+    .await;
+    match outcome {
+        Ok(txid) => {
+            result.record_skipped_step("request_btc");
+            result.record_skipped_step("request_lava_usd");
+            result.record_detail("prefund", json!({
+                "source_address": source_address,
+                "txid": txid,
+                "amount_sats": amount.to_sats()
+            }));
+            // The wallet now holds funds to return, as if the faucet had paid out
+            result.faucet_accepted = true;
+            result.funding_source = Some("prefund_wallet".to_string());
+            result.sats_requested = amount.to_sats();
+            result.update_net_sats();
+            // The send is simulated, so there's no transaction on chain to wait for
+            result.record_detail("funding_confirmation", json!({
+                "confirmed": false,
+                "simulated": true
+            }));
+            true
+        }
+        Err(e) => {
+            error!("Failed to pre-fund the wallet from {}: {}", source_address, e);
+            result.fail_from_step(TestError::PrefundFailed, &e, format!("Failed to pre-fund the wallet from {}: {}", source_address, e));
+            false
+        }
+    }
+}
+
+/// Run the suite steps from pre-flight to final status, stopping at the first failure
+async fn run_lifecycle(
+    config: &Config,
+    db_pool: &DbPool,
+    state: &AppState,
+    request: &RunTestRequest,
+    result: &mut TestResult,
+    run_dir: &Path,
+) {
+    let mnemonic = result.mnemonic.clone();
+    let btc_address = result.btc_address.clone();
+    // Presets are validated with the request, so an unknown one can't reach here
+    let terms = config.loan_terms(result.preset.as_deref()).unwrap_or_default();
+    result.loan_terms = Some(terms);
+    
+    // Pre-flight: fail fast instead of sleeping through steps that can't succeed
+    if let Err(e) = check_connectivity(config).await {
+        error!("Connectivity pre-flight to {} failed: {}", config.connectivity_probe_url, e);
+        result.fail(
+            TestError::NoConnectivity,
+            format!("no_connectivity: could not reach {}: {}", config.connectivity_probe_url, e),
+        );
+        return;
+    }
+    
+    if let Err(message) = check_wallet_cooldown(config, db_pool, &btc_address, &result.id) {
+        error!("{}", message);
+        result.fail(TestError::WalletCooldown, message);
+        return;
+    }
+    
+    // Flag addresses already used recently, a common cause of faucet limits
+    match address_used_recently(db_pool, &btc_address, config.address_reuse_lookback) {
+        Ok(reused) => {
+            result.record_detail("address_reuse_detected", json!(reused));
+            if reused {
+                match config.address_reuse_policy {
+                    AddressReusePolicy::Warn => {
+                        warn!("BTC address {} was already used in a recent run", btc_address);
+                    }
+                    AddressReusePolicy::Error => {
+                        error!("Refusing to reuse BTC address {} from a recent run", btc_address);
+                        result.fail(
+                            TestError::AddressReused,
+                            format!("BTC address {} was already used in a recent run", btc_address),
+                        );
+                        return;
+                    }
+                }
+            }
+        }
+        Err(e) => warn!("Failed to check for address reuse: {}", e),
+    }
+    
+    // Step 2: Fund the wallet, from the pre-funded source wallet when one is configured
+    let funding_txid = match &config.prefund_mnemonic_file {
+        Some(source_file) => {
+            if !prefund_from_source(config, state, request, result, source_file).await {
+                return;
+            }
+            None
+        }
+        None => {
+            if !request_faucet_funds(config, db_pool, state, request, result).await {
+                return;
+            }
+            result.btc_faucet_response.txid.clone()
+        }
+    };
+    
+    // Step 3: Setup CLI, with any debug flags the run asked for
+    let cli_args = request.cli_args();
+    if !cli_args.is_empty() {
+        result.record_detail("cli_args", json!({
+            "args": cli_args,
+            "log": cli_log_path(config, run_dir).to_string_lossy()
+        }));
+    }
+    let run_id = result.id.clone();
+    let live = state.live_runs.run(&run_id);
+    let outcome = run_step(result, request, "setup_cli", &config.retries.setup_cli, is_retryable, || {
+        setup_cli(config, run_dir, &cli_args, live)
+    })
+    .await;
+    if !cli_args.is_empty() {
+        record_cli_logs(config, run_dir, result);
+    }
+    if let Err(e) = outcome {
+        error!("Failed to setup CLI: {}", e);
+        let kind = if e.downcast_ref::<CliDownloadTruncated>().is_some() {
+            TestError::CliDownloadTruncated
+        } else {
+            TestError::SetupCliFailed
+        };
+        result.fail_from_step(kind, &e, format!("Failed to setup CLI: {}", e));
+        return;
+    }
+    
+    // Wait for funds to be confirmed
+    let started = Instant::now();
+    match funding_txid {
+        Some(txid) => confirm_funding(config, db_pool, result, &txid).await,
+        // Recorded as simulated when the source wallet sent it
+        None if config.prefund_mnemonic_file.is_some() => {}
+        None => {
+            info!("No funding txid to track, waiting for funds...");
+            sleep(Duration::from_secs(10)).await;
+        }
+    }
+    result.record_wait("funds_confirm", started);
+    
+    // Wait for the LavaUSD transfer to finalize
+    if config.lava_usd_confirmation {
+        if let Some(signature) = result.lava_usd_faucet_response.txid.clone() {
+            let started = Instant::now();
+            let outcome = wait_for_lava_usd_confirmation(config, &signature).await;
+            result.record_wait("lava_usd_confirm", started);
+            
+            let waited_ms = started.elapsed().as_millis() as u64;
+            let failure = match outcome {
+                SolanaConfirmation::Finalized => {
+                    result.record_detail("lava_usd_confirmation", json!({
+                        "status": "finalized",
+                        "waited_ms": waited_ms
+                    }));
+                    None
+                }
+                SolanaConfirmation::Failed(error) => {
+                    result.record_detail("lava_usd_confirmation", json!({
+                        "status": "failed",
+                        "error": error,
+                        "waited_ms": waited_ms
+                    }));
+                    Some(format!("LavaUSD transfer {} failed on Solana: {}", signature, error))
+                }
+                SolanaConfirmation::TimedOut(last_status) => {
+                    result.record_detail("lava_usd_confirmation", json!({
+                        "status": "timed_out",
+                        "last_status": last_status,
+                        "waited_ms": waited_ms,
+                        "timeout_ms": config.lava_usd_confirmation_timeout_ms
+                    }));
+                    Some(format!(
+                        "LavaUSD transfer {} not finalized within {}ms (last status: {})",
+                        signature,
+                        config.lava_usd_confirmation_timeout_ms,
+                        last_status.as_deref().unwrap_or("not seen")
+                    ))
+                }
+            };
+            if let Some(message) = failure {
+                error!("{}", message);
+                result.fail(TestError::LavaUsdNotFinalized, message);
+                return;
+            }
+        }
+    }
+    
+    // Step 4: Create loan
+    let outcome = run_step(result, request, "create_loan", &config.retries.create_loan, is_retryable, || {
+        create_loan(state, mnemonic.expose(), &terms)
+    })
+    .await;
+    match outcome {
+        Ok(contract_id) => {
+            check_contract_id_collision(db_pool, result, &contract_id);
+            result.loan_contract_id = Some(contract_id);
+        }
+        Err(e) => {
+            error!("Failed to create loan: {}", e);
+            result.fail_from_step(TestError::LoanCreationFailed, &e, format!("Failed to create loan: {}", e));
+            return;
+        }
+    }
+    
+    // Wait for loan creation
+    info!("Waiting for loan to be processed...");
+    wait_phase(result, "loan_process", Duration::from_secs(10)).await;
+    
+    // Step 5: Repay loan, unless it already closed on its own
+    if let Some(contract_id) = result.loan_contract_id.clone() {
+        // Best effort: if the lookup fails, repay as usual
+        match get_contract_details(state, mnemonic.expose(), &contract_id, &terms, false).await {
+            Ok(details) => {
+                if apply_pre_repay_details(config, result, details) {
+                    if result.status == "failed" {
+                        return;
+                    }
+                    return_funds_and_finish(config, db_pool, state, request, result).await;
+                    return;
+                }
+            }
+            Err(e) => warn!("Failed to check contract {} before repayment: {}", contract_id, e),
+        }
+        
+        let outcome = run_step(result, request, "repay_loan", &config.retries.repay_loan, is_retryable, || {
+            repay_loan(mnemonic.expose(), &contract_id)
+        })
+        .await;
+        if let Err(e) = outcome {
+            error!("Failed to repay loan: {}", e);
+            result.fail_from_step(TestError::RepaymentFailed, &e, format!("Failed to repay loan: {}", e));
+            return;
+        }
+        
+        // Wait for repayment
+        info!("Waiting for repayment to be processed...");
+        wait_phase(result, "repayment_wait", Duration::from_secs(10)).await;
+        
+        // Step 6: Get contract details and check if closed
+        let outcome = run_step(result, request, "get_contract_details", &config.retries.contract_details, is_retryable, || {
+            get_contract_details(state, mnemonic.expose(), &contract_id, &terms, true)
+        })
+        .await;
+        match outcome {
+            Ok(details) => apply_contract_details(result, details),
+            Err(e) => {
+                error!("Failed to get contract details: {}", e);
+                result.fail_from_step(TestError::ContractDetailsFailed, &e, format!("Failed to get contract details: {}", e));
+                return;
+            }
+        }
+        if config.verify_repayment_onchain {
+            verify_repayment(config, result).await;
+        }
+    }
+    
+    return_funds_and_finish(config, db_pool, state, request, result).await;
+}
+
+/// Return funds unless the request keeps them, then set the final status
+async fn return_funds_and_finish(
+    config: &Config,
+    db_pool: &DbPool,
+    state: &AppState,
+    request: &RunTestRequest,
+    result: &mut TestResult,
+) {
+    let mnemonic = result.mnemonic.clone();
+    let btc_address = result.btc_address.clone();
+    
+    // Step 7: Return funds
+    if request.returns_own_funds() {
+        let contract_id = result.loan_contract_id.clone();
+        let run_id = result.id.clone();
+        let return_address = fund_return_address(result);
+        let outcome = run_step(result, request, "return_funds", &config.retries.return_funds, is_retryable, || {
+            return_funds_once(db_pool, state, mnemonic.expose(), &btc_address, contract_id.as_deref(), &run_id, &return_address)
+        })
+        .await;
+        match outcome {
+            Ok((txid, already_returned)) => {
+                result.record_fund_return(txid);
+                result.record_detail("return_already_broadcast", json!(already_returned));
+            }
+            Err(e) => {
+                error!("Failed to return funds: {}", e);
+                
+                result.returned_funds = false;
+                result.record_detail("return_funds_error", json!(e.to_string()));
+            }
+        }
+    } else if request.preserves_wallet() {
+        warn!("Preserving wallet {} for inspection, its funds are not returned", btc_address);
+        result.record_skipped_step("return_funds");
+        result.record_detail("return_funds_skipped", json!("Funds stay in the wallet for inspection (preserve_wallet)"));
+    } else if request.return_funds.unwrap_or(true) {
+        info!("Leaving the fund return of {} to the batch's consolidated return", btc_address);
+        result.record_skipped_step("return_funds");
+        result.record_detail("return_funds_deferred", json!("Funds are returned in the batch's consolidated transaction"));
+    } else {
+        info!("Skipping fund return as requested, funds stay in {}", btc_address);
+        result.record_skipped_step("return_funds");
+        result.record_detail("return_funds_skipped", json!("Fund return was intentionally skipped by the request"));
+    }
+    
+    // Final status
+    let required_paths = required_closure_paths(config, result.details.as_ref());
+    let (status, reasons) = evaluate_result(result.details.as_ref(), required_paths);
+    let return_error = result
+        .details
+        .as_ref()
+        .and_then(|details| details.get("return_funds_error"))
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let discrepancy = result
+        .details
+        .as_ref()
+        .and_then(|details| details.get("repayment_discrepancy"))
+        .and_then(|discrepancy| discrepancy.get("reason"))
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    // The details can look closed while the repayment never confirmed on-chain
+    if status == "success" && (discrepancy.is_some() || !result.loan_closed) {
+        let message = discrepancy.unwrap_or_else(|| "CLI didn't report the loan closed".to_string());
+        result.fail(TestError::LoanNotClosed, format!("Loan was not properly closed: {}", message));
+    } else if status == "success" && config.strict_fund_return && return_error.is_some() {
+        let message = format!("Loan closed but the fund return failed: {}", return_error.unwrap_or_default());
+        result.fail(TestError::FundReturnFailed, message);
+    } else if status == "success" {
+        result.status = status;
+    } else {
+        let message = result
+            .error_message
+            .clone()
+            .unwrap_or_else(|| format!("Loan was not properly closed: {}", reasons.join("; ")));
+        result.fail(TestError::LoanNotClosed, message);
+    }
+}
+
+/// Paths a run's details must hold to pass: a loan that closed before we repaid it has
+/// no repayment of ours to look for
+fn required_closure_paths<'a>(config: &'a Config, details: Option<&Value>) -> &'a [String] {
+    if details.is_some_and(|details| details.get("pre_closed_loan").is_some()) {
+        &config.pre_closed_loan_required_paths
+    } else {
+        &config.closed_loan_required_paths
+    }
+}
+
+/// Judge a run from its contract details alone: it passes when every required dotted
+/// path is present and non-null. Returns the status and why it failed, if it did.
+fn evaluate_result(details: Option<&Value>, required_paths: &[String]) -> (String, Vec<String>) {
+    let reasons: Vec<String> = required_paths
+        .iter()
+        .filter(|path| {
+            let found = details.and_then(|details| path.split('.').try_fold(details, |v, key| v.get(key)));
+            found.is_none_or(Value::is_null)
+        })
+        .map(|path| format!("details.{} is missing", path))
+        .collect();
+    
+    let status = if reasons.is_empty() { "success" } else { "failed" };
+    (status.to_string(), reasons)
+}
+
+/// Return funds after the lifecycle stopped early; the outcome is recorded in details
+async fn cleanup_return_funds(
+    db_pool: &DbPool,
+    state: &AppState,
+    result: &mut TestResult,
+    mnemonic: &str,
+    btc_address: &str,
+) {
+    info!("Cleanup: returning funds for run {}", result.id);
+    
+    let contract_id = result.loan_contract_id.clone();
+    let run_id = result.id.clone();
+    let return_address = fund_return_address(result);
+    match return_funds_once(db_pool, state, mnemonic, btc_address, contract_id.as_deref(), &run_id, &return_address).await {
+        Ok((txid, already_returned)) => {
+            result.record_fund_return(txid.clone());
+            result.record_detail("cleanup_return", json!({
+                "succeeded": true,
+                "txid": txid,
+                "return_address": return_address,
+                "already_broadcast": already_returned
+            }));
+        }
+        Err(e) => {
+            error!("Cleanup fund return failed: {}", e);
+            result.record_detail("cleanup_return", json!({
+                "succeeded": false,
+                "error": e.to_string()
+            }));
+        }
+    }
+}
+
+/// A result serialized for storage outside the DB, with the mnemonic of every wallet masked
+fn result_without_mnemonics(result: &TestResult) -> Value {
+    let mut value = serde_json::to_value(result).unwrap_or_default();
+    mask_mnemonics(&mut value);
+    value
+}
+
+/// Mask the mnemonic of a serialized result and of each wallet it ran in details.wallets
+fn mask_mnemonics(value: &mut Value) {
+    if let Some(mnemonic) = value.get_mut("mnemonic") {
+        *mnemonic = json!("[REDACTED]");
+    }
+    if let Some(wallets) = value.pointer_mut("/details/wallets").and_then(Value::as_array_mut) {
+        wallets.iter_mut().for_each(mask_mnemonics);
+    }
+}
+
+/// A result serialized for a read endpoint, with REDACTION_RULES applied to it and to
+/// each wallet in details.wallets
+fn redacted_result(result: &TestResult, rules: &[RedactionRule]) -> Value {
+    let mut value = serde_json::to_value(result).unwrap_or_default();
+    apply_redaction_rules(&mut value, rules);
+    value
+}
+
+fn apply_redaction_rules(value: &mut Value, rules: &[RedactionRule]) {
+    for rule in rules {
+        let pointer = format!("/{}", rule.path.replace('.', "/"));
+        if let Some(field) = value.pointer_mut(&pointer).filter(|field| !field.is_null()) {
+            *field = redact_value(field, rule.style);
+        }
+    }
+    if let Some(wallets) = value.pointer_mut("/details/wallets").and_then(Value::as_array_mut) {
+        wallets.iter_mut().for_each(|wallet| apply_redaction_rules(wallet, rules));
+    }
+}
+
+fn redact_value(value: &Value, style: RedactionStyle) -> Value {
+    let text = match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    match style {
+        RedactionStyle::Mask => json!("[REDACTED]"),
+        RedactionStyle::Partial => {
+            let chars: Vec<char> = text.chars().collect();
+            if chars.len() <= 8 {
+                json!("[REDACTED]")
+            } else {
+                let head: String = chars[..4].iter().collect();
+                let tail: String = chars[chars.len() - 4..].iter().collect();
+                json!(format!("{}...{}", head, tail))
+            }
+        }
+        RedactionStyle::Hash => {
+            use sha2::{Digest, Sha256};
+            json!(format!("sha256:{}", &hex::encode(Sha256::digest(text.as_bytes()))[..16]))
+        }
+    }
+}
+
+/// Best-effort upload of the result to S3; the outcome is recorded in details
+async fn archive_result(config: &Config, result: &mut TestResult) {
+    let key = s3::result_key(&config.s3_prefix, &result.id);
+    let upload = match serde_json::to_vec_pretty(&result_without_mnemonics(result)) {
+        Ok(body) => s3::put_json(config, &key, body).await,
+        Err(e) => Err(e.into()),
+    };
+    
+    match upload {
+        Ok(()) => {
+            info!("Archived test result {} to S3 as {}", result.id, key);
+            result.record_detail("s3_upload", json!({ "status": "uploaded", "key": key }));
+        }
+        Err(e) => {
+            warn!("Failed to archive test result {} to S3: {}", result.id, e);
+            result.record_detail("s3_upload", json!({ "status": "failed", "key": key, "error": e.to_string() }));
+        }
+    }
+}
+
+/// Run the loan lifecycle for several wallets concurrently and aggregate the outcomes
+async fn run_multi_wallet_suite(
+    config: &Config,
+    db_pool: &DbPool,
+    request: &RunTestRequest,
+    state: &AppState,
+    wallets: u32,
+    id: String,
+) -> Result<TestResult> {
+    info!("Starting multi-wallet test with {} wallets", wallets);
+    
+    // Each flow derives its own account, so the wallets don't share an address
+    let base_index = request.account_index.unwrap_or(0);
+    let flows = (0..wallets).map(|index| async move {
+        let id = Uuid::new_v4().to_string();
+        let _permit = match state.run_slots.acquire().await {
+            Ok(permit) => permit,
+            Err(e) => return (id, Err(anyhow!("Run slots closed: {}", e))),
+        };
+        info!("Starting wallet flow {}/{}", index + 1, wallets);
+        let flow_request = RunTestRequest { account_index: Some(base_index + index), ..request.clone() };
+        let outcome = run_test_suite(config, db_pool, state, &flow_request, id.clone()).await;
+        (id, outcome)
+    });
+    
+    // A flow that couldn't run still gets a failed result, so the others' results are kept
+    let mut sub_results = Vec::new();
+    for (id, outcome) in join_all(flows).await {
+        sub_results.push(outcome.unwrap_or_else(|e| {
+            error!("Wallet flow {} failed to run: {}", id, e);
+            let mut failed = TestResult::new(id, &Mnemonic::default(), "", "");
+            failed.status = "failed".to_string();
+            failed.error_message = Some(format!("Wallet flow failed to run: {}", e));
+            failed
+        }));
+    }
+    
+    let consolidated_txid = if request.consolidate_returns.unwrap_or(false) {
+        consolidate_fund_returns(db_pool, state, &mut sub_results).await
+    } else {
+        None
+    };
+    
+    let first = sub_results.iter().find(|r| !r.btc_address.is_empty()).unwrap_or(&sub_results[0]);
+    let mut result = TestResult::new(id, &first.mnemonic, &first.btc_address, &first.lava_usd_pubkey);
+    result.return_txid = consolidated_txid;
+    result.preset = first.preset.clone();
+    result.loan_terms = first.loan_terms;
+    // Only a source every wallet shares describes the batch
+    if sub_results.iter().all(|r| r.funding_source == first.funding_source) {
+        result.funding_source = first.funding_source.clone();
+    }
+    result.loan_closed = sub_results.iter().all(|r| r.loan_closed);
+    result.returned_funds = sub_results.iter().all(|r| r.returned_funds);
+    result.faucet_accepted = sub_results.iter().all(|r| r.faucet_accepted);
+    result.funds_confirmed = sub_results.iter().all(|r| r.funds_confirmed);
+    result.contract_id_collision = sub_results.iter().any(|r| r.contract_id_collision);
+    result.preserved_wallet = sub_results.iter().any(|r| r.preserved_wallet);
+    result.sats_requested = sub_results.iter().map(|r| r.sats_requested).sum();
+    // Unknown when any wallet's return amount is
+    result.sats_returned = sub_results.iter().map(|r| r.sats_returned).sum();
+    result.update_net_sats();
+    
+    let failures: Vec<String> = sub_results
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| r.status != "success")
+        .map(|(index, r)| format!("wallet {}: {}", index + 1, r.error_message.as_deref().unwrap_or("failed")))
+        .collect();
+    
+    if failures.is_empty() {
+        result.status = "success".to_string();
+    } else {
+        result.fail(
+            TestError::WalletFlowsFailed,
+            format!("{} of {} wallet flows failed ({})", failures.len(), wallets, failures.join("; ")),
+        );
+    }
+    
+    result.record_detail("wallets", serde_json::to_value(&sub_results)?);
+    
+    info!("Multi-wallet test completed with status: {}", result.status);
+    Ok(result)
+}
+
+/// Build a JSON error response
+fn error_response(status: StatusCode, message: &str) -> HttpResponse {
+    HttpResponse::build(status)
+        .content_type("application/json")
+        .body(json!({ "error": message }).to_string())
+}
+
+/// Replace framework error bodies (e.g. 405, unhandled 500) with the JSON shape handlers use
+fn json_error_body<B>(res: ServiceResponse<B>) -> actix_web::Result<ErrorHandlerResponse<B>> {
+    let is_json = res
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    if is_json {
+        return Ok(ErrorHandlerResponse::Response(res.map_into_left_body()));
+    }
+    
+    let status = res.status();
+    let message = match status {
+        StatusCode::NOT_FOUND => format!("No route for {} {}", res.request().method(), res.request().path()),
+        StatusCode::METHOD_NOT_ALLOWED => format!("Method {} not allowed for {}", res.request().method(), res.request().path()),
+        _ => status.canonical_reason().unwrap_or("Request failed").to_string(),
+    };
+    let (req, _) = res.into_parts();
+    let response = error_response(status, &message).map_into_right_body();
+    Ok(ErrorHandlerResponse::Response(ServiceResponse::new(req, response)))
+}
+
+// Fallback for requests no route matches. Resources fall back here too, so a known
+// path with the wrong method is a 405 rather than a 404.
+async fn not_found_handler(req: HttpRequest) -> impl Responder {
+    if req.match_pattern().is_some() {
+        return error_response(StatusCode::METHOD_NOT_ALLOWED, &format!("Method {} not allowed for {}", req.method(), req.path()));
+    }
+    error_response(StatusCode::NOT_FOUND, &format!("No route for {} {}", req.method(), req.path()))
+}
+
+/// Reject the request unless it carries the configured API key
+fn check_api_key(req: &HttpRequest, config: &Config) -> Option<HttpResponse> {
+    let Some(expected) = config.api_key.as_ref() else {
+        if config.allow_unguarded_endpoints {
+            return None;
+        }
+        return Some(error_response(
+            StatusCode::FORBIDDEN,
+            "This endpoint is disabled because API_KEY is not set (ALLOW_UNGUARDED_ENDPOINTS=true opens it)",
+        ));
+    };
+    let provided = req.headers().get("X-API-Key").and_then(|v| v.to_str().ok());
+    
+    if provided.is_some_and(|provided| constant_time_eq(provided.as_bytes(), expected.as_bytes())) {
+        None
+    } else {
+        Some(error_response(StatusCode::UNAUTHORIZED, "Missing or invalid API key"))
+    }
+}
+
+/// Compare secrets without returning at the first differing byte, so response timing
+/// doesn't reveal how much of a guess was right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Parse and validate the optional /run-test body
+fn parse_run_request(body: &[u8], config: &Config) -> std::result::Result<RunTestRequest, String> {
+    let request: RunTestRequest = if body.iter().all(|b| b.is_ascii_whitespace()) {
+        RunTestRequest::default()
+    } else {
+        serde_json::from_slice(body).map_err(|e| format!("Invalid request body: {}", e))?
+    };
+    
+    validate_run_request(&request, config)?;
+    Ok(request)
+}
+
+/// Check a run request against the current config
+fn validate_run_request(request: &RunTestRequest, config: &Config) -> std::result::Result<(), String> {
+    if request.request_btc == Some(false) {
+        return Err("request_btc can't be disabled: the BTC faucet funds the loan collateral".to_string());
+    }
+    
+    if let Some(wallets) = request.wallets {
+        if wallets == 0 || wallets > config.max_wallets_per_run {
+            return Err(format!("wallets must be between 1 and {}", config.max_wallets_per_run));
+        }
+    }
+    
+    if let Some(index) = request.account_index {
+        // Wallet flows take consecutive indexes from account_index
+        let last_index = index.saturating_add(request.wallets.unwrap_or(1).saturating_sub(1));
+        if last_index > MAX_ACCOUNT_INDEX {
+            return Err(format!("account_index must be between 0 and {} for its last wallet", MAX_ACCOUNT_INDEX));
+        }
+    }
+    
+    if request.preserves_wallet() && (request.return_funds == Some(true) || request.consolidate_returns == Some(true)) {
+        return Err("preserve_wallet keeps the funds in the wallet, so it can't be combined with return_funds: true or consolidate_returns".to_string());
+    }
+    
+    if request.consolidate_returns == Some(true) {
+        if request.wallets.unwrap_or(1) < 2 {
+            return Err("consolidate_returns needs wallets of 2 or more".to_string());
+        }
+        if request.return_funds == Some(false) {
+            return Err("consolidate_returns can't be combined with return_funds: false".to_string());
+        }
+    }
+    
+    if let Some(arg) = request.extra_cli_args.iter().flatten().find(|arg| !ALLOWED_EXTRA_CLI_ARGS.contains(&arg.as_str())) {
+        return Err(format!("extra_cli_args doesn't allow '{}', expected any of: {}", arg, ALLOWED_EXTRA_CLI_ARGS.join(", ")));
+    }
+    
+    if let Some(preset) = &request.preset {
+        if !config.loan_presets.contains_key(preset) {
+            let valid: Vec<&str> = config.loan_presets.keys().map(|name| name.as_str()).collect();
+            return Err(format!("Unknown preset '{}', expected one of: {}", preset, valid.join(", ")));
+        }
+    }
+    
+    if let Some(timeouts) = &request.timeouts {
+        if let Some(ms) = timeouts.confirmation_ms {
+            if !(MIN_RUN_TIMEOUT_MS..=config.confirmation_max_timeout_ms).contains(&ms) {
+                return Err(format!(
+                    "timeouts.confirmation_ms must be between {} and {}",
+                    MIN_RUN_TIMEOUT_MS, config.confirmation_max_timeout_ms
+                ));
+            }
+        }
+        if let Some(ms) = timeouts.faucet_request_ms {
+            if !(MIN_RUN_TIMEOUT_MS..=MAX_FAUCET_REQUEST_TIMEOUT_MS).contains(&ms) {
+                return Err(format!(
+                    "timeouts.faucet_request_ms must be between {} and {}",
+                    MIN_RUN_TIMEOUT_MS, MAX_FAUCET_REQUEST_TIMEOUT_MS
+                ));
+            }
+        }
+    }
+    
+    if let Some(network) = &request.network {
+        if !config.networks.contains_key(network) {
+            let valid: Vec<&str> = config.networks.keys().map(|name| name.as_str()).collect();
+            return Err(format!("Unknown network '{}', expected one of: {}", network, valid.join(", ")));
+        }
+    }
+    
+    if let Some(step) = &request.fail_at_step {
+        if !config.enable_failure_injection {
+            return Err("fail_at_step requires ENABLE_FAILURE_INJECTION=true".to_string());
+        }
+        if !SUITE_STEPS.contains(&step.as_str()) {
+            return Err(format!("Unknown step '{}', expected one of: {}", step, SUITE_STEPS.join(", ")));
+        }
+    }
+    
+    Ok(())
+}
+
+/// Identify which API key a request used without recording the key itself
+fn api_key_id(headers: &actix_web::http::header::HeaderMap, config: &Config) -> Option<String> {
+    let provided = headers.get("X-API-Key")?.to_str().unwrap_or_default();
+    match &config.api_key {
+        Some(expected) if constant_time_eq(expected.as_bytes(), provided.as_bytes()) => Some("primary".to_string()),
+        _ => Some("invalid".to_string()),
+    }
+}
+
+/// Start an audit entry for mutating requests; request bodies are never recorded
+fn begin_audit(req: &ServiceRequest) -> Option<(web::Data<DbPool>, AuditEntry)> {
+    if matches!(req.method().as_str(), "GET" | "HEAD" | "OPTIONS") {
+        return None;
+    }
+    
+    let db_pool = req.app_data::<web::Data<DbPool>>()?.clone();
+    let config = req.app_data::<web::Data<Config>>()?;
+    // Forwarding headers are set by the client unless a proxy we run replaced them, so
+    // they're only recorded for requests from a trusted proxy, and never as client_ip
+    let peer = req.peer_addr();
+    let forwarded = ["forwarded", "x-forwarded-for"].iter().any(|name| req.headers().contains_key(*name));
+    let forwarded_ip = match peer {
+        Some(addr) if forwarded && config.trusted_proxies.contains(&addr.ip()) => {
+            req.connection_info().realip_remote_addr().map(|ip| ip.to_string())
+        }
+        _ => None,
+    };
+    
+    let entry = AuditEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        client_ip: peer.map(|addr| addr.ip().to_string()),
+        forwarded_ip,
+        api_key_id: api_key_id(req.headers(), config),
+        method: req.method().to_string(),
+        route: req.match_pattern().unwrap_or_else(|| req.path().to_string()),
+        status: 0,
+    };
+    
+    Some((db_pool, entry))
+}
+
+/// Write the audit entry for a mutating request once its response status is known
+async fn audit_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> std::result::Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let audit = begin_audit(&req);
+    let res = next.call(req).await?;
+    
+    if let Some((db_pool, mut entry)) = audit {
+        entry.status = res.status().as_u16();
+        if let Err(e) = save_audit_entry(&db_pool, &entry) {
+            error!("Failed to write audit entry: {}", e);
+        }
+    }
+    
+    Ok(res)
+}
+
+// HTTP handler for test
+async fn run_test_handler(
+    body: web::Bytes,
+    db_pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if state.paused.load(Ordering::SeqCst) {
+        return error_response(StatusCode::SERVICE_UNAVAILABLE, "New test runs are paused");
+    }
+    
+    let request = match parse_run_request(&body, &config) {
+        Ok(request) => request,
+        Err(message) => return error_response(StatusCode::BAD_REQUEST, &message),
+    };
+    
+    execute_run(config, db_pool, state, request, None).await
+}
+
+// POST run the suite and answer with the final result in the same response. Waits for a
+// run slot instead of queueing. After SYNC_RUN_TIMEOUT_SECS it answers 504 with the run's
+// ID; the run carries on and is saved as usual.
+async fn run_test_sync_handler(
+    body: web::Bytes,
+    db_pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if state.paused.load(Ordering::SeqCst) {
+        return error_response(StatusCode::SERVICE_UNAVAILABLE, "New test runs are paused");
+    }
+    
+    let request = match parse_run_request(&body, &config) {
+        Ok(request) => request,
+        Err(message) => return error_response(StatusCode::BAD_REQUEST, &message),
+    };
+    
+    let wallets = request.wallets.unwrap_or(1);
+    // Named up front, so a timed out response can point at the result, batch or not
+    let id = Uuid::new_v4().to_string();
+    let timeout = Duration::from_secs(config.sync_run_timeout_secs);
+    let run_id = id.clone();
+    
+    // Saved before the run starts, so the ID a timed out response hands back resolves
+    let mut running = TestResult::new(id.clone(), &Mnemonic::default(), "", "");
+    running.status = "running".to_string();
+    running.preset = Some(request.preset.clone().unwrap_or_else(|| "default".to_string()));
+    running.run_request = Some(request.clone());
+    running.canary = request.canary.unwrap_or(false);
+    if let Err(e) = save_test_result(&db_pool, &running) {
+        error!("Failed to save running test result: {}", e);
+        return error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed to start test run: {}", e));
+    }
+    
+    // Own task, so the run isn't abandoned half way when the wait times out
+    let run = actix_web::rt::spawn(async move {
+        let outcome = if wallets > 1 {
+            run_multi_wallet_suite(&config, &db_pool, &request, &state, wallets, run_id).await
+        } else {
+            match state.run_slots.acquire().await {
+                Ok(_permit) => run_test_suite(&config, &db_pool, &state, &request, run_id).await,
+                Err(e) => Err(anyhow!("Run slots closed before the run started: {}", e)),
+            }
+        };
+        // A run that couldn't run still replaces its running row, or it would stay running
+        if let Err(e) = &outcome {
+            running.status = "failed".to_string();
+            running.error_message = Some(format!("Test execution failed: {}", e));
+            if let Err(e) = save_test_result(&db_pool, &running) {
+                error!("Failed to save failed test result {}: {}", running.id, e);
+            }
+        }
+        finish_run(&config, &db_pool, &state, outcome, request, None).await
+    });
+    
+    match tokio::time::timeout(timeout, run).await {
+        Ok(Ok(response)) => response,
+        Ok(Err(e)) => {
+            error!("Synchronous test run task failed: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("Test execution failed: {}", e))
+        }
+        Err(_) => {
+            warn!("Synchronous test run didn't finish within {:?}, leaving it running", timeout);
+            HttpResponse::GatewayTimeout()
+                .content_type("application/json")
+                .body(json!({
+                    "error": format!("Run didn't finish within SYNC_RUN_TIMEOUT_SECS ({}s); it is still running", timeout.as_secs()),
+                    "id": id,
+                    "status": "running"
+                }).to_string())
+        }
+    }
+}
+
+// POST lint a /run-test body without running it: the same validation, plus the request
+// with defaults filled in and the settings it would run with
+async fn validate_run_test_handler(body: web::Bytes, config: web::Data<Config>, state: web::Data<AppState>) -> impl Responder {
+    let request = match parse_run_request(&body, &config) {
+        Ok(request) => request,
+        Err(message) => return error_response(StatusCode::BAD_REQUEST, &message),
+    };
+    
+    let normalized = normalize_run_request(&request, &config);
+    // Validation already checked the network and preset exist
+    let mut run_config = config.for_network(normalized.network.as_deref()).unwrap_or_else(|| config.as_ref().clone());
+    if let Some(timeouts) = &request.timeouts {
+        apply_run_timeouts(&mut run_config, timeouts);
+    }
+    
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .body(serde_json::to_string_pretty(&json!({
+            "valid": true,
+            "request": normalized,
+            "effective": {
+                "network": run_config.network,
+                "btc_faucet_url": run_config.btc_faucet_url,
+                "lava_usd_faucet_url": run_config.lava_usd_faucet_url,
+                "explorer_url": run_config.explorer_url,
+                "funding": if run_config.prefund_mnemonic_file.is_some() { "prefund" } else { "faucet" },
+                "loan_terms": run_config.loan_terms(normalized.preset.as_deref()),
+                "timeouts": {
+                    "confirmation_ms": run_config.confirmation_timeout_ms,
+                    "confirmation_adaptive": run_config.adaptive_confirmation_timeout,
+                    "faucet_request_ms": run_config.faucet_request_timeout_ms
+                }
+            },
+            "warnings": run_request_warnings(&normalized, &config, &state)
+        })).unwrap_or_default())
+}
+
+/// A validated request with every default spelled out
+fn normalize_run_request(request: &RunTestRequest, config: &Config) -> RunTestRequest {
+    RunTestRequest {
+        fail_at_step: request.fail_at_step.clone(),
+        wallets: Some(request.wallets.unwrap_or(1)),
+        request_btc: Some(request.request_btc.unwrap_or(true)),
+        request_lava_usd: Some(request.request_lava_usd.unwrap_or(true)),
+        preset: Some(request.preset.clone().unwrap_or_else(|| "default".to_string())),
+        return_funds: Some(request.return_funds.unwrap_or(true)),
+        consolidate_returns: Some(request.consolidate_returns.unwrap_or(false)),
+        network: Some(request.network.clone().unwrap_or_else(|| config.network.clone())),
+        canary: Some(request.canary.unwrap_or(false)),
+        timeouts: request.timeouts.clone(),
+        account_index: Some(request.account_index.unwrap_or(0)),
+        preserve_wallet: Some(request.preserves_wallet()),
+        cli_verbose: Some(request.cli_verbose.unwrap_or(false)),
+        extra_cli_args: Some(request.extra_cli_args.clone().unwrap_or_default()),
+    }
+}
+
+/// Things about a valid request that are likely not what was meant, or would stop it
+/// from running right now
+fn run_request_warnings(request: &RunTestRequest, config: &Config, state: &AppState) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if state.paused.load(Ordering::SeqCst) {
+        warnings.push("New test runs are paused, /run-test would refuse this request".to_string());
+    }
+    if config.mnemonic_file.is_none() && !config.allow_placeholder_wallet {
+        warnings.push("No MNEMONIC_FILE is set and ALLOW_PLACEHOLDER_WALLET is false, so the run would be refused".to_string());
+    }
+    if let Some(step) = &request.fail_at_step {
+        warnings.push(format!("fail_at_step makes the run fail at {}", step));
+    }
+    if request.return_funds == Some(false) {
+        warnings.push("return_funds is false, so faucet funds stay in the test wallet".to_string());
+    }
+    if request.preserves_wallet() {
+        warnings.push("preserve_wallet is set, so faucet funds stay in the test wallet and the result is never archived".to_string());
+    }
+    if request.request_lava_usd == Some(false) {
+        warnings.push("request_lava_usd is false, so the loan is repaid without faucet LavaUSD".to_string());
+    }
+    warnings
+}
+
+/// Run the suite for a validated request, then archive and save the result
+async fn execute_run(
+    config: web::Data<Config>,
+    db_pool: web::Data<DbPool>,
+    state: web::Data<AppState>,
+    request: RunTestRequest,
+    parent_id: Option<String>,
+) -> HttpResponse {
+    let wallets = request.wallets.unwrap_or(1);
+    let outcome = if wallets > 1 {
+        run_multi_wallet_suite(&config, &db_pool, &request, &state, wallets, Uuid::new_v4().to_string()).await
+    } else {
+        let _permit = match state.run_slots.try_acquire() {
+            Ok(permit) => permit,
+            Err(_) => return queue_run(config, db_pool, state.clone(), request, parent_id),
+        };
+        run_test_suite(&config, &db_pool, &state, &request, Uuid::new_v4().to_string()).await
+    };
+    
+    finish_run(&config, &db_pool, &state, outcome, request, parent_id).await
+}
+
+/// Queue a single-wallet run until a slot frees up, answering 202 with its
+/// position straight away. The queued row is overwritten when the run finishes.
+fn queue_run(
+    config: web::Data<Config>,
+    db_pool: web::Data<DbPool>,
+    state: web::Data<AppState>,
+    request: RunTestRequest,
+    parent_id: Option<String>,
+) -> HttpResponse {
+    let id = Uuid::new_v4().to_string();
+    let position = match state.enqueue_run(&id, config.max_queued_runs) {
+        Some(position) => position,
+        None => {
+            return error_response(StatusCode::TOO_MANY_REQUESTS, "Maximum concurrent test runs reached and the run queue is full, try again later");
+        }
+    };
+    
+    let mut queued = TestResult::new(id.clone(), &Mnemonic::default(), "", "");
+    queued.status = "queued".to_string();
+    queued.preset = Some(request.preset.clone().unwrap_or_else(|| "default".to_string()));
+    queued.run_request = Some(request.clone());
+    queued.parent_id = parent_id.clone();
+    queued.canary = request.canary.unwrap_or(false);
+    if let Err(e) = save_test_result(&db_pool, &queued) {
+        state.dequeue_run(&id);
+        error!("Failed to save queued test result: {}", e);
+        return error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed to queue test run: {}", e));
+    }
+    info!("Queued test run {} at position {}", id, position);
+    
+    let task_id = id.clone();
+    actix_web::rt::spawn(async move {
+        // Semaphore waiters are served in order, so runs start first-in first-out
+        let _permit = match state.run_slots.acquire().await {
+            Ok(permit) => permit,
+            Err(e) => {
+                state.dequeue_run(&task_id);
+                error!("Run slots closed before queued run {} started: {}", task_id, e);
+                return;
+            }
+        };
+        if !state.dequeue_run(&task_id) {
+            info!("Queued test run {} was cancelled before it started", task_id);
+            return;
+        }
+        info!("Starting queued test run {}", task_id);
+        
+        // A run that can't start still replaces its queued row, or it would stay queued
+        let outcome = run_test_suite(&config, &db_pool, &state, &request, task_id).await.or_else(|e| {
+            error!("Queued test run {} failed: {}", queued.id, e);
+            queued.status = "failed".to_string();
+            queued.error_message = Some(format!("Test execution failed: {}", e));
+            Ok(queued)
+        });
+        finish_run(&config, &db_pool, &state, outcome, request, parent_id).await;
+    });
+    
+    HttpResponse::Accepted()
+        .content_type("application/json")
+        .body(json!({
+            "id": id,
+            "status": "queued",
+            "queue_position": position
+        }).to_string())
+}
+
+/// Client for alert webhooks, bounded by ALERT_WEBHOOK_TIMEOUT_MS so a hung webhook
+/// can't hold up saving the run
+fn alert_client(config: &Config) -> Client {
+    Client::builder()
+        .timeout(Duration::from_millis(config.alert_webhook_timeout_ms))
+        .build()
+        .expect("an HTTP client with only a timeout set always builds")
+}
+
+/// Notify ALERT_WEBHOOK_URL of a failed canary run and record the outcome in details
+async fn send_canary_alert(config: &Config, result: &mut TestResult) {
+    let url = match &config.alert_webhook_url {
+        Some(url) => url,
+        None => {
+            warn!("Canary run {} failed but ALERT_WEBHOOK_URL is not set", result.id);
+            return;
+        }
+    };
+    
+    let failed_step = result
+        .steps
+        .iter()
+        .find(|step| step.status == "failed")
+        .map(|step| step.name.clone());
+    let alert = json!({
+        "event": "canary_failed",
+        "priority": "high",
+        "id": result.id,
+        "network": result.run_request.as_ref().and_then(|request| request.network.clone()).unwrap_or_else(|| config.network.clone()),
+        "failed_step": failed_step,
+        "error": result.error_message,
+        "timestamp": chrono::Utc::now().to_rfc3339()
+    });
+    
+    let sent = match alert_client(config).post(url).json(&alert).send().await {
+        Ok(response) if response.status().is_success() => Ok(()),
+        Ok(response) => Err(format!("Alert webhook returned {}", response.status())),
+        Err(e) => Err(format!("Alert webhook request failed: {}", e)),
+    };
+    
+    match sent {
+        Ok(()) => {
+            warn!("Canary run {} failed, alert sent", result.id);
+            result.record_detail("canary_alert", json!({ "sent": true }));
+        }
+        Err(e) => {
+            error!("Canary run {} failed and the alert could not be sent: {}", result.id, e);
+            result.record_detail("canary_alert", json!({ "sent": false, "error": e }));
+        }
+    }
+}
+
+/// Run POST_RUN_HOOK with the result JSON (every mnemonic redacted) on stdin. Best effort:
+/// failures and timeouts are logged and recorded in details, never fail the run.
+async fn run_post_run_hook(hook: &str, timeout_ms: u64, result: &mut TestResult) {
+    let payload = result_without_mnemonics(result);
+    
+    let started = Instant::now();
+    let outcome = tokio::time::timeout(Duration::from_millis(timeout_ms), spawn_hook(hook, payload.to_string())).await;
+    let duration_ms = started.elapsed().as_millis() as u64;
+    
+    let details = match outcome {
+        Ok(Ok(exit_code)) => {
+            if exit_code != Some(0) {
+                warn!("Post-run hook {} exited with {:?}", hook, exit_code);
+            }
+            json!({ "exit_code": exit_code, "duration_ms": duration_ms })
+        }
+        Ok(Err(e)) => {
+            warn!("Post-run hook {} failed: {}", hook, e);
+            json!({ "exit_code": null, "error": e.to_string(), "duration_ms": duration_ms })
+        }
+        Err(_) => {
+            warn!("Post-run hook {} timed out after {}ms and was killed", hook, timeout_ms);
+            json!({ "exit_code": null, "timed_out": true, "duration_ms": duration_ms })
+        }
+    };
+    result.record_detail("post_run_hook", details);
+}
+
+/// Spawn the hook, write `input` to its stdin and wait for its exit code.
+/// The child is killed if this future is dropped, e.g. on timeout.
+async fn spawn_hook(hook: &str, input: String) -> Result<Option<i32>> {
+    use tokio::io::AsyncWriteExt;
+    
+    let mut child = tokio::process::Command::new(hook)
+        .stdin(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that exits without reading stdin closes the pipe; that's not an error
+        if let Err(e) = stdin.write_all(input.as_bytes()).await {
+            if e.kind() != std::io::ErrorKind::BrokenPipe {
+                return Err(e.into());
+            }
+        }
+    }
+    
+    Ok(child.wait().await?.code())
+}
+
+/// Archive and save a finished run and build its response
+async fn finish_run(
+    config: &Config,
+    db_pool: &DbPool,
+    state: &AppState,
+    outcome: Result<TestResult>,
+    request: RunTestRequest,
+    parent_id: Option<String>,
+) -> HttpResponse {
+    match outcome {
+        Ok(mut result) => {
+            result.canary = request.canary.unwrap_or(false);
+            result.run_request = Some(request);
+            result.parent_id = parent_id;
+            
+            if result.canary && result.status == "failed" {
+                send_canary_alert(config, &mut result).await;
+            }
+            
+            if let Some(hook) = &config.post_run_hook {
+                run_post_run_hook(hook, config.post_run_hook_timeout_ms, &mut result).await;
+            }
+            
+            if config.s3_bucket.is_some() {
+                archive_result(config, &mut result).await;
+            }
+            
+            // Save test to data/test_results.db
+            result.cap_details(config.max_details_bytes);
+            if let Err(e) = save_test_result(db_pool, &result) {
+                error!("Failed to save test result to database: {}", e);
+            }
+            state.result_cache.invalidate(&result.id);
+            
+            let json = serde_json::to_string_pretty(&redacted_result(&result, &config.redaction_rules)).unwrap_or_default();
+            HttpResponse::Ok()
+                .content_type("application/json")
+                .body(json)
+        }
+        Err(e) => {
+            error!("Test suite execution failed: {}", e);
+            HttpResponse::InternalServerError()
+                .content_type("application/json")
+                .body(json!({
+                    "error": format!("Test execution failed: {}", e)
+                }).to_string())
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ResultsQuery {
+    include_archived: Option<bool>,
+    status: Option<String>,
+    /// ISO 8601 timestamp or date, inclusive
+    from: Option<String>,
+    /// ISO 8601 timestamp or date, inclusive
+    to: Option<String>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+    /// Only runs whose loan LTV is at least this, in basis points
+    min_ltv_bp: Option<u32>,
+    /// Only runs failed by a step whose CLI invocation exited with this code
+    cli_exit_code: Option<i32>,
+}
+
+/// Parse an RFC 3339 timestamp, or a bare date as the start or end of that day, into the
+/// UTC form results are stored with
+fn parse_range_bound(value: &str, end_of_day: bool) -> std::result::Result<String, String> {
+    if let Ok(timestamp) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(timestamp.with_timezone(&chrono::Utc).to_rfc3339());
+    }
+    
+    let date = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| format!("Invalid timestamp '{}', expected RFC 3339 or YYYY-MM-DD", value))?;
+    let time = if end_of_day {
+        chrono::NaiveTime::from_hms_nano_opt(23, 59, 59, 999_999_999)
+    } else {
+        chrono::NaiveTime::from_hms_opt(0, 0, 0)
+    };
+    let timestamp = chrono::DateTime::<chrono::Utc>::from_naive_utc_and_offset(date.and_time(time.unwrap_or_default()), chrono::Utc);
+    Ok(timestamp.to_rfc3339())
+}
+
+/// Turn /results query params into a filter, checking the date range
+fn results_filter(query: &ResultsQuery, max_rows: u32) -> std::result::Result<ResultFilter, String> {
+    let from = query.from.as_deref().map(|v| parse_range_bound(v, false)).transpose()?;
+    let to = query.to.as_deref().map(|v| parse_range_bound(v, true)).transpose()?;
+    
+    if let (Some(from), Some(to)) = (&from, &to) {
+        let from_time = chrono::DateTime::parse_from_rfc3339(from).map_err(|e| e.to_string())?;
+        let to_time = chrono::DateTime::parse_from_rfc3339(to).map_err(|e| e.to_string())?;
+        if from_time > to_time {
+            return Err("from must not be after to".to_string());
+        }
+    }
+    
+    Ok(ResultFilter {
+        status: query.status.clone(),
+        from,
+        to,
+        include_archived: query.include_archived.unwrap_or(false),
+        limit: Some(query.limit.unwrap_or(max_rows).clamp(1, max_rows)),
+        offset: query.offset.unwrap_or(0),
+        min_ltv_bp: query.min_ltv_bp,
+        cli_exit_code: query.cli_exit_code,
+    })
+}
+
+// GET test results, optionally filtered by status and date range and paginated.
+// Archived results only when asked for. At most MAX_EXPORT_ROWS per response; when
+// more match, X-Results-Truncated and X-Next-Offset point at the next page.
+async fn get_results_handler(
+    query: web::Query<ResultsQuery>,
+    db_pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+) -> impl Responder {
+    let mut filter = match results_filter(&query, config.max_export_rows) {
+        Ok(filter) => filter,
+        Err(message) => return error_response(StatusCode::BAD_REQUEST, &message),
+    };
+    
+    // Fetch one extra row to tell whether there's another page
+    let page_size = filter.limit.unwrap_or(config.max_export_rows);
+    filter.limit = Some(page_size + 1);
+    
+    match with_busy_retry(|| get_test_results(&db_pool, &filter)).await {
+        Ok(mut results) => {
+            let truncated = results.len() > page_size as usize;
+            results.truncate(page_size as usize);
+            
+            let mut response = HttpResponse::Ok();
+            response.content_type("application/json");
+            if truncated {
+                response.insert_header(("X-Results-Truncated", "true"));
+                response.insert_header(("X-Next-Offset", (filter.offset + page_size).to_string()));
+            }
+            let results: Vec<Value> = results.iter().map(|r| redacted_result(r, &config.redaction_rules)).collect();
+            response.body(serde_json::to_string_pretty(&results).unwrap_or_default())
+        }
+        Err(e) => {
+            error!("Failed to get test results: {}", e);
+            HttpResponse::InternalServerError()
+                .content_type("application/json")
+                .body(json!({
+                    "error": format!("Failed to get test results: {}", e)
+                }).to_string())
+        }
+    }
+}
+
+// GET specific test result by ID
+async fn get_result_by_id_handler(
+    path: web::Path<String>,
+    db_pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let id = path.into_inner();
+    if let Some(json) = state.result_cache.get(&id) {
+        return HttpResponse::Ok().content_type("application/json").body(json);
+    }
+    
+    match with_busy_retry(|| get_test_result_by_id(&db_pool, &id)).await {
+        Ok(Some(mut result)) => {
+            let json = if result.status == "queued" || state.live_runs.is_live(&id) {
+                // Position and progress change as runs move on, so those results aren't cached
+                result.queue_position = state.queue_position(&id);
+                result.current_step = state.live_runs.current_step(&id);
+                result.cli_output = state.live_runs.lines(&id);
+                serde_json::to_string_pretty(&redacted_result(&result, &config.redaction_rules)).unwrap_or_default()
+            } else {
+                let json = serde_json::to_string_pretty(&redacted_result(&result, &config.redaction_rules)).unwrap_or_default();
+                state.result_cache.put(&id, json.clone());
+                json
+            };
+            HttpResponse::Ok()
+                .content_type("application/json")
+                .body(json)
+        }
+        Ok(None) => {
+            HttpResponse::NotFound()
+                .content_type("application/json")
+                .body(json!({
+                    "error": format!("Test result with ID {} not found", id)
+                }).to_string())
+        }
+        Err(e) => {
+            error!("Failed to get test result: {}", e);
+            HttpResponse::InternalServerError()
+                .content_type("application/json")
+                .body(json!({
+                    "error": format!("Failed to get test result: {}", e)
+                }).to_string())
+        }
+    }
+}
+
+// GET a running test's CLI output over a WebSocket: the lines printed so far, then each new
+// one as it's printed, closing when the run ends
+async fn result_ws_handler(
+    req: HttpRequest,
+    body: web::Payload,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> actix_web::Result<HttpResponse> {
+    use tokio::sync::broadcast::error::RecvError;
+    
+    let id = path.into_inner();
+    let Some((backlog, mut lines)) = state.live_runs.subscribe(&id) else {
+        if state.queue_position(&id).is_some() {
+            return Ok(error_response(StatusCode::CONFLICT, &format!("Test run {} is queued and hasn't started yet", id)));
+        }
+        return Ok(error_response(StatusCode::NOT_FOUND, &format!("Test run {} isn't running", id)));
+    };
+    let (response, mut session, mut messages) = actix_ws::handle(&req, body)?;
+    
+    actix_web::rt::spawn(async move {
+        for line in backlog {
+            if session.text(line).await.is_err() {
+                return;
+            }
+        }
+        loop {
+            tokio::select! {
+                line = lines.recv() => {
+                    let sent = match line {
+                        Ok(line) => session.text(line).await,
+                        Err(RecvError::Lagged(skipped)) => session.text(format!("[{} lines skipped]", skipped)).await,
+                        Err(RecvError::Closed) => break,
+                    };
+                    if sent.is_err() {
+                        return;
+                    }
+                }
+                message = messages.recv() => match message {
+                    Some(Ok(actix_ws::Message::Ping(bytes))) => {
+                        if session.pong(&bytes).await.is_err() {
+                            return;
+                        }
+                    }
+                    Some(Ok(actix_ws::Message::Close(_))) | Some(Err(_)) | None => return,
+                    Some(Ok(_)) => {}
+                },
+            }
+        }
+        let _ = session.close(Some(actix_ws::CloseCode::Normal.into())).await;
+    });
+    Ok(response)
+}
+
+// GET a result as a JUnit XML report for CI. A multi-wallet run becomes one testsuite per wallet.
+async fn result_junit_handler(path: web::Path<String>, db_pool: web::Data<DbPool>) -> impl Responder {
+    let id = path.into_inner();
+    match with_busy_retry(|| get_test_result_by_id(&db_pool, &id)).await {
+        Ok(Some(result)) => {
+            let wallets = result
+                .details
+                .as_ref()
+                .and_then(|details| details.get("wallets"))
+                .and_then(|wallets| serde_json::from_value::<Vec<TestResult>>(wallets.clone()).ok());
+            let xml = match wallets {
+                Some(wallets) => report::render_junit(&wallets),
+                None => report::render_junit(std::slice::from_ref(&result)),
+            };
+            HttpResponse::Ok().content_type("application/xml; charset=utf-8").body(xml)
+        }
+        Ok(None) => error_response(StatusCode::NOT_FOUND, &format!("Test result with ID {} not found", id)),
+        Err(e) => {
+            error!("Failed to get test result: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed to get test result: {}", e))
+        }
+    }
+}
+
+// GET the results matching the /results filters as one JUnit XML report, a testsuite per run
+async fn results_junit_handler(
+    query: web::Query<ResultsQuery>,
+    db_pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+) -> impl Responder {
+    let filter = match results_filter(&query, config.max_export_rows) {
+        Ok(filter) => filter,
+        Err(message) => return error_response(StatusCode::BAD_REQUEST, &message),
+    };
+    
+    match with_busy_retry(|| get_test_results(&db_pool, &filter)).await {
+        Ok(results) => HttpResponse::Ok()
+            .content_type("application/xml; charset=utf-8")
+            .body(report::render_junit(&results)),
+        Err(e) => {
+            error!("Failed to get test results: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed to get test results: {}", e))
+        }
+    }
+}
+
+/// Explorer for the network a result ran on, falling back to the default network's
+fn report_explorer_url(config: &Config, result: &TestResult) -> String {
+    let network = result.run_request.as_ref().and_then(|request| request.network.as_deref());
+    config
+        .for_network(network)
+        .map(|network_config| network_config.explorer_url)
+        .unwrap_or_else(|| config.explorer_url.clone())
+}
+
+// GET a result as a standalone HTML report, wallet mnemonic redacted
+async fn result_report_handler(
+    path: web::Path<String>,
+    db_pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+) -> impl Responder {
+    let id = path.into_inner();
+    match with_busy_retry(|| get_test_result_by_id(&db_pool, &id)).await {
+        Ok(Some(result)) => {
+            HttpResponse::Ok()
+                .content_type("text/html; charset=utf-8")
+                .body(report::render_html(&result, &report_explorer_url(&config, &result)))
+        }
+        Ok(None) => error_response(StatusCode::NOT_FOUND, &format!("Test result with ID {} not found", id)),
+        Err(e) => {
+            error!("Failed to get test result: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed to get test result: {}", e))
+        }
+    }
+}
+
+// POST re-run a stored result's inputs with a fresh wallet, linked via parent_id
+async fn replay_result_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    db_pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Some(response) = check_api_key(&req, &config) {
+        return response;
+    }
+    if state.paused.load(Ordering::SeqCst) {
+        return error_response(StatusCode::SERVICE_UNAVAILABLE, "New test runs are paused");
+    }
+    
+    let id = path.into_inner();
+    let original = match with_busy_retry(|| get_test_result_by_id(&db_pool, &id)).await {
+        Ok(Some(result)) => result,
+        Ok(None) => {
+            return error_response(StatusCode::NOT_FOUND, &format!("Test result with ID {} not found", id));
+        }
+        Err(e) => {
+            error!("Failed to get test result: {}", e);
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed to get test result: {}", e));
+        }
+    };
+    
+    // Results saved before requests were stored only carry their preset
+    let request = original.run_request.clone().unwrap_or_else(|| RunTestRequest {
+        preset: original.preset.clone(),
+        ..RunTestRequest::default()
+    });
+    if let Err(message) = validate_run_request(&request, &config) {
+        return error_response(StatusCode::CONFLICT, &format!("Can't replay {}: {}", id, message));
+    }
+    
+    info!("Replaying test result {}", id);
+    execute_run(config, db_pool, state, request, Some(id)).await
+}
+
+// GET a run's wallet in an importable form. Sensitive: guarded and off by default.
+async fn wallet_export_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    db_pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+) -> impl Responder {
+    if let Some(response) = check_api_key(&req, &config) {
+        return response;
+    }
+    if !config.expose_mnemonic {
+        return error_response(StatusCode::FORBIDDEN, "Wallet export is disabled; set EXPOSE_MNEMONIC=true to enable it");
+    }
+    
+    let id = path.into_inner();
+    let result = match with_busy_retry(|| get_test_result_by_id(&db_pool, &id)).await {
+        Ok(Some(result)) => result,
+        Ok(None) => {
+            return error_response(StatusCode::NOT_FOUND, &format!("Test result with ID {} not found", id));
+        }
+        Err(e) => {
+            error!("Failed to get test result: {}", e);
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed to get test result: {}", e));
+        }
+    };
+    
+    warn!("Exporting wallet for test result {}", id);
+    
+    let account_index = result.run_request.as_ref().and_then(|request| request.account_index).unwrap_or(0);
+    let account_path = btc_account_path(account_index);
+    let export = json!({
+        "sensitive": true,
+        "warning": "Contains the wallet mnemonic. Anyone holding it controls the funds; do not share or store it.",
+        "result_id": result.id,
+        "preserved_wallet": result.preserved_wallet,
+        "format": "bip39",
+        "mnemonic": result.mnemonic.expose(),
+        "passphrase": "",
+        "btc": {
+            "network": "testnet",
+            "address": result.btc_address,
+            "script_type": "p2wpkh",
+            "account_path": account_path,
+            "receive_path": format!("{}/0/*", account_path),
+            "change_path": format!("{}/1/*", account_path)
+        },
+        "lava_usd": {
+            "pubkey": result.lava_usd_pubkey,
+            "derivation_path": lava_usd_derivation_path(account_index)
+        }
+    });
+    
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .insert_header(("Cache-Control", "no-store"))
+        .body(serde_json::to_string_pretty(&export).unwrap_or_default())
+}
+
+// POST re-fetch contract details for a stored result
+async fn refresh_result_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    db_pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Some(response) = check_api_key(&req, &config) {
+        return response;
+    }
+    
+    let id = path.into_inner();
+    let mut result = match with_busy_retry(|| get_test_result_by_id(&db_pool, &id)).await {
+        Ok(Some(result)) => result,
+        Ok(None) => {
+            return error_response(StatusCode::NOT_FOUND, &format!("Test result with ID {} not found", id));
+        }
+        Err(e) => {
+            error!("Failed to get test result: {}", e);
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed to get test result: {}", e));
+        }
+    };
+    
+    let contract_id = match result.loan_contract_id.clone() {
+        Some(contract_id) => contract_id,
+        None => {
+            return error_response(StatusCode::CONFLICT, &format!("Test result {} has no loan contract to refresh", id));
+        }
+    };
+    
+    let terms = config.loan_terms(result.preset.as_deref()).unwrap_or_default();
+    match get_contract_details(&state, result.mnemonic.expose(), &contract_id, &terms, true).await {
+        Ok(details) => apply_contract_details(&mut result, details),
+        Err(e) => {
+            error!("Failed to refresh contract details: {}", e);
+            return error_response(StatusCode::BAD_GATEWAY, &format!("Failed to refresh contract details: {}", e));
+        }
+    }
+    
+    result.cap_details(config.max_details_bytes);
+    if let Err(e) = save_test_result(&db_pool, &result) {
+        error!("Failed to save refreshed test result: {}", e);
+        return error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed to save test result: {}", e));
+    }
+    state.result_cache.invalidate(&id);
+    
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .body(serde_json::to_string_pretty(&redacted_result(&result, &config.redaction_rules)).unwrap_or_default())
+}
+
+// POST re-judge a stored result against the current CLOSED_LOAN_REQUIRED_PATHS without re-running it
+async fn reevaluate_result_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    db_pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Some(response) = check_api_key(&req, &config) {
+        return response;
+    }
+    
+    let id = path.into_inner();
+    let mut result = match with_busy_retry(|| get_test_result_by_id(&db_pool, &id)).await {
+        Ok(Some(result)) => result,
+        Ok(None) => {
+            return error_response(StatusCode::NOT_FOUND, &format!("Test result with ID {} not found", id));
+        }
+        Err(e) => {
+            error!("Failed to get test result: {}", e);
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed to get test result: {}", e));
+        }
+    };
+    
+    // Only the final judgment is re-run; runs that stopped at a step or never ran stay as they are
+    let error_kind = result.details.as_ref().and_then(|d| d.get("error_kind")).and_then(|k| k.as_str());
+    if let Some(kind) = error_kind.filter(|kind| *kind != TestError::LoanNotClosed.info().error_kind) {
+        return error_response(StatusCode::CONFLICT, &format!("Test result {} failed during execution ({}), nothing to re-evaluate", id, kind));
+    }
+    if result.status != "success" && result.status != "failed" {
+        return error_response(StatusCode::CONFLICT, &format!("Test result {} is {}, nothing to re-evaluate", id, result.status));
+    }
+    if result.details_truncated {
+        return error_response(StatusCode::CONFLICT, &format!("Test result {} has truncated details and can't be re-evaluated", id));
+    }
+    if result.details.as_ref().is_some_and(|d| d.get("wallets").is_some()) {
+        return error_response(StatusCode::CONFLICT, &format!("Test result {} is a multi-wallet run; re-evaluate its wallet results instead", id));
+    }
+    
+    let previous_status = result.status.clone();
+    let required_paths = required_closure_paths(&config, result.details.as_ref());
+    let (status, reasons) = evaluate_result(result.details.as_ref(), required_paths);
+    if status == "success" {
+        result.status = status;
+        result.error_message = None;
+        if let Some(map) = result.details.as_mut().and_then(Value::as_object_mut) {
+            map.remove("error_kind");
+            map.remove("remediation");
+        }
+    } else {
+        result.fail(TestError::LoanNotClosed, format!("Loan was not properly closed: {}", reasons.join("; ")));
+    }
+    result.record_detail("reevaluation", json!({
+        "previous_status": previous_status,
+        "status": result.status,
+        "reasons": reasons,
+        "required_paths": required_paths,
+        "evaluated_at": chrono::Utc::now().to_rfc3339()
+    }));
+    info!("Re-evaluated test result {}: {} -> {}", id, previous_status, result.status);
+    
+    if let Err(e) = save_test_result(&db_pool, &result) {
+        error!("Failed to save re-evaluated test result: {}", e);
+        return error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed to save test result: {}", e));
+    }
+    state.result_cache.invalidate(&id);
+    
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .body(serde_json::to_string_pretty(&redacted_result(&result, &config.redaction_rules)).unwrap_or_default())
+}
+
+// GET Prometheus metrics of finished runs, labeled by network, status and error kind
+async fn metrics_handler(state: web::Data<AppState>) -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(state.run_metrics.render())
+}
+
+// GET aggregate stats across all test results
+async fn get_stats_handler(db_pool: web::Data<DbPool>, state: web::Data<AppState>, config: web::Data<Config>) -> impl Responder {
+    match get_run_stats(&db_pool) {
+        Ok(stats) => {
+            let mut body = serde_json::to_value(&stats).unwrap_or_else(|_| json!({}));
+            body["paused"] = json!(state.paused.load(Ordering::SeqCst));
+            body["faucet_health"] = json!(state.faucet_health.snapshot());
+            body["faucet_rate_limits"] = json!(state.faucet_health.rate_limits());
+            body["rolling_success_rate"] = json!(state.success_rate.snapshot());
+            match daily_budget_snapshot(&config, &db_pool) {
+                Ok(budget) => body["daily_budget"] = budget,
+                Err(e) => warn!("Failed to get the daily faucet budget: {}", e),
+            }
+            
+            HttpResponse::Ok()
+                .content_type("application/json")
+                .body(serde_json::to_string_pretty(&body).unwrap_or_default())
+        }
+        Err(e) => {
+            error!("Failed to get stats: {}", e);
+            HttpResponse::InternalServerError()
+                .content_type("application/json")
+                .body(json!({
+                    "error": format!("Failed to get stats: {}", e)
+                }).to_string())
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GateQuery {
+    window: Option<u32>,
+    min_success_rate: Option<f64>,
+}
+
+// GET success-rate gate over recent runs: 200 when met, 412 otherwise
+async fn stats_gate_handler(query: web::Query<GateQuery>, db_pool: web::Data<DbPool>) -> impl Responder {
+    let window = query.window.unwrap_or(20).clamp(1, 1000);
+    let min_success_rate = query.min_success_rate.unwrap_or(0.9);
+    if !(0.0..=1.0).contains(&min_success_rate) {
+        return error_response(StatusCode::BAD_REQUEST, "min_success_rate must be between 0 and 1");
+    }
+    
+    let (runs, successful_runs) = match get_recent_success_counts(&db_pool, window) {
+        Ok(counts) => counts,
+        Err(e) => {
+            error!("Failed to get recent results: {}", e);
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed to get recent results: {}", e));
+        }
+    };
+    
+    // No runs means nothing to vouch for, so the gate fails
+    let success_rate = if runs > 0 { successful_runs as f64 / runs as f64 } else { 0.0 };
+    let passed = runs > 0 && success_rate >= min_success_rate;
+    let status = if passed { StatusCode::OK } else { StatusCode::PRECONDITION_FAILED };
+    
+    HttpResponse::build(status)
+        .content_type("application/json")
+        .body(serde_json::to_string_pretty(&json!({
+            "passed": passed,
+            "window": window,
+            "runs": runs,
+            "successful_runs": successful_runs,
+            "success_rate": success_rate,
+            "min_success_rate": min_success_rate
+        })).unwrap_or_default())
+}
+
+// GET effective config with secrets redacted
+async fn get_config_handler(req: HttpRequest, config: web::Data<Config>) -> impl Responder {
+    if let Some(response) = check_api_key(&req, &config) {
+        return response;
+    }
+    
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .body(serde_json::to_string_pretty(&config.redacted()).unwrap_or_default())
+}
+
+// POST write an online backup of the results database
+async fn backup_handler(req: HttpRequest, db_pool: web::Data<DbPool>, config: web::Data<Config>) -> impl Responder {
+    if let Some(response) = check_api_key(&req, &config) {
+        return response;
+    }
+    
+    match backup_database(&db_pool, &config.backup_dir) {
+        Ok(path) => {
+            HttpResponse::Ok()
+                .content_type("application/json")
+                .body(json!({ "path": path }).to_string())
+        }
+        Err(e) => {
+            error!("Failed to back up database: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed to back up database: {}", e))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PageQuery {
+    limit: Option<u32>,
+    offset: Option<u32>,
+}
+
+// GET the catalog of run error kinds with descriptions and remediation
+async fn get_errors_handler() -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .body(serde_json::to_string_pretty(&errors::catalog()).unwrap_or_default())
+}
+
+// GET canary runs, newest first
+async fn get_canary_results_handler(
+    query: web::Query<PageQuery>,
+    db_pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+) -> impl Responder {
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let offset = query.offset.unwrap_or(0);
+    
+    match with_busy_retry(|| get_canary_results(&db_pool, limit, offset)).await {
+        Ok(results) => {
+            let results: Vec<Value> = results.iter().map(|r| redacted_result(r, &config.redaction_rules)).collect();
+            HttpResponse::Ok()
+                .content_type("application/json")
+                .body(serde_json::to_string_pretty(&json!({
+                    "limit": limit,
+                    "offset": offset,
+                    "results": results
+                })).unwrap_or_default())
+        }
+        Err(e) => {
+            error!("Failed to get canary results: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed to get canary results: {}", e))
+        }
+    }
+}
+
+// GET distinct wallets used by the suite with run counts
+async fn get_wallets_handler(query: web::Query<PageQuery>, db_pool: web::Data<DbPool>) -> impl Responder {
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let offset = query.offset.unwrap_or(0);
+    
+    match get_wallets(&db_pool, limit, offset) {
+        Ok(wallets) => {
+            HttpResponse::Ok()
+                .content_type("application/json")
+                .body(serde_json::to_string_pretty(&json!({
+                    "limit": limit,
+                    "offset": offset,
+                    "wallets": wallets
+                })).unwrap_or_default())
+        }
+        Err(e) => {
+            error!("Failed to get wallets: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed to get wallets: {}", e))
+        }
+    }
+}
+
+// GET paginated audit log of mutating API calls
+async fn get_audit_handler(
+    req: HttpRequest,
+    query: web::Query<PageQuery>,
+    db_pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+) -> impl Responder {
+    if let Some(response) = check_api_key(&req, &config) {
+        return response;
+    }
+    
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let offset = query.offset.unwrap_or(0);
+    
+    match get_audit_entries(&db_pool, limit, offset) {
+        Ok(entries) => {
+            HttpResponse::Ok()
+                .content_type("application/json")
+                .body(serde_json::to_string_pretty(&json!({
+                    "limit": limit,
+                    "offset": offset,
+                    "entries": entries
+                })).unwrap_or_default())
+        }
+        Err(e) => {
+            error!("Failed to get audit log: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed to get audit log: {}", e))
+        }
+    }
+}
+
+// POST stop accepting new runs without affecting reads or in-flight runs
+async fn pause_handler(req: HttpRequest, config: web::Data<Config>, state: web::Data<AppState>) -> impl Responder {
+    set_paused(&req, &config, &state, true)
+}
+
+// POST resume accepting new runs
+async fn resume_handler(req: HttpRequest, config: web::Data<Config>, state: web::Data<AppState>) -> impl Responder {
+    set_paused(&req, &config, &state, false)
+}
+
+// POST cancel every queued run that hasn't started; in-flight runs carry on
+async fn cancel_queued_handler(
+    req: HttpRequest,
+    db_pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Some(response) = check_api_key(&req, &config) {
+        return response;
+    }
+    
+    let ids = state.cancel_queued_runs();
+    for id in &ids {
+        if let Err(e) = cancel_queued_result(&db_pool, id) {
+            error!("Failed to mark queued run {} cancelled: {}", id, e);
+        }
+        state.result_cache.invalidate(id);
+    }
+    info!("Cancelled {} queued test runs", ids.len());
+    
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .body(json!({ "cancelled": ids.len(), "ids": ids }).to_string())
+}
+
+/// Toggle the paused flag behind the API key
+fn set_paused(req: &HttpRequest, config: &Config, state: &AppState, paused: bool) -> HttpResponse {
+    if let Some(response) = check_api_key(req, config) {
+        return response;
+    }
+    
+    state.paused.store(paused, Ordering::SeqCst);
+    info!("New test runs {}", if paused { "paused" } else { "resumed" });
+    
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .body(json!({ "paused": paused }).to_string())
+}
+
+#[derive(Debug, Deserialize)]
+struct LogLevelRequest {
+    level: String,
+}
+
+// POST change the log level without a restart
+async fn log_level_handler(
+    req: HttpRequest,
+    body: web::Json<LogLevelRequest>,
+    config: web::Data<Config>,
+) -> impl Responder {
+    if let Some(response) = check_api_key(&req, &config) {
+        return response;
+    }
+    
+    let level = match body.level.parse::<log::LevelFilter>() {
+        Ok(level) => level,
+        Err(_) => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                &format!("Unknown log level '{}', expected off, error, warn, info, debug or trace", body.level),
+            );
+        }
+    };
+    
+    logging::set_level(level);
+    info!("Log level changed to {}", level);
+    
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .body(json!({ "level": level.to_string().to_lowercase() }).to_string())
+}
+
+/// List log files in the CLI work dir, excluding wallet/key material
+fn collect_cli_log_files(cli_work_dir: &str) -> Result<Vec<String>> {
+    let mut files = Vec::new();
+    
+    for entry in std::fs::read_dir(cli_work_dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        
+        let name = entry.file_name().to_string_lossy().to_string();
+        let lower = name.to_lowercase();
+        if !lower.ends_with(".log") || SENSITIVE_FILE_MARKERS.iter().any(|marker| lower.contains(marker)) {
+            continue;
+        }
+        files.push(name);
+    }
+    
+    files.sort();
+    Ok(files)
+}
+
+// GET a tar.gz of the CLI's log files
+async fn cli_logs_handler(req: HttpRequest, config: web::Data<Config>) -> impl Responder {
+    if let Some(response) = check_api_key(&req, &config) {
+        return response;
+    }
+    
+    let files = match collect_cli_log_files(&config.cli_work_dir) {
+        Ok(files) => files,
+        Err(e) => {
+            error!("Failed to list CLI logs in {}: {}", config.cli_work_dir, e);
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed to list CLI logs: {}", e));
+        }
+    };
+    
+    if files.is_empty() {
+        return error_response(StatusCode::NOT_FOUND, &format!("No CLI log files found in {}", config.cli_work_dir));
+    }
+    
+    let archive = match spawn_cli_log_archive(&config.cli_work_dir, &files) {
+        Ok(archive) => archive,
+        Err(e) => {
+            error!("Failed to run tar: {}", e);
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed to archive CLI logs: {}", e));
+        }
+    };
+    
+    HttpResponse::Ok()
+        .content_type("application/gzip")
+        .insert_header(("Content-Disposition", "attachment; filename=\"cli-logs.tar.gz\""))
+        .streaming(archive)
+}
+
+/// Run tar over the files and stream its gzipped output as it's produced, so a large
+/// archive is neither held in memory nor waited on by a worker thread. tar is killed
+/// if the client goes away; a tar failure ends the stream with an error, aborting the
+/// download instead of passing off a partial archive as complete.
+fn spawn_cli_log_archive(
+    cli_work_dir: &str,
+    files: &[String],
+) -> std::io::Result<impl futures::Stream<Item = std::io::Result<web::Bytes>>> {
+    use tokio::io::AsyncReadExt;
+    
+    let mut child = tokio::process::Command::new("tar")
+        .arg("-czf")
+        .arg("-")
+        .arg("-C")
+        .arg(cli_work_dir)
+        .args(files)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let stdout = child.stdout.take().ok_or_else(|| std::io::Error::other("tar stdout wasn't piped"))?;
+    
+    Ok(futures::stream::unfold(Some((child, stdout)), |state| async move {
+        let (child, mut stdout) = state?;
+        let mut chunk = vec![0u8; 64 * 1024];
+        match stdout.read(&mut chunk).await {
+            Ok(0) => match child.wait_with_output().await {
+                Ok(output) if output.status.success() => None,
+                Ok(output) => {
+                    let stderr = sanitize_output(&output.stderr);
+                    error!("Failed to archive CLI logs: {}", stderr);
+                    Some((Err(std::io::Error::other(format!("tar failed: {}", stderr))), None))
+                }
+                Err(e) => {
+                    error!("Failed to wait for tar: {}", e);
+                    Some((Err(e), None))
+                }
+            },
+            Ok(read) => {
+                chunk.truncate(read);
+                Some((Ok(web::Bytes::from(chunk)), Some((child, stdout))))
+            }
+            Err(e) => {
+                error!("Failed to read tar output: {}", e);
+                Some((Err(e), None))
+            }
+        }
+    }))
+}
+
+// Server status
+async fn health_check(state: web::Data<AppState>) -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .body(json!({
+            "status": "Server is running",
+            "paused": state.paused.load(Ordering::SeqCst),
+            "faucets": if state.faucet_health.is_degraded() { "degraded" } else { "ok" }
+        }).to_string())
+}
+
+/// Periodic maintenance: archive old results under the retention policy
+async fn run_sweep(config: web::Data<Config>, db_pool: web::Data<DbPool>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(config.sweep_interval_secs));
+    loop {
+        interval.tick().await;
+        
+        let cutoff = |hours: u64| (chrono::Utc::now() - chrono::Duration::hours(hours as i64)).to_rfc3339();
+        let success_before = config.archive_success_after_hours.map(cutoff);
+        let failed_before = config.archive_failed_after_hours.map(cutoff);
+        
+        match archive_old_results(&db_pool, success_before.as_deref(), failed_before.as_deref()) {
+            Ok(0) => {}
+            Ok(count) => info!("Archived {} old test results", count),
+            Err(e) => warn!("Result archive sweep failed: {}", e),
+        }
+    }
+}
+
+/// Check each faucet is reachable without minting: a GET of the faucet URL, where any
+/// response below 500 counts as up
+async fn probe_faucets(config: &Config, db_pool: &DbPool) {
+    let client = match faucet_client(config) {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("Failed to build the faucet probe client: {}", e);
+            return;
+        }
+    };
+    
+    for (faucet, url) in [("btc", &config.btc_faucet_url), ("lava_usd", &config.lava_usd_faucet_url)] {
+        let started = Instant::now();
+        let outcome = client
+            .get(url)
+            .timeout(Duration::from_millis(config.connectivity_probe_timeout_ms))
+            .send()
+            .await;
+        let (status, http_status, error) = match outcome {
+            Ok(response) if response.status().is_server_error() => {
+                ("degraded", Some(response.status().as_u16()), None)
+            }
+            Ok(response) => ("up", Some(response.status().as_u16()), None),
+            Err(e) => ("down", None, Some(e.to_string())),
+        };
+        if status != "up" {
+            warn!("Faucet probe: {} faucet is {}", faucet, status);
+        }
+        
+        let probe = FaucetProbe {
+            faucet: faucet.to_string(),
+            status: status.to_string(),
+            http_status,
+            latency_ms: started.elapsed().as_millis() as u64,
+            error,
+            checked_at: chrono::Utc::now().to_rfc3339(),
+        };
+        if let Err(e) = save_faucet_probe(db_pool, &probe, config.faucet_probe_history) {
+            warn!("Failed to record faucet probe: {}", e);
+        }
+    }
+}
+
+/// Check the success rate over the last SUCCESS_RATE_ALERT_WINDOW runs, alerting
+/// ALERT_WEBHOOK_URL once it has stayed below `threshold` for long enough
+async fn check_success_rate(config: &Config, db_pool: &DbPool, state: &AppState, threshold: f64) {
+    let window = config.success_rate_alert_window;
+    let (runs, successful_runs) = match get_recent_success_counts(db_pool, window) {
+        Ok(counts) => counts,
+        Err(e) => {
+            warn!("Failed to check the rolling success rate: {}", e);
+            return;
+        }
+    };
+    if !state.success_rate.observe(window, runs, successful_runs, threshold, config.success_rate_alert_sustain_checks) {
+        return;
+    }
+    
+    let success_rate = successful_runs as f64 / runs as f64;
+    warn!("Success rate over the last {} runs is {:.2}, below {}", runs, success_rate, threshold);
+    let url = match &config.alert_webhook_url {
+        Some(url) => url,
+        None => {
+            warn!("Success rate is low but ALERT_WEBHOOK_URL is not set");
+            return;
+        }
+    };
+    let alert = json!({
+        "event": "success_rate_low",
+        "priority": "high",
+        "success_rate": success_rate,
+        "threshold": threshold,
+        "window": window,
+        "runs": runs,
+        "successful_runs": successful_runs,
+        "timestamp": chrono::Utc::now().to_rfc3339()
+    });
+    match alert_client(config).post(url).json(&alert).send().await {
+        Ok(response) if response.status().is_success() => {}
+        Ok(response) => error!("Success rate alert webhook returned {}", response.status()),
+        Err(e) => error!("Success rate alert could not be sent: {}", e),
+    }
+}
+
+/// Periodic success-rate check, enabled by SUCCESS_RATE_ALERT_THRESHOLD
+async fn run_success_rate_monitor(config: web::Data<Config>, db_pool: web::Data<DbPool>, state: web::Data<AppState>, threshold: f64) {
+    let mut interval = tokio::time::interval(Duration::from_secs(config.success_rate_check_interval_secs));
+    loop {
+        interval.tick().await;
+        check_success_rate(&config, &db_pool, &state, threshold).await;
+    }
+}
+
+/// Periodic faucet reachability probe, independent of test runs
+async fn run_faucet_probe(config: web::Data<Config>, db_pool: web::Data<DbPool>, interval_ms: u64) {
+    let mut interval = tokio::time::interval(Duration::from_millis(interval_ms));
+    loop {
+        interval.tick().await;
+        probe_faucets(&config, &db_pool).await;
+    }
+}
+
+// GET the faucet probe history, newest first, with each faucet's uptime over the page
+async fn faucet_health_handler(query: web::Query<PageQuery>, db_pool: web::Data<DbPool>) -> impl Responder {
+    let limit = query.limit.unwrap_or(100).clamp(1, 1000);
+    let offset = query.offset.unwrap_or(0);
+    
+    match get_faucet_probes(&db_pool, limit, offset) {
+        Ok(probes) => {
+            let mut uptime = serde_json::Map::new();
+            for faucet in ["btc", "lava_usd"] {
+                let checks = probes.iter().filter(|p| p.faucet == faucet).count();
+                let up = probes.iter().filter(|p| p.faucet == faucet && p.status == "up").count();
+                let ratio = if checks == 0 { Value::Null } else { json!(up as f64 / checks as f64) };
+                uptime.insert(faucet.to_string(), json!({ "checks": checks, "up": up, "uptime": ratio }));
+            }
+            
+            HttpResponse::Ok()
+                .content_type("application/json")
+                .body(serde_json::to_string_pretty(&json!({
+                    "limit": limit,
+                    "offset": offset,
+                    "uptime": uptime,
+                    "probes": probes
+                })).unwrap_or_default())
+        }
+        Err(e) => {
+            error!("Failed to get faucet probes: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed to get faucet probes: {}", e))
+        }
+    }
+}
+
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    // Init LOG
+    logging::init();
+    
+    info!("Starting borrower CLI testing server");
+    
+    let config = web::Data::new(Config::from_env());
+    let state = web::Data::new(AppState::new(&config));
+    if config.api_key.is_none() {
+        if config.allow_unguarded_endpoints {
+            warn!("API_KEY is not set and ALLOW_UNGUARDED_ENDPOINTS is on, guarded endpoints are open");
+        } else {
+            warn!("API_KEY is not set, guarded endpoints are disabled");
+        }
+    }
+    
+    // Init DB
+    let db_path = "./data/test_results.db";
+    let warmup = config.db_pool_warmup.then_some(config.db_pool_warmup_connections);
+    let db_pool = match init_pool(db_path, config.db_busy_timeout_ms, config.db_corruption_policy, warmup) {
+        Ok(pool) => pool,
+        Err(e) => {
+            error!("Failed to initialize database: {}", e);
+            return Err(std::io::Error::new(std::io::ErrorKind::Other, 
+                                           format!("Database initialization error: {}", e)));
+        }
+    };
+    
+    db::set_details_compression(config.compress_details);
+    
+    if warmup.is_some() {
+        info!("Database pool warmed up with {} idle connections", db_pool.state().idle_connections);
+    }
+    
+    match fail_stale_runs(&db_pool) {
+        Ok(0) => {}
+        Ok(count) => warn!("Marked {} runs left queued or running by a previous process as failed", count),
+        Err(e) => warn!("Failed to clear stale queued and running runs: {}", e),
+    }
+    
+    // Create web::Data from pool to share with handlers
+    let db_pool = web::Data::new(db_pool);
+    
+    if config.archive_success_after_hours.is_some() || config.archive_failed_after_hours.is_some() {
+        actix_web::rt::spawn(run_sweep(config.clone(), db_pool.clone()));
+    }
+    
+    if let Some(interval_ms) = config.faucet_probe_interval_ms {
+        actix_web::rt::spawn(run_faucet_probe(config.clone(), db_pool.clone(), interval_ms));
+    }
+    
+    if let Some(threshold) = config.success_rate_alert_threshold {
+        actix_web::rt::spawn(run_success_rate_monitor(config.clone(), db_pool.clone(), state.clone(), threshold));
+    }
+    
+    HttpServer::new(move || {
+        App::new()
+            .app_data(db_pool.clone())
+            .app_data(config.clone())
+            .app_data(state.clone())
+            .wrap(from_fn(audit_middleware))
+            .wrap(
+                ErrorHandlers::new()
+                    .handler(StatusCode::NOT_FOUND, json_error_body)
+                    .handler(StatusCode::METHOD_NOT_ALLOWED, json_error_body)
+                    .handler(StatusCode::INTERNAL_SERVER_ERROR, json_error_body),
+            )
+            .route("/health", web::get().to(health_check))
+            .route("/run-test", web::post().to(run_test_handler))
+            .route("/run-test/validate", web::post().to(validate_run_test_handler))
+            .route("/run-test-sync", web::post().to(run_test_sync_handler))
+            .route("/results", web::get().to(get_results_handler))
+            .route("/results/canary", web::get().to(get_canary_results_handler))
+            .route("/results/junit.xml", web::get().to(results_junit_handler))
+            .route("/results/{id}", web::get().to(get_result_by_id_handler))
+            .route("/results/{id}/ws", web::get().to(result_ws_handler))
+            .route("/results/{id}/refresh", web::post().to(refresh_result_handler))
+            .route("/results/{id}/replay", web::post().to(replay_result_handler))
+            .route("/results/{id}/reevaluate", web::post().to(reevaluate_result_handler))
+            .route("/results/{id}/wallet-export", web::get().to(wallet_export_handler))
+            .route("/results/{id}/report.html", web::get().to(result_report_handler))
+            .route("/results/{id}/junit.xml", web::get().to(result_junit_handler))
+            .route("/stats", web::get().to(get_stats_handler))
+            .route("/metrics", web::get().to(metrics_handler))
+            .route("/stats/gate", web::get().to(stats_gate_handler))
+            .route("/wallets", web::get().to(get_wallets_handler))
+            .route("/errors", web::get().to(get_errors_handler))
+            .route("/faucet/health", web::get().to(faucet_health_handler))
+            .route("/config", web::get().to(get_config_handler))
+            .route("/admin/backup", web::post().to(backup_handler))
+            .route("/audit", web::get().to(get_audit_handler))
+            .route("/admin/pause", web::post().to(pause_handler))
+            .route("/admin/resume", web::post().to(resume_handler))
+            .route("/admin/cancel-queued", web::post().to(cancel_queued_handler))
+            .route("/admin/log-level", web::post().to(log_level_handler))
+            .route("/cli/logs", web::get().to(cli_logs_handler))
+            .default_service(web::route().to(not_found_handler))
+    })
+    .bind("0.0.0.0:8080")?
+    .run()
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    
+    #[test]
+    fn long_body_is_truncated_for_log() {
+        let body = "x".repeat(2000);
+        let logged = truncate_for_log(&body, 512);
+        assert_eq!(logged.len(), 512 + "...".len());
+        assert!(logged.ends_with("..."));
+        
+        assert_eq!(truncate_for_log("short", 512), "short");
+        // Cuts on a char boundary rather than a byte offset
+        assert_eq!(truncate_for_log("ééé", 2), "éé...");
+    }
+    
+    #[actix_web::test]
+    async fn result_ws_replays_the_output_so_far_then_streams_until_the_run_ends() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        
+        /// Read one unmasked server frame with a short payload: (opcode, payload)
+        async fn read_frame(socket: &mut tokio::net::TcpStream) -> (u8, String) {
+            let mut header = [0u8; 2];
+            socket.read_exact(&mut header).await.unwrap();
+            let mut payload = vec![0u8; (header[1] & 0x7f) as usize];
+            socket.read_exact(&mut payload).await.unwrap();
+            (header[0] & 0x0f, String::from_utf8_lossy(&payload).into_owned())
+        }
+        
+        let state = web::Data::new(test_state(1, 5));
+        let server_state = state.clone();
+        let server = HttpServer::new(move || {
+            App::new().app_data(server_state.clone()).route("/results/{id}/ws", web::get().to(result_ws_handler))
+        })
+        .workers(1)
+        .disable_signals()
+        .bind("127.0.0.1:0")
+        .unwrap();
+        let addr = server.addrs()[0];
+        let server = server.run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+        let connect = |id: String| async move {
+            let mut socket = tokio::net::TcpStream::connect(addr).await.unwrap();
+            let handshake = format!(
+                "GET /results/{}/ws HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                 Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+                id, addr
+            );
+            socket.write_all(handshake.as_bytes()).await.unwrap();
+            let mut head = Vec::new();
+            while !head.ends_with(b"\r\n\r\n") {
+                head.push(socket.read_u8().await.unwrap());
+            }
+            (String::from_utf8_lossy(&head).into_owned(), socket)
+        };
+        
+        let id = Uuid::new_v4().to_string();
+        let live = state.live_runs.start(&id);
+        state.live_runs.run(&id).push_line("wallet funded".to_string());
+        let (head, mut socket) = connect(id.clone()).await;
+        assert!(head.starts_with("HTTP/1.1 101"), "{}", head);
+        // A late subscriber gets what was printed before it connected, then the new lines
+        assert_eq!(read_frame(&mut socket).await, (1, "wallet funded".to_string()));
+        state.live_runs.run(&id).push_line("loan created".to_string());
+        assert_eq!(read_frame(&mut socket).await, (1, "loan created".to_string()));
+        drop(live);
+        assert_eq!(read_frame(&mut socket).await.0, 8);
+        
+        // Finished or unknown runs have nothing to stream; queued ones haven't started
+        assert!(connect(id).await.0.starts_with("HTTP/1.1 404"));
+        state.enqueue_run("queued-run", 10).unwrap();
+        assert!(connect("queued-run".to_string()).await.0.starts_with("HTTP/1.1 409"));
+        handle.stop(false).await;
+    }
+    
+    #[actix_web::test]
+    async fn cli_output_lines_reach_the_live_run_as_they_are_printed() {
+        let run_dir = std::env::temp_dir().join(format!("cli_live_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&run_dir).unwrap();
+        let cli_path = run_dir.join("loans-borrower-cli");
+        std::fs::write(&cli_path, "#!/bin/sh\necho 'usage: loans-borrower-cli'\nsleep 1\nprintf 'no newline' >&2\nexit 2\n").unwrap();
+        std::process::Command::new("chmod").arg("+x").arg(&cli_path).output().unwrap();
+        let config = Config::from_env();
+        let live_runs = live::LiveRuns::default();
+        let _live = live_runs.start("run");
+        
+        let mut command = tokio::process::Command::new(&cli_path);
+        let run = run_cli(command.arg("--help"), &config, None, live_runs.run("run"));
+        let watch = async {
+            // The first line shows up while the CLI is still running
+            while live_runs.subscribe("run").unwrap().0.is_empty() {
+                sleep(Duration::from_millis(20)).await;
+            }
+            live_runs.subscribe("run").unwrap().0
+        };
+        let (outcome, seen) = tokio::join!(run, watch);
+        assert_eq!(seen, ["usage: loans-borrower-cli"]);
+        // A last line without a newline is still reported, and the exit fails the run
+        assert_eq!(live_runs.subscribe("run").unwrap().0, ["usage: loans-borrower-cli", "no newline"]);
+        let error = outcome.unwrap_err().to_string();
+        assert!(error.contains("no newline") && error.contains("exit status: 2"), "{}", error);
+        std::fs::remove_dir_all(&run_dir).unwrap();
+    }
+    
+    #[actix_web::test]
+    async fn a_hung_cli_is_killed_at_the_timeout() {
+        let run_dir = std::env::temp_dir().join(format!("cli_hung_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&run_dir).unwrap();
+        let cli_path = run_dir.join(CLI_BINARY_NAME);
+        std::fs::write(&cli_path, "#!/bin/sh\nexec sleep 30\n").unwrap();
+        std::process::Command::new("chmod").arg("+x").arg(&cli_path).output().unwrap();
+        let mut config = Config::from_env();
+        config.cli_timeout_ms = 200;
+        config.cli_work_dir = run_dir.to_string_lossy().to_string();
+        let live_runs = live::LiveRuns::default();
+        
+        let started = Instant::now();
+        let error = check_cli_runs(&config, &run_dir, &["--debug".to_string()], live_runs.run("run")).await.unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(10), "{:?}", started.elapsed());
+        assert!(format!("{:#}", error).contains("timed out after 200ms"), "{:#}", error);
+        assert_eq!(cli_exit_code(&error), None);
+        // The log still records the invocation that hung
+        let log = std::fs::read_to_string(cli_log_path(&config, &run_dir)).unwrap();
+        assert!(log.contains("--debug --help") && log.contains("timed out"), "{}", log);
+        std::fs::remove_dir_all(&run_dir).unwrap();
+    }
+    
+    #[actix_web::test]
+    async fn cli_confirmation_is_answered_on_stdin_and_a_stuck_prompt_is_killed() {
+        let run_dir = std::env::temp_dir().join(format!("cli_prompt_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&run_dir).unwrap();
+        let cli_path = run_dir.join("loans-borrower-cli");
+        let write_cli = |script: &str| {
+            std::fs::write(&cli_path, script).unwrap();
+            std::process::Command::new("chmod").arg("+x").arg(&cli_path).output().unwrap();
+        };
+        let mut config = Config::from_env();
+        let live_runs = live::LiveRuns::default();
+        let _live = live_runs.start("run");
+        
+        // A CLI that asks once gets the configured answer
+        write_cli("#!/bin/sh\nprintf 'Create loan? [y/N] '\nread answer\necho \"answer: $answer args: $*\"\n");
+        let mut command = tokio::process::Command::new(&cli_path);
+        let output = run_cli(command.arg("create"), &config, None, live_runs.run("run")).await.unwrap();
+        assert!(String::from_utf8_lossy(&output.stdout).contains("answer: y args: create"));
+        config.cli_confirm_response = "yes".to_string();
+        config.cli_yes_flag = Some("--yes".to_string());
+        let mut command = tokio::process::Command::new(&cli_path);
+        let output = run_cli(command.arg("create"), &config, None, live_runs.run("run")).await.unwrap();
+        assert!(String::from_utf8_lossy(&output.stdout).contains("answer: yes args: create --yes"));
+        
+        // Asking again reads EOF rather than hanging
+        write_cli("#!/bin/sh\nread first\nprintf 'Are you sure? (y/n) '\nread second || { echo 'no answer' >&2; exit 4; }\n");
+        let mut command = tokio::process::Command::new(&cli_path);
+        let error = run_cli(command.arg("create"), &config, None, live_runs.run("run")).await.unwrap_err().to_string();
+        assert!(error.contains("no answer") && error.contains("exit status: 4"), "{}", error);
+        
+        // A prompt that never reads stdin is killed at the timeout, and named
+        write_cli("#!/bin/sh\nprintf 'Are you sure? (y/n) '\nexec sleep 30\n");
+        config.cli_timeout_ms = 200;
+        let started = Instant::now();
+        let mut command = tokio::process::Command::new(&cli_path);
+        let error = run_cli(command.arg("create"), &config, None, live_runs.run("run")).await.unwrap_err().to_string();
+        assert!(started.elapsed() < Duration::from_secs(10), "{:?}", started.elapsed());
+        assert!(error.contains("waiting at a prompt") && error.contains("Are you sure? (y/n)"), "{}", error);
+        std::fs::remove_dir_all(&run_dir).unwrap();
+    }
+    
+    #[actix_web::test]
+    async fn cli_progress_events_are_reported_while_the_cli_runs() {
+        let run_dir = std::env::temp_dir().join(format!("cli_progress_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&run_dir).unwrap();
+        let cli_path = run_dir.join(CLI_BINARY_NAME);
+        let write_cli = |script: &str| {
+            std::fs::write(&cli_path, script).unwrap();
+            std::process::Command::new("chmod").arg("+x").arg(&cli_path).output().unwrap();
+        };
+        let config = Config::from_env();
+        let state = web::Data::new(test_state(1, 5));
+        let db_pool = test_pool();
+        let id = Uuid::new_v4().to_string();
+        let mut running = TestResult::new(id.clone(), &Mnemonic::default(), "tb1q", "pk");
+        running.status = "running".to_string();
+        save_test_result(&db_pool, &running).unwrap();
+        let live = state.live_runs.start(&id);
+        
+        // NDJSON progress, with a plain stderr line and a last event without a newline
+        write_cli(concat!(
+            "#!/bin/sh\n",
+            "echo '{\"event\":\"progress\",\"step\":\"create_loan\",\"message\":\"creating\"}'\n",
+            "echo 'contract pending' >&2\n",
+            "sleep 1\n",
+            "printf '{\"event\":\"progress\",\"step\":\"repay_loan\"}'\n",
+        ));
+        let mut command = tokio::process::Command::new(&cli_path);
+        let run = run_cli(command.arg("create"), &config, None, state.live_runs.run(&id));
+        let watch = async {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while Instant::now() < deadline {
+                if let Some(step) = state.live_runs.current_step(&id) {
+                    return Some(step);
+                }
+                sleep(Duration::from_millis(20)).await;
+            }
+            None
+        };
+        let (output, seen) = tokio::join!(run, watch);
+        let output = output.unwrap();
+        // The first event was seen before the CLI exited
+        assert_eq!(seen.as_deref(), Some("create_loan"));
+        assert_eq!(state.live_runs.current_step(&id).as_deref(), Some("repay_loan"));
+        assert!(String::from_utf8_lossy(&output.stdout).contains("\"step\":\"repay_loan\""));
+        let lines = state.live_runs.lines(&id);
+        assert_eq!(lines.len(), 3, "{:?}", lines);
+        assert!(lines.contains(&"contract pending".to_string()));
+        
+        // A live run's result shows its progress
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(web::Data::new(db_pool))
+                .app_data(web::Data::new(config.clone()))
+                .app_data(state.clone())
+                .route("/results/{id}", web::get().to(get_result_by_id_handler)),
+        )
+        .await;
+        let get = || actix_web::test::TestRequest::get().uri(&format!("/results/{}", id)).to_request();
+        let live_result: Value = actix_web::test::call_and_read_body_json(&app, get()).await;
+        assert_eq!(live_result["status"], "running");
+        assert_eq!(live_result["current_step"], "repay_loan");
+        assert!(live_result["cli_output"].as_array().unwrap().contains(&json!("contract pending")));
+        drop(live);
+        let finished: Value = actix_web::test::call_and_read_body_json(&app, get()).await;
+        assert!(finished.get("current_step").is_none() && finished.get("cli_output").is_none());
+        
+        // Output that isn't JSON lines is still buffered whole and reported as plain lines
+        let _live = state.live_runs.start(&id);
+        write_cli("#!/bin/sh\necho 'usage: loans-borrower-cli'\necho '{\"status\":\"ok\"}'\n");
+        let output = run_cli(tokio::process::Command::new(&cli_path).arg("--help"), &config, None, state.live_runs.run(&id)).await.unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "usage: loans-borrower-cli\n{\"status\":\"ok\"}\n");
+        assert_eq!(state.live_runs.current_step(&id), None);
+        assert_eq!(state.live_runs.lines(&id).len(), 2);
+        std::fs::remove_dir_all(&run_dir).unwrap();
+    }
+    
+    #[test]
+    fn guarded_endpoints_need_a_configured_api_key() {
+        let status = |config: &Config, key: Option<&str>| {
+            let mut request = actix_web::test::TestRequest::default();
+            if let Some(key) = key {
+                request = request.insert_header(("X-API-Key", key));
+            }
+            check_api_key(&request.to_http_request(), config).map(|response| response.status())
+        };
+        
+        // Closed without a key unless explicitly opened
+        let mut config = Config::from_env();
+        config.api_key = None;
+        config.allow_unguarded_endpoints = false;
+        assert_eq!(status(&config, None), Some(StatusCode::FORBIDDEN));
+        config.allow_unguarded_endpoints = true;
+        assert_eq!(status(&config, None), None);
+        
+        config.api_key = Some("test-key".to_string());
+        assert_eq!(status(&config, None), Some(StatusCode::UNAUTHORIZED));
+        assert_eq!(status(&config, Some("test-kez")), Some(StatusCode::UNAUTHORIZED));
+        assert_eq!(status(&config, Some("test-key-longer")), Some(StatusCode::UNAUTHORIZED));
+        assert_eq!(status(&config, Some("test-key")), None);
+    }
+    
+    const TXID: &str = "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b";
+    
+    fn test_state(max_concurrent_runs: usize, faucet_health_window: usize) -> AppState {
+        let mut config = Config::from_env();
+        config.max_concurrent_runs = max_concurrent_runs;
+        config.faucet_health_window = faucet_health_window;
+        AppState::new(&config)
+    }
+    
+    fn txid_paths() -> Vec<String> {
+        vec!["txid".to_string(), "data.txid".to_string()]
+    }
+    
+    #[test]
+    fn faucet_txid_from_flat_body() {
+        let body = json!({ "txid": TXID }).to_string();
+        let response = parse_faucet_response(reqwest::StatusCode::OK, &body, &txid_paths());
+        assert_eq!(response.txid.as_deref(), Some(TXID));
+        assert!(response.error.is_none());
+    }
+    
+    #[test]
+    fn faucet_txid_from_nested_body() {
+        let body = json!({ "data": { "txid": TXID } }).to_string();
+        let response = parse_faucet_response(reqwest::StatusCode::OK, &body, &txid_paths());
+        assert_eq!(response.txid.as_deref(), Some(TXID));
+        
+        let unknown = json!({ "result": { "txid": TXID } }).to_string();
+        let response = parse_faucet_response(reqwest::StatusCode::OK, &unknown, &txid_paths());
+        assert!(response.txid.is_none());
+        assert_eq!(response.message.as_deref(), Some(unknown.as_str()));
+    }
+    
+    #[test]
+    fn faucet_txid_from_array_and_scalar_bodies() {
+        let parse = |body: Value| parse_faucet_response(reqwest::StatusCode::OK, &body.to_string(), &txid_paths());
+        
+        assert_eq!(parse(json!({ "txid": TXID })).txid.as_deref(), Some(TXID));
+        assert_eq!(parse(json!([TXID, "b".repeat(64)])).txid.as_deref(), Some(TXID));
+        assert_eq!(parse(json!([{ "data": { "txid": TXID } }])).txid.as_deref(), Some(TXID));
+        assert_eq!(parse(json!(TXID)).txid.as_deref(), Some(TXID));
+        
+        for (body, error) in [
+            (json!([]), "empty JSON array"),
+            (json!([{ "status": "queued" }]), "without a txid in its first element"),
+            (json!("rate limited, try later"), "isn't a txid"),
+            (json!(42), "bare JSON value"),
+        ] {
+            let response = parse(body);
+            assert!(response.txid.is_none());
+            assert!(response.error.as_deref().is_some_and(|e| e.contains(error)), "{:?}", response.error);
+        }
+    }
+    
+    #[test]
+    fn repayment_is_skipped_when_loan_closed_before_repay() {
+        let mut config = Config::from_env();
+        let liquidated = json!({
+            "Closed": { "timestamp": "2024-03-01T12:00:00+00:00", "reason": "liquidated" },
+            "status": "closed"
+        });
+        
+        let mut open = TestResult::new("open".to_string(), &Mnemonic::default(), "tb1q", "pk");
+        assert!(!apply_pre_repay_details(&config, &mut open, json!({ "status": "open" })));
+        assert!(open.steps.is_empty());
+        
+        let mut result = TestResult::new("run".to_string(), &Mnemonic::default(), "tb1q", "pk");
+        result.loan_contract_id = Some("contract".to_string());
+        assert!(apply_pre_repay_details(&config, &mut result, liquidated.clone()));
+        assert_eq!((result.steps[0].name.as_str(), result.steps[0].status.as_str()), ("repay_loan", "skipped"));
+        assert!(result.loan_closed);
+        let details = result.details.as_ref().unwrap();
+        assert_eq!(details["pre_closed_loan"]["reason"], "liquidated");
+        // No repayment txid, but the loan did close
+        let (status, reasons) = evaluate_result(result.details.as_ref(), required_closure_paths(&config, result.details.as_ref()));
+        assert_eq!((status.as_str(), reasons.len()), ("success", 0));
+        
+        config.pre_closed_loan_policy = PreClosedLoanPolicy::Fail;
+        let mut strict = TestResult::new("strict".to_string(), &Mnemonic::default(), "tb1q", "pk");
+        assert!(apply_pre_repay_details(&config, &mut strict, liquidated));
+        assert_eq!(strict.status, "failed");
+        assert_eq!(strict.details.as_ref().unwrap()["error_kind"], "loan_closed_before_repay");
+    }
+    
+    #[actix_web::test]
+    async fn batch_consolidation_returns_every_wallet_in_one_transaction() {
+        let db_pool = test_pool();
+        let state = test_state(3, 5);
+        let mut results: Vec<TestResult> = (0..4)
+            .map(|index| {
+                let mut result = TestResult::new(format!("wallet-{}", index), &Mnemonic::default(), &format!("tb1q{}", index), "pk");
+                result.faucet_accepted = index < 3;
+                result
+            })
+            .collect();
+        
+        let txid = consolidate_fund_returns(&db_pool, &state, &mut results).await.unwrap();
+        
+        for result in &results[..3] {
+            assert_eq!(result.return_txid.as_deref(), Some(txid.as_str()));
+            assert!(result.returned_funds);
+            assert_eq!(result.details.as_ref().unwrap()["consolidated_return"]["wallets"], 3);
+            // Recorded per wallet, so a retry can't send any of them again
+            assert_eq!(find_fund_return(&db_pool, &result.btc_address, None, &result.id).unwrap().as_deref(), Some(txid.as_str()));
+        }
+        // The faucet never funded the last wallet, so it has nothing to return
+        assert!(results[3].return_txid.is_none());
+        
+        let single: RunTestRequest = serde_json::from_value(json!({ "consolidate_returns": true })).unwrap();
+        assert!(validate_run_request(&single, &Config::from_env()).unwrap_err().contains("wallets"));
+    }
+    
+    #[actix_web::test]
+    async fn wallet_flows_that_fail_to_run_keep_a_failed_result() {
+        let mut config = Config::from_env();
+        // The placeholder wallet is refused before any network call
+        config.mnemonic_file = None;
+        config.allow_placeholder_wallet = false;
+        let request: RunTestRequest = serde_json::from_value(json!({ "wallets": 2 })).unwrap();
+        
+        let result = run_multi_wallet_suite(&config, &test_pool(), &request, &test_state(2, 5), 2, "batch".to_string()).await.unwrap();
+        
+        assert_eq!(result.id, "batch");
+        assert_eq!(result.status, "failed");
+        assert_eq!(result.details.as_ref().unwrap()["error_kind"], "wallet_flows_failed");
+        let wallets: Vec<TestResult> = serde_json::from_value(result.details.unwrap()["wallets"].clone()).unwrap();
+        assert_eq!(wallets.len(), 2);
+        for wallet in &wallets {
+            assert_eq!(wallet.status, "failed");
+            assert!(wallet.error_message.as_deref().unwrap().contains("placeholder"));
+        }
+        
+        // Each flow takes the next account_index, so the last one has to be in range
+        let too_high: RunTestRequest = serde_json::from_value(json!({ "wallets": 3, "account_index": MAX_ACCOUNT_INDEX - 1 })).unwrap();
+        assert!(validate_run_request(&too_high, &config).unwrap_err().contains("account_index"));
+        let in_range: RunTestRequest = serde_json::from_value(json!({ "wallets": 3, "account_index": MAX_ACCOUNT_INDEX - 2 })).unwrap();
+        assert!(validate_run_request(&in_range, &config).is_ok());
+    }
+    
+    #[test]
+    fn run_within_the_wallet_cooldown_is_refused() {
+        let db_pool = test_pool();
+        let mut config = Config::from_env();
+        config.wallet_cooldown_secs = 300;
+        
+        assert!(check_wallet_cooldown(&config, &db_pool, "tb1qcool", "second").is_ok());
+        let first = TestResult::new("first".to_string(), &Mnemonic::default(), "tb1qcool", "pk");
+        save_test_result(&db_pool, &first).unwrap();
+        
+        let message = check_wallet_cooldown(&config, &db_pool, "tb1qcool", "second").unwrap_err();
+        assert!(message.contains("WALLET_COOLDOWN_SECS requires 300s"), "{}", message);
+        // Other wallets aren't held up, nor is the run itself
+        assert!(check_wallet_cooldown(&config, &db_pool, "tb1qother", "second").is_ok());
+        assert!(check_wallet_cooldown(&config, &db_pool, "tb1qcool", "first").is_ok());
+        
+        let earlier = (chrono::Utc::now() - chrono::Duration::seconds(301)).to_rfc3339();
+        db_pool.get().unwrap().execute("UPDATE test_results SET timestamp = ? WHERE id = 'first'", [earlier]).unwrap();
+        assert!(check_wallet_cooldown(&config, &db_pool, "tb1qcool", "second").is_ok());
+    }
+    
+    fn test_pool() -> DbPool {
+        let path = std::env::temp_dir().join(format!("test_results_{}.db", Uuid::new_v4()));
+        init_pool(path.to_str().unwrap(), 5000, config::DbCorruptionPolicy::Fail, None).unwrap()
+    }
+    
+    #[actix_web::test]
+    async fn run_test_writes_exactly_one_audit_row() {
+        let db_pool = test_pool();
+        let mut config = Config::from_env();
+        // The placeholder wallet is refused before any network call
+        config.allow_placeholder_wallet = false;
+        
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(web::Data::new(db_pool.clone()))
+                .app_data(web::Data::new(config))
+                .app_data(web::Data::new(test_state(1, 5)))
+                .wrap(from_fn(audit_middleware))
+                .route("/run-test", web::post().to(run_test_handler))
+                .route("/stats", web::get().to(get_stats_handler)),
+        )
+        .await;
+        
+        let run = actix_web::test::TestRequest::post().uri("/run-test").to_request();
+        let response = actix_web::test::call_service(&app, run).await;
+        let read = actix_web::test::TestRequest::get().uri("/stats").to_request();
+        actix_web::test::call_service(&app, read).await;
+        
+        let entries = get_audit_entries(&db_pool, 50, 0).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].method, "POST");
+        assert_eq!(entries[0].route, "/run-test");
+        assert_eq!(entries[0].status, response.status().as_u16());
+    }
+    
+    #[actix_web::test]
+    async fn audit_records_the_peer_and_only_a_trusted_proxys_forwarded_client() {
+        let db_pool = test_pool();
+        let mut config = Config::from_env();
+        config.allow_placeholder_wallet = false;
+        config.trusted_proxies = vec!["10.0.0.5".parse().unwrap()];
+        
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(web::Data::new(db_pool.clone()))
+                .app_data(web::Data::new(config))
+                .app_data(web::Data::new(test_state(1, 5)))
+                .wrap(from_fn(audit_middleware))
+                .route("/run-test", web::post().to(run_test_handler)),
+        )
+        .await;
+        let run_from = |peer: &str, forwarded_for: Option<&str>| {
+            let mut request = actix_web::test::TestRequest::post().uri("/run-test").peer_addr(peer.parse().unwrap());
+            if let Some(client) = forwarded_for {
+                request = request.insert_header(("X-Forwarded-For", client));
+            }
+            request.to_request()
+        };
+        
+        // A client can't put another address in the log by sending the header itself
+        actix_web::test::call_service(&app, run_from("198.51.100.7:5000", Some("203.0.113.9"))).await;
+        actix_web::test::call_service(&app, run_from("10.0.0.5:4000", Some("203.0.113.9"))).await;
+        actix_web::test::call_service(&app, run_from("10.0.0.5:4001", None)).await;
+        
+        let entries = get_audit_entries(&db_pool, 50, 0).unwrap();
+        let ips: Vec<_> = entries.iter().rev().map(|e| (e.client_ip.as_deref(), e.forwarded_ip.as_deref())).collect();
+        assert_eq!(
+            ips,
+            [(Some("198.51.100.7"), None), (Some("10.0.0.5"), Some("203.0.113.9")), (Some("10.0.0.5"), None)]
+        );
+    }
+    
+    #[actix_web::test]
+    async fn pause_blocks_new_runs_until_resumed() {
+        let mut config = Config::from_env();
+        config.api_key = Some("test-key".to_string());
+        config.allow_placeholder_wallet = false;
+        
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(web::Data::new(test_pool()))
+                .app_data(web::Data::new(config))
+                .app_data(web::Data::new(test_state(1, 5)))
+                .route("/health", web::get().to(health_check))
+                .route("/run-test", web::post().to(run_test_handler))
+                .route("/admin/pause", web::post().to(pause_handler))
+                .route("/admin/resume", web::post().to(resume_handler)),
+        )
+        .await;
+        let admin = |uri: &str| {
+            actix_web::test::TestRequest::post()
+                .uri(uri)
+                .insert_header(("X-API-Key", "test-key"))
+                .to_request()
+        };
+        let run = || actix_web::test::TestRequest::post().uri("/run-test").to_request();
+        
+        assert!(actix_web::test::call_service(&app, admin("/admin/pause")).await.status().is_success());
+        let blocked = actix_web::test::call_service(&app, run()).await;
+        assert_eq!(blocked.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let health: Value = actix_web::test::call_and_read_body_json(
+            &app,
+            actix_web::test::TestRequest::get().uri("/health").to_request(),
+        )
+        .await;
+        assert_eq!(health["paused"], true);
+        
+        assert!(actix_web::test::call_service(&app, admin("/admin/resume")).await.status().is_success());
+        let unblocked = actix_web::test::call_service(&app, run()).await;
+        assert_ne!(unblocked.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+    
+    #[test]
+    fn btc_only_run_is_accepted_and_skips_lava_usd() {
+        let config = Config::from_env();
+        let request = parse_run_request(br#"{"request_lava_usd": false}"#, &config).unwrap();
+        assert_eq!(request.request_lava_usd, Some(false));
+        assert!(request.request_btc.unwrap_or(true));
+        
+        let mut result = TestResult::new("run".to_string(), &Mnemonic::default(), "tb1q", "pk");
+        result.record_skipped_step("request_lava_usd");
+        assert_eq!(result.steps[0].status, "skipped");
+        assert_eq!(result.steps[0].attempts, 0);
+    }
+    
+    #[test]
+    fn disabling_the_collateral_faucet_is_rejected() {
+        let config = Config::from_env();
+        assert!(parse_run_request(br#"{"request_btc": false}"#, &config).is_err());
+    }
+    
+    #[actix_web::test]
+    async fn stored_return_txid_skips_the_send() {
+        let db_pool = test_pool();
+        save_fund_return(&db_pool, "tb1qwallet", Some("contract-1"), "earlier-run", "stored-txid").unwrap();
+        
+        let started = Instant::now();
+        let (txid, already_returned) = return_funds_once(&db_pool, &test_state(1, 5), "m", "tb1qwallet", Some("contract-1"), "retry-run", FUNDS_RETURN_ADDRESS)
+            .await
+            .unwrap();
+        
+        assert_eq!(txid, "stored-txid");
+        assert!(already_returned);
+        // return_funds sleeps before sending, so a skipped send returns immediately
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+    
+    #[actix_web::test]
+    async fn empty_faucet_body_flips_health_to_degraded() {
+        let config = Config::from_env();
+        let state = web::Data::new(test_state(1, 2));
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(state.clone())
+                .route("/health", web::get().to(health_check)),
+        )
+        .await;
+        let faucets = || async {
+            let health: Value = actix_web::test::call_and_read_body_json(
+                &app,
+                actix_web::test::TestRequest::get().uri("/health").to_request(),
+            )
+            .await;
+            health["faucets"].clone()
+        };
+        
+        let healthy = parse_faucet_response(reqwest::StatusCode::OK, &json!({ "txid": TXID }).to_string(), &txid_paths());
+        state.faucet_health.record("btc", is_faucet_exhausted(&healthy, &config.faucet_exhaustion_patterns));
+        assert_eq!(faucets().await, "ok");
+        
+        let empty = parse_faucet_response(reqwest::StatusCode::SERVICE_UNAVAILABLE, "Faucet is EMPTY, try again later", &txid_paths());
+        assert!(is_faucet_exhausted(&empty, &config.faucet_exhaustion_patterns));
+        state.faucet_health.record("btc", true);
+        assert_eq!(faucets().await, "degraded");
+        assert_eq!(state.faucet_health.snapshot()["btc"].exhausted_calls, 1);
+        
+        // Recovers once the exhausted call leaves the window
+        state.faucet_health.record("btc", false);
+        state.faucet_health.record("btc", false);
+        assert_eq!(faucets().await, "ok");
+    }
+    
+    /// A request received by `MockServer`
+    #[derive(Debug, Clone)]
+    struct MockRequest {
+        method: String,
+        path: String,
+        headers: Vec<(String, String)>,
+        body: String,
+    }
+    
+    /// Minimal HTTP server for faucet/S3/RPC stand-ins. Serves the canned responses
+    /// in order, repeating the last one, and records every request it receives.
+    /// Status 0 drops the connection unanswered, like a request that timed out client-side.
+    /// A 3xx response redirects to its body.
+    struct MockServer {
+        url: String,
+        requests: Arc<Mutex<Vec<MockRequest>>>,
+    }
+    
+    impl MockServer {
+        async fn start(responses: Vec<(u16, String)>) -> Self {
+            Self::start_with_headers(responses, &[]).await
+        }
+        
+        /// Like `start`, adding the given headers to every response
+        async fn start_with_headers(responses: Vec<(u16, String)>, headers: &[(&str, &str)]) -> Self {
+            let extra_headers: String = headers.iter().map(|(name, value)| format!("{}: {}\r\n", name, value)).collect();
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let received = requests.clone();
+            
+            tokio::spawn(async move {
+                let mut served = 0;
+                while let Ok((mut socket, _)) = listener.accept().await {
+                    let request = match read_mock_request(&mut socket).await {
+                        Some(request) => request,
+                        None => continue,
+                    };
+                    received.lock().unwrap().push(request);
+                    
+                    let (status, body) = responses[served.min(responses.len() - 1)].clone();
+                    served += 1;
+                    if status == 0 {
+                        continue;
+                    }
+                    // A redirect's body is the Location it points to
+                    let (location, body) = match status {
+                        300..=399 => (format!("Location: {}\r\n", body), String::new()),
+                        _ => (String::new(), body),
+                    };
+                    let response = format!(
+                        "HTTP/1.1 {} Mock\r\n{}{}Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        status,
+                        location,
+                        extra_headers,
+                        body.len(),
+                        body
+                    );
+                    let _ = tokio::io::AsyncWriteExt::write_all(&mut socket, response.as_bytes()).await;
+                }
+            });
+            
+            MockServer { url, requests }
+        }
+        
+        fn requests(&self) -> Vec<MockRequest> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+    
+    async fn read_mock_request(socket: &mut tokio::net::TcpStream) -> Option<MockRequest> {
+        use tokio::io::AsyncReadExt;
+        
+        let mut data = Vec::new();
+        let mut buf = [0u8; 4096];
+        let header_end = loop {
+            let n = socket.read(&mut buf).await.ok()?;
+            if n == 0 {
+                return None;
+            }
+            data.extend_from_slice(&buf[..n]);
+            if let Some(pos) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos + 4;
+            }
+        };
+        
+        let head = String::from_utf8_lossy(&data[..header_end]).to_string();
+        let mut lines = head.split("\r\n");
+        let mut request_line = lines.next()?.split(' ');
+        let method = request_line.next()?.to_string();
+        let path = request_line.next()?.to_string();
+        let headers: Vec<(String, String)> = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+            .collect();
+        let length = headers
+            .iter()
+            .find(|(name, _)| name == "content-length")
+            .and_then(|(_, value)| value.parse::<usize>().ok())
+            .unwrap_or(0);
+        
+        while data.len() < header_end + length {
+            let n = socket.read(&mut buf).await.ok()?;
+            if n == 0 {
+                break;
+            }
+            data.extend_from_slice(&buf[..n]);
+        }
+        
+        Some(MockRequest {
+            method,
+            path,
+            headers,
+            body: String::from_utf8_lossy(&data[header_end..]).to_string(),
+        })
+    }
+    
+    fn s3_config(endpoint: &str) -> Config {
+        let mut config = Config::from_env();
+        config.s3_bucket = Some("results".to_string());
+        config.s3_prefix = "archive".to_string();
+        config.s3_endpoint = Some(endpoint.to_string());
+        config.s3_access_key_id = Some("AKIDEXAMPLE".to_string());
+        config.s3_secret_access_key = Some("secret".to_string());
+        config
+    }
+    
+    #[actix_web::test]
+    async fn archived_result_masks_every_mnemonic() {
+        let s3 = MockServer::start(vec![(200, String::new())]).await;
+        let config = s3_config(&s3.url);
+        
+        let wallet = TestResult::new("wallet-1".to_string(), &Mnemonic::new("nested secret words".to_string()), "tb1qwallet", "pk");
+        let mut result = TestResult::new("run-1".to_string(), &Mnemonic::new("top secret words".to_string()), "tb1qrun", "pk");
+        result.record_detail("wallets", json!([wallet]));
+        archive_result(&config, &mut result).await;
+        
+        let requests = s3.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "PUT");
+        assert!(requests[0].path.starts_with("/results/archive/"));
+        assert!(requests[0].path.ends_with("/run-1.json"));
+        assert!(requests[0].headers.iter().any(|(name, value)| name == "authorization" && value.starts_with("AWS4-HMAC-SHA256")));
+        
+        let uploaded: Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(uploaded["mnemonic"], "[REDACTED]");
+        assert_eq!(uploaded["details"]["wallets"][0]["mnemonic"], "[REDACTED]");
+        assert!(!requests[0].body.contains("secret words"));
+        
+        // The stored result keeps the mnemonic and records the upload
+        assert_eq!(result.mnemonic.expose(), "top secret words");
+        assert_eq!(result.details.unwrap()["s3_upload"]["status"], "uploaded");
+    }
+    
+    #[actix_web::test]
+    async fn failed_upload_is_recorded_not_fatal() {
+        let s3 = MockServer::start(vec![(403, "<Error>AccessDenied</Error>".to_string())]).await;
+        let config = s3_config(&s3.url);
+        
+        let mut result = TestResult::new("run-2".to_string(), &Mnemonic::default(), "tb1qrun", "pk");
+        archive_result(&config, &mut result).await;
+        
+        let upload = &result.details.unwrap()["s3_upload"];
+        assert_eq!(upload["status"], "failed");
+        assert!(upload["error"].as_str().unwrap().contains("403"));
+    }
+    
+    #[actix_web::test]
+    async fn configured_headers_are_sent_to_the_faucet() {
+        let faucet = MockServer::start(vec![(200, json!({ "txid": TXID }).to_string())]).await;
+        let mut config = Config::from_env();
+        config.btc_faucet_url = format!("{}/mint-mutinynet", faucet.url);
+        config.faucet_headers = vec![("X-Faucet-Token".to_string(), "abc123".to_string())];
+        
+        let response = request_btc(&config, &test_state(1, 5), "tb1qwallet", "key-1", &Cell::new(0)).await.unwrap();
+        assert_eq!(response.txid.as_deref(), Some(TXID));
+        
+        let requests = faucet.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].path, "/mint-mutinynet");
+        let header = |name: &str| {
+            requests[0].headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.clone())
+        };
+        assert_eq!(header("x-faucet-token").as_deref(), Some("abc123"));
+        assert_eq!(header("content-type").as_deref(), Some("application/json"));
+    }
+    
+    #[actix_web::test]
+    async fn each_faucet_gets_its_own_endpoint_and_auth() {
+        let btc_faucet = MockServer::start(vec![(200, json!({ "txid": TXID }).to_string())]).await;
+        let lava_usd_faucet = MockServer::start(vec![(200, json!({ "signature": "d".repeat(64) }).to_string())]).await;
+        let mut config = Config::from_env();
+        config.btc_faucet_url = format!("{}/btc", btc_faucet.url);
+        config.lava_usd_faucet_url = format!("{}/lava", lava_usd_faucet.url);
+        config.faucet_headers = vec![("X-Client".to_string(), "suite".to_string()), ("Authorization".to_string(), "Bearer shared".to_string())];
+        config.btc_faucet_headers = vec![("Authorization".to_string(), "Bearer btc-token".to_string())];
+        config.lava_usd_faucet_headers = vec![("X-Api-Key".to_string(), "lava-key".to_string())];
+        
+        let state = test_state(1, 5);
+        request_btc(&config, &state, "tb1qwallet", "key-1", &Cell::new(0)).await.unwrap();
+        request_lava_usd(&config, &state, "pubkey", "key-2", &Cell::new(0)).await.unwrap();
+        
+        let header = |request: &MockRequest, name: &str| {
+            request.headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.clone())
+        };
+        let btc = &btc_faucet.requests()[0];
+        assert_eq!(btc.path, "/btc");
+        assert_eq!(header(btc, "authorization").as_deref(), Some("Bearer btc-token"));
+        assert_eq!(header(btc, "x-client").as_deref(), Some("suite"));
+        assert!(header(btc, "x-api-key").is_none());
+        
+        let lava_usd = &lava_usd_faucet.requests()[0];
+        assert_eq!(lava_usd.path, "/lava");
+        assert_eq!(header(lava_usd, "authorization").as_deref(), Some("Bearer shared"));
+        assert_eq!(header(lava_usd, "x-api-key").as_deref(), Some("lava-key"));
+    }
+    
+    #[actix_web::test]
+    async fn cross_host_faucet_redirect_follows_the_configured_policy() {
+        let target = MockServer::start(vec![(200, json!({ "txid": TXID }).to_string())]).await;
+        // Another port counts as another host
+        let faucet = MockServer::start(vec![(307, format!("{}/moved", target.url))]).await;
+        let mut config = Config::from_env();
+        config.btc_faucet_url = faucet.url.clone();
+        
+        assert_eq!(config.faucet_redirect_policy, FaucetRedirectPolicy::SameHost);
+        let error = request_btc(&config, &test_state(1, 5), "tb1qwallet", "key-1", &Cell::new(0)).await.unwrap_err();
+        assert!(error.to_string().contains("not followed"), "{}", error);
+        assert!(target.requests().is_empty());
+        
+        config.faucet_redirect_policy = FaucetRedirectPolicy::All;
+        let response = request_btc(&config, &test_state(1, 5), "tb1qwallet", "key-2", &Cell::new(0)).await.unwrap();
+        assert_eq!(response.txid.as_deref(), Some(TXID));
+        let requests = target.requests();
+        assert_eq!((requests.len(), requests[0].path.as_str()), (1, "/moved"));
+        assert!(requests[0].body.contains("tb1qwallet"));
+    }
+    
+    #[actix_web::test]
+    async fn faucet_probe_records_each_faucet_on_its_interval() {
+        let btc = MockServer::start(vec![(200, "{}".to_string())]).await;
+        let lava_usd = MockServer::start(vec![(503, "{}".to_string())]).await;
+        let mut config = Config::from_env();
+        config.btc_faucet_url = btc.url.clone();
+        config.lava_usd_faucet_url = lava_usd.url.clone();
+        let db_pool = web::Data::new(test_pool());
+        
+        let probe = tokio::spawn(run_faucet_probe(web::Data::new(config), db_pool.clone(), 100));
+        sleep(Duration::from_millis(350)).await;
+        probe.abort();
+        
+        let probes = get_faucet_probes(&db_pool, 100, 0).unwrap();
+        let statuses = |faucet: &str| -> Vec<String> {
+            probes.iter().filter(|p| p.faucet == faucet).map(|p| p.status.clone()).collect()
+        };
+        // Ticks at 0, 100, 200 and 300ms
+        assert!((3..=4).contains(&statuses("btc").len()), "{:?}", statuses("btc"));
+        assert!(statuses("btc").iter().all(|s| s == "up"));
+        assert!(statuses("lava_usd").iter().all(|s| s == "degraded"));
+        // Probes are plain GETs, never a mint
+        assert!(btc.requests().iter().all(|r| r.method == "GET"));
+    }
+    
+    #[actix_web::test]
+    async fn faucet_calls_respect_their_own_cap_across_runs() {
+        use std::sync::atomic::AtomicUsize;
+        
+        // A faucet that answers every connection concurrently after 100ms, tracking the peak
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let (counter, max) = (in_flight.clone(), peak.clone());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let (counter, max) = (counter.clone(), max.clone());
+                tokio::spawn(async move {
+                    if read_mock_request(&mut socket).await.is_none() {
+                        return;
+                    }
+                    let now = counter.fetch_add(1, Ordering::SeqCst) + 1;
+                    max.fetch_max(now, Ordering::SeqCst);
+                    sleep(Duration::from_millis(100)).await;
+                    counter.fetch_sub(1, Ordering::SeqCst);
+                    let body = json!({ "txid": TXID }).to_string();
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = tokio::io::AsyncWriteExt::write_all(&mut socket, response.as_bytes()).await;
+                });
+            }
+        });
+        
+        let mut config = Config::from_env();
+        config.btc_faucet_url = url;
+        config.max_concurrent_runs = 6;
+        config.max_concurrent_faucet_calls = 2;
+        let state = AppState::new(&config);
+        
+        let waits: Vec<Cell<u64>> = (0..6).map(|_| Cell::new(0)).collect();
+        let calls = waits.iter().enumerate().map(|(run, wait)| {
+            let (config, state) = (&config, &state);
+            async move { request_btc(config, state, "tb1qwallet", &format!("key-{}", run), wait).await }
+        });
+        for response in join_all(calls).await {
+            assert_eq!(response.unwrap().txid.as_deref(), Some(TXID));
+        }
+        
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        // Six calls through two slots: two go straight away, the rest wait their turn
+        assert_eq!(waits.iter().filter(|wait| wait.get() >= 90).count(), 4);
+    }
+    
+    #[actix_web::test]
+    async fn lava_usd_signature_is_captured_as_txid() {
+        let signature = "5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW";
+        let faucet = MockServer::start(vec![(200, json!({ "signature": signature }).to_string())]).await;
+        let mut config = Config::from_env();
+        config.lava_usd_faucet_url = faucet.url.clone();
+        
+        let response = request_lava_usd(&config, &test_state(1, 5), "pubkey", "key-1", &Cell::new(0)).await.unwrap();
+        assert_eq!(response.txid.as_deref(), Some(signature));
+        
+        // The BTC faucet doesn't accept a bare signature unless configured to
+        let parsed = parse_faucet_response(
+            reqwest::StatusCode::OK,
+            &json!({ "signature": signature }).to_string(),
+            &config.btc_faucet_txid_paths,
+        );
+        assert!(parsed.txid.is_none());
+    }
+    
+    #[actix_web::test]
+    async fn accepted_faucet_with_confirmation_timeout_is_not_funded() {
+        let faucet = MockServer::start(vec![(200, json!({ "txid": TXID }).to_string())]).await;
+        let explorer = MockServer::start(vec![(200, json!({ "confirmed": false }).to_string())]).await;
+        let mut config = Config::from_env();
+        config.btc_faucet_url = faucet.url.clone();
+        config.explorer_url = explorer.url.clone();
+        config.confirmation_poll_interval_ms = 100;
+        config.confirmation_timeout_ms = 300;
+        
+        let db_pool = test_pool();
+        let mut result = TestResult::new("run".to_string(), &Mnemonic::default(), "tb1qwallet", "pk");
+        let response = request_btc(&config, &test_state(1, 5), "tb1qwallet", "key-1", &Cell::new(0)).await.unwrap();
+        result.faucet_accepted = response.txid.is_some();
+        confirm_funding(&config, &db_pool, &mut result, response.txid.as_deref().unwrap()).await;
+        
+        assert!(result.faucet_accepted);
+        assert!(!result.funds_confirmed);
+        assert_eq!(result.details.as_ref().unwrap()["funding_confirmation"]["confirmed"], false);
+        assert!(explorer.requests()[0].path.ends_with(&format!("/api/tx/{}/status", TXID)));
+        
+        // Both flags survive a round trip through /results storage
+        save_test_result(&db_pool, &result).unwrap();
+        let stored = get_test_result_by_id(&db_pool, "run").unwrap().unwrap();
+        assert!(stored.faucet_accepted && !stored.funds_confirmed);
+    }
+    
+    #[test]
+    fn oversized_details_are_truncated_and_flagged() {
+        let mut result = TestResult::new("run".to_string(), &Mnemonic::default(), "tb1q", "pk");
+        result.record_detail("cli_output", json!("é".repeat(5000)));
+        result.cap_details(1024);
+        
+        assert!(result.details_truncated);
+        let details = result.details.as_ref().unwrap();
+        assert_eq!(details["truncated"], true);
+        assert!(details["original_bytes"].as_u64().unwrap() > 1024);
+        assert!(serde_json::to_string(details).unwrap().len() <= 1024);
+        
+        let mut small = TestResult::new("run".to_string(), &Mnemonic::default(), "tb1q", "pk");
+        small.record_detail("cli_output", json!("ok"));
+        small.cap_details(1024);
+        assert!(!small.details_truncated);
+        assert_eq!(small.details.unwrap()["cli_output"], "ok");
+    }
+    
+    #[actix_web::test]
+    async fn mid_suite_failure_still_returns_faucet_funds() {
+        let faucet = MockServer::start(vec![(200, json!({ "txid": TXID }).to_string())]).await;
+        let mut config = Config::from_env();
+        config.allow_placeholder_wallet = true;
+        config.enable_failure_injection = true;
+        config.connectivity_probe_url = faucet.url.clone();
+        config.btc_faucet_url = faucet.url.clone();
+        config.cleanup_return_funds = true;
+        
+        let db_pool = test_pool();
+        let request: RunTestRequest = serde_json::from_value(json!({ "fail_at_step": "request_lava_usd" })).unwrap();
+        let result = run_test_suite(&config, &db_pool, &test_state(1, 5), &request, Uuid::new_v4().to_string()).await.unwrap();
+        
+        assert_eq!(result.status, "failed");
+        assert!(result.faucet_accepted);
+        assert!(result.returned_funds);
+        let cleanup = &result.details.as_ref().unwrap()["cleanup_return"];
+        assert_eq!(cleanup["succeeded"], true);
+        assert_eq!(cleanup["txid"], json!(result.return_txid));
+        assert_eq!(find_fund_return(&db_pool, &result.btc_address, None, &result.id).unwrap(), result.return_txid);
+    }
+    
+    #[test]
+    fn wallet_derives_from_mnemonic_file() {
+        let path = std::env::temp_dir().join(format!("mnemonic_{}", Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            "  abandon abandon abandon abandon abandon abandon\nabandon abandon abandon abandon abandon about\n",
+        )
+        .unwrap();
+        let mut config = Config::from_env();
+        config.mnemonic_file = Some(path.to_string_lossy().to_string());
+        
+        let (mnemonic, btc_address, lava_usd_pubkey) = generate_wallet(&config, 0).unwrap();
+        assert_eq!(mnemonic.expose().split(' ').count(), 12);
+        assert_eq!(btc_address, "tb1q6rz28mcfaxtmd6v789l9rrlrusdprr9pqcpvkl");
+        assert_eq!(lava_usd_pubkey, "HAgk14JpMQLgt6rVgv7cBQFJWFto5Dqxi472uT3DKpqk");
+        assert!(!is_placeholder_wallet(mnemonic.expose(), &btc_address));
+        
+        // Another account of the same mnemonic is a different wallet
+        let (_, other_btc_address, other_lava_usd_pubkey) = generate_wallet(&config, 1).unwrap();
+        assert_ne!(other_btc_address, btc_address);
+        assert_ne!(other_lava_usd_pubkey, lava_usd_pubkey);
+        assert_ne!(generate_wallet(&config, 2).unwrap().1, other_btc_address);
+        let out_of_range: RunTestRequest = serde_json::from_value(json!({ "account_index": MAX_ACCOUNT_INDEX + 1 })).unwrap();
+        assert!(validate_run_request(&out_of_range, &config).unwrap_err().contains("account_index"));
+        
+        // A phrase with a bad checksum is rejected rather than run with the demo addresses
+        std::fs::write(&path, "abandon ".repeat(12)).unwrap();
+        assert!(generate_wallet(&config, 0).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    #[test]
+    fn result_debug_does_not_reveal_the_mnemonic() {
+        let mnemonic = Mnemonic::new("top secret words".to_string());
+        let result = TestResult::new("run".to_string(), &mnemonic, "tb1q", "pk");
+        let debug = format!("{:?}", result);
+        assert!(!debug.contains("top secret words"));
+        assert!(debug.contains("[REDACTED]"));
+    }
+    
+    #[test]
+    fn cli_output_is_decoded_and_stripped_of_ansi() {
+        let mut bytes = b"\x1b[32mloan created\x1b[0m \xff\xfe".to_vec();
+        bytes.extend_from_slice(b"\x1b]0;title\x07\x1b[2K\rdone\n");
+        
+        let clean = sanitize_output(&bytes);
+        assert_eq!(clean, "loan created \u{fffd}\u{fffd}\rdone");
+        assert!(!clean.contains('\x1b'));
+        
+        let output = std::process::Command::new("sh")
+            .args(["-c", "printf '\\033[31mboom\\033[0m' >&2; exit 3"])
+            .output()
+            .unwrap();
+        let message = command_error(&output, &Config::from_env());
+        assert!(message.starts_with("boom ("));
+    }
+    
+    #[actix_web::test]
+    async fn concurrent_runs_get_separate_run_dirs() {
+        let faucet = MockServer::start(vec![(200, json!({ "txid": TXID }).to_string())]).await;
+        let base = std::env::temp_dir().join(format!("runs_{}", Uuid::new_v4()));
+        let mut config = Config::from_env();
+        config.allow_placeholder_wallet = true;
+        config.enable_failure_injection = true;
+        config.connectivity_probe_url = faucet.url.clone();
+        config.btc_faucet_url = faucet.url.clone();
+        config.run_dir_base = base.to_string_lossy().to_string();
+        config.keep_run_dirs = true;
+        
+        let db_pool = test_pool();
+        let state = test_state(2, 5);
+        let request: RunTestRequest = serde_json::from_value(json!({ "fail_at_step": "request_lava_usd" })).unwrap();
+        let (first, second) = futures::future::join(
+            run_test_suite(&config, &db_pool, &state, &request, Uuid::new_v4().to_string()),
+            run_test_suite(&config, &db_pool, &state, &request, Uuid::new_v4().to_string()),
+        )
+        .await;
+        let (first, second) = (first.unwrap(), second.unwrap());
+        
+        let run_dir = |result: &TestResult| result.details.as_ref().unwrap()["run_dir"].as_str().unwrap().to_string();
+        assert_ne!(first.id, second.id);
+        assert_ne!(run_dir(&first), run_dir(&second));
+        assert_eq!(run_dir(&first), base.join(&first.id).to_string_lossy());
+        
+        // Removing a run directory that was never created is not an error
+        assert!(remove_run_dir(&base.join("never-created")).is_ok());
+        
+        // A run's CLI log goes with its run directory unless KEEP_RUN_DIRS is set
+        let work_dir = std::env::temp_dir().join(format!("cli_work_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&work_dir).unwrap();
+        config.cli_work_dir = work_dir.to_string_lossy().to_string();
+        config.connectivity_probe_url = "http://127.0.0.1:9".to_string();
+        for keep in [true, false] {
+            config.keep_run_dirs = keep;
+            let id = Uuid::new_v4().to_string();
+            let log = cli_log_path(&config, &base.join(&id));
+            std::fs::write(&log, "$ loans-borrower-cli --verbose --help\n").unwrap();
+            run_test_suite(&config, &db_pool, &state, &request, id).await.unwrap();
+            assert_eq!(log.exists(), keep);
+        }
+        std::fs::remove_dir_all(&work_dir).unwrap();
+        let _ = std::fs::remove_dir_all(&base);
+    }
+    
+    /// Faucet that holds each response until another gated faucet has a request in flight
+    /// too, or a second has passed. Records whether each request overlapped another.
+    async fn start_gated_faucet(gate: Arc<tokio::sync::Barrier>, overlapped: Arc<Mutex<Vec<bool>>>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                if read_mock_request(&mut socket).await.is_none() {
+                    continue;
+                }
+                let met = tokio::time::timeout(Duration::from_secs(1), gate.wait()).await.is_ok();
+                overlapped.lock().unwrap().push(met);
+                let body = json!({ "txid": TXID }).to_string();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = tokio::io::AsyncWriteExt::write_all(&mut socket, response.as_bytes()).await;
+            }
+        });
+        url
+    }
+    
+    #[actix_web::test]
+    async fn parallel_mode_has_both_faucet_requests_in_flight_at_once() {
+        let probe = MockServer::start(vec![(200, "{}".to_string())]).await;
+        let gate = Arc::new(tokio::sync::Barrier::new(2));
+        let overlapped = Arc::new(Mutex::new(Vec::new()));
+        let mut config = Config::from_env();
+        config.allow_placeholder_wallet = true;
+        config.enable_failure_injection = true;
+        config.connectivity_probe_url = probe.url.clone();
+        config.btc_faucet_url = start_gated_faucet(gate.clone(), overlapped.clone()).await;
+        config.lava_usd_faucet_url = start_gated_faucet(gate, overlapped.clone()).await;
+        config.parallel_faucet_requests = true;
+        config.max_concurrent_faucet_calls = 2;
+        let state = AppState::new(&config);
+        
+        let request: RunTestRequest = serde_json::from_value(json!({ "fail_at_step": "setup_cli" })).unwrap();
+        let result = run_test_suite(&config, &test_pool(), &state, &request, Uuid::new_v4().to_string()).await.unwrap();
+        
+        assert_eq!(*overlapped.lock().unwrap(), [true, true]);
+        assert_eq!(result.details.as_ref().unwrap()["faucet_requests"], "parallel");
+        assert_eq!(result.lava_usd_faucet_response.txid.as_deref(), Some(TXID));
+        let status = |name: &str| result.steps.iter().find(|s| s.name == name).map(|s| s.status.clone());
+        assert_eq!(status("request_btc").as_deref(), Some("success"));
+        assert_eq!(status("request_lava_usd").as_deref(), Some("success"));
+        // No pause between the requests when they run together
+        assert_eq!(status("faucet_wait"), None);
+        
+        // One faucet serving both assets gets its requests one at a time
+        let faucet = MockServer::start(vec![(200, json!({ "txid": TXID }).to_string())]).await;
+        config.btc_faucet_url = faucet.url.clone();
+        config.lava_usd_faucet_url = format!("{}/", faucet.url);
+        let result = run_test_suite(&config, &test_pool(), &state, &request, Uuid::new_v4().to_string()).await.unwrap();
+        assert_eq!(result.details.as_ref().unwrap()["faucet_requests"], "sequential");
+        assert!(result.steps.iter().any(|s| s.name == "faucet_wait"));
+        
+        // A BTC failure still leaves the LavaUSD payout that already went out on the result
+        let lava_usd_faucet = MockServer::start(vec![(200, json!({ "txid": TXID }).to_string())]).await;
+        config.lava_usd_faucet_url = lava_usd_faucet.url.clone();
+        let request: RunTestRequest = serde_json::from_value(json!({ "fail_at_step": "request_btc" })).unwrap();
+        let result = run_test_suite(&config, &test_pool(), &state, &request, Uuid::new_v4().to_string()).await.unwrap();
+        assert_eq!(result.details.as_ref().unwrap()["faucet_requests"], "parallel");
+        assert_eq!(result.status, "failed");
+        assert_eq!(result.lava_usd_faucet_response.txid.as_deref(), Some(TXID));
+        let lava_usd_step = result.steps.iter().find(|s| s.name == "request_lava_usd").unwrap();
+        assert_eq!(lava_usd_step.status, "success");
+    }
+    
+    #[actix_web::test]
+    async fn preserved_wallet_keeps_its_funds_and_is_never_archived() {
+        let faucet = MockServer::start(vec![(200, json!({ "txid": TXID }).to_string())]).await;
+        let mut config = Config::from_env();
+        config.allow_placeholder_wallet = true;
+        config.enable_failure_injection = true;
+        config.cleanup_return_funds = true;
+        config.connectivity_probe_url = faucet.url.clone();
+        config.btc_faucet_url = faucet.url.clone();
+        let db_pool = test_pool();
+        let state = test_state(1, 5);
+        
+        // A failed run would normally have its faucet funds returned by the cleanup
+        let preserve: RunTestRequest = serde_json::from_value(json!({ "fail_at_step": "request_lava_usd", "preserve_wallet": true })).unwrap();
+        let preserved = run_test_suite(&config, &db_pool, &state, &preserve, Uuid::new_v4().to_string()).await.unwrap();
+        assert!(preserved.faucet_accepted);
+        assert!(preserved.preserved_wallet);
+        assert!(!preserved.returned_funds);
+        assert!(preserved.details.as_ref().unwrap().get("cleanup_return").is_none());
+        
+        let plain: RunTestRequest = serde_json::from_value(json!({ "fail_at_step": "request_lava_usd" })).unwrap();
+        let cleaned = run_test_suite(&config, &db_pool, &state, &plain, Uuid::new_v4().to_string()).await.unwrap();
+        assert!(cleaned.returned_funds);
+        
+        save_test_result(&db_pool, &preserved).unwrap();
+        save_test_result(&db_pool, &cleaned).unwrap();
+        let later = (chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc3339();
+        assert_eq!(archive_old_results(&db_pool, Some(&later), Some(&later)).unwrap(), 1);
+        let listed = get_test_results(&db_pool, &ResultFilter::default()).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, preserved.id);
+        assert!(listed[0].preserved_wallet);
+        
+        let conflicting: RunTestRequest = serde_json::from_value(json!({ "preserve_wallet": true, "return_funds": true })).unwrap();
+        assert!(validate_run_request(&conflicting, &config).is_err());
+    }
+    
+    #[actix_web::test]
+    async fn sync_run_answers_with_the_finished_result() {
+        let faucet = MockServer::start(vec![(200, json!({ "txid": TXID }).to_string())]).await;
+        let mut config = Config::from_env();
+        config.allow_placeholder_wallet = true;
+        config.enable_failure_injection = true;
+        config.connectivity_probe_url = faucet.url.clone();
+        config.btc_faucet_url = faucet.url.clone();
+        let db_pool = test_pool();
+        let app = |config: Config| {
+            App::new()
+                .app_data(web::Data::new(db_pool.clone()))
+                .app_data(web::Data::new(config))
+                .app_data(web::Data::new(test_state(1, 5)))
+                .route("/run-test-sync", web::post().to(run_test_sync_handler))
+        };
+        let body = json!({ "fail_at_step": "request_lava_usd" });
+        
+        let service = actix_web::test::init_service(app(config.clone())).await;
+        let request = actix_web::test::TestRequest::post().uri("/run-test-sync").set_json(&body).to_request();
+        let response = actix_web::test::call_service(&service, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let result: Value = actix_web::test::read_body_json(response).await;
+        assert_eq!(result["status"], "failed");
+        assert_eq!(result["details"]["error_kind"], "lava_usd_faucet_failed");
+        assert_eq!(result["mnemonic"], "[REDACTED]");
+        let stored = get_test_result_by_id(&db_pool, result["id"].as_str().unwrap()).unwrap().unwrap();
+        assert_eq!(stored.status, "failed");
+        
+        // Out of time the response names the run, which still finishes and is saved;
+        // a multi-wallet run is named by its batch result
+        config.sync_run_timeout_secs = 0;
+        let service = actix_web::test::init_service(app(config)).await;
+        for body in [body.clone(), json!({ "fail_at_step": "request_lava_usd", "wallets": 2 })] {
+            let request = actix_web::test::TestRequest::post().uri("/run-test-sync").set_json(&body).to_request();
+            let response = actix_web::test::call_service(&service, request).await;
+            assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+            let pending: Value = actix_web::test::read_body_json(response).await;
+            assert_eq!(pending["status"], "running");
+            let id = pending["id"].as_str().unwrap().to_string();
+            // The ID resolves straight away, to a running placeholder
+            let mut stored = get_test_result_by_id(&db_pool, &id).unwrap().unwrap();
+            assert_eq!(stored.status, "running", "{}", body);
+            for _ in 0..150 {
+                if stored.status != "running" {
+                    break;
+                }
+                sleep(Duration::from_millis(100)).await;
+                stored = get_test_result_by_id(&db_pool, &id).unwrap().unwrap();
+            }
+            assert_eq!(stored.status, "failed", "{}", body);
+        }
+    }
+    
+    #[test]
+    fn short_cli_download_is_rejected_as_truncated() {
+        let mut config = Config::from_env();
+        config.cli_min_size_bytes = 16;
+        let binary = vec![0x7f; 64];
+        
+        // Empty, cut short of Content-Length, and implausibly small without one
+        for (content, content_length) in [(&binary[..0], None), (&binary[..40], Some(64)), (&binary[..8], None)] {
+            let error = check_cli_download(&config, content, content_length).unwrap_err();
+            assert!(error.downcast_ref::<CliDownloadTruncated>().is_some());
+            assert!(error.to_string().starts_with("cli_download_truncated"), "{}", error);
+        }
+        assert_eq!(
+            check_cli_download(&config, &binary[..40], Some(64)).unwrap_err().to_string(),
+            "cli_download_truncated: received 40 of 64 bytes"
+        );
+        assert!(check_cli_download(&config, &binary, Some(64)).is_ok());
+        assert!(check_cli_download(&config, &binary[..20], None).is_ok());
+    }
+    
+    #[actix_web::test]
+    async fn extra_cli_flags_are_appended_only_when_requested() {
+        let base = std::env::temp_dir().join(format!("cli_args_{}", Uuid::new_v4()));
+        let run_dir = base.join("run-a");
+        std::fs::create_dir_all(&run_dir).unwrap();
+        let cli_path = run_dir.join(CLI_BINARY_NAME);
+        std::fs::write(&cli_path, "#!/bin/sh\necho \"args: $*\"\n").unwrap();
+        std::process::Command::new("chmod").arg("+x").arg(&cli_path).output().unwrap();
+        let mut config = Config::from_env();
+        config.cli_work_dir = base.to_string_lossy().to_string();
+        let live_runs = live::LiveRuns::default();
+        // Through setup_cli's check, the CLI invocation a run makes
+        let check = |request: serde_json::Value| {
+            let request: RunTestRequest = serde_json::from_value(request).unwrap();
+            let (config, run_dir, live_runs) = (&config, &run_dir, &live_runs);
+            async move {
+                let _live = live_runs.start("run");
+                check_cli_runs(config, run_dir, &request.cli_args(), live_runs.run("run")).await.map(|_| live_runs.lines("run").join("\n"))
+            }
+        };
+        
+        assert_eq!(check(json!({})).await.unwrap(), "args: --help");
+        assert_eq!(check(json!({ "cli_verbose": false })).await.unwrap(), "args: --help");
+        // Nothing is logged for a run that didn't ask for flags
+        assert!(collect_cli_log_files(&config.cli_work_dir).unwrap().is_empty());
+        assert_eq!(check(json!({ "cli_verbose": true })).await.unwrap(), "args: --verbose --help");
+        assert_eq!(check(json!({ "cli_verbose": true, "extra_cli_args": ["--verbose", "-vv"] })).await.unwrap(), "args: --verbose -vv --help");
+        
+        // A run with extra flags keeps the CLI's output where /cli/logs finds it
+        check(json!({ "extra_cli_args": ["--debug"] })).await.unwrap();
+        let log = cli_log_path(&config, &run_dir);
+        assert!(std::fs::read_to_string(&log).unwrap().contains("args: --debug --help"));
+        assert_eq!(collect_cli_log_files(&config.cli_work_dir).unwrap(), ["cli-run-a.log"]);
+        // and on the result itself
+        let mut result = TestResult::new("run-a".to_string(), &Mnemonic::default(), "tb1q", "pk");
+        record_cli_logs(&config, &run_dir, &mut result);
+        let logs = result.details.as_ref().unwrap()["cli_logs"].as_str().unwrap().to_string();
+        assert!(logs.contains(&format!("$ {} --debug --help", CLI_BINARY_NAME)), "{}", logs);
+        assert!(logs.contains("args: --debug --help"), "{}", logs);
+        
+        // An allowed flag the binary rejects fails the check, so setup fails
+        std::fs::write(&cli_path, "#!/bin/sh\ncase \"$*\" in *--log-level=trace*) echo 'unknown flag' >&2; exit 2;; esac\necho ok\n").unwrap();
+        assert!(check(json!({})).await.is_ok());
+        let error = check(json!({ "extra_cli_args": ["--log-level=trace"] })).await.unwrap_err();
+        assert!(format!("{:#}", error).contains("unknown flag"), "{:#}", error);
+        assert_eq!(cli_exit_code(&error), Some(2));
+        assert!(std::fs::read_to_string(&log).unwrap().contains("--log-level=trace --help"));
+        std::fs::remove_dir_all(&base).unwrap();
+        
+        let config = Config::from_env();
+        let disallowed: RunTestRequest = serde_json::from_value(json!({ "extra_cli_args": ["--verbose", "--network=mainnet"] })).unwrap();
+        let error = validate_run_request(&disallowed, &config).unwrap_err();
+        assert!(error.contains("'--network=mainnet'"), "{}", error);
+        let allowed: RunTestRequest = serde_json::from_value(json!({ "extra_cli_args": ["--log-level=trace"] })).unwrap();
+        assert!(validate_run_request(&allowed, &config).is_ok());
+    }
+    
+    #[actix_web::test]
+    async fn cli_logs_are_streamed_as_a_tar_without_wallet_files() {
+        let work_dir = std::env::temp_dir().join(format!("cli_logs_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&work_dir).unwrap();
+        std::fs::write(work_dir.join("cli-run.log"), "borrow init ok\n").unwrap();
+        std::fs::write(work_dir.join("wallet.log"), "seed words\n").unwrap();
+        let mut config = Config::from_env();
+        config.cli_work_dir = work_dir.to_string_lossy().to_string();
+        config.api_key = Some("test-key".to_string());
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(web::Data::new(config))
+                .route("/cli/logs", web::get().to(cli_logs_handler)),
+        )
+        .await;
+        
+        let req = actix_web::test::TestRequest::get().uri("/cli/logs").insert_header(("X-API-Key", "test-key"));
+        let res = actix_web::test::call_service(&app, req.to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = actix_web::test::read_body(res).await;
+        let mut archive = Vec::new();
+        flate2::read::GzDecoder::new(&body[..]).read_to_end(&mut archive).unwrap();
+        let archive = String::from_utf8_lossy(&archive);
+        assert!(archive.contains("cli-run.log") && archive.contains("borrow init ok"));
+        assert!(!archive.contains("wallet.log") && !archive.contains("seed words"));
+        std::fs::remove_dir_all(&work_dir).unwrap();
+    }
+    
+    #[actix_web::test]
+    async fn cli_exit_code_is_recorded_and_filterable() {
+        let run_dir = std::env::temp_dir().join(format!("cli_exit_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&run_dir).unwrap();
+        let cli_path = run_dir.join(CLI_BINARY_NAME);
+        std::fs::write(&cli_path, "#!/bin/sh\n[ \"$1\" = create ] && exit 4\necho 'wallet locked' >&2\nexit 3\n").unwrap();
+        std::process::Command::new("chmod").arg("+x").arg(&cli_path).output().unwrap();
+        
+        let config = Config::from_env();
+        let request: RunTestRequest = serde_json::from_value(json!({})).unwrap();
+        let policy = RetryPolicy { max_attempts: 1, backoff_ms: 0, retry_on_error: false, retry_on_server_error: false };
+        let mut result = TestResult::new(Uuid::new_v4().to_string(), &Mnemonic::default(), "tb1q", "pk");
+        let live_runs = live::LiveRuns::default();
+        // Any step's failed CLI invocation is recorded on the step, but only the step that
+        // fails the run sets the run's exit code
+        let outcome = run_step(&mut result, &request, "create_loan", &policy, is_retryable, || async {
+            run_cli(cli_command(&run_dir, &run_dir, &[]).arg("create"), &config, None, live_runs.run("run")).await.map(|_| ())
+        })
+        .await;
+        assert!(outcome.is_err());
+        assert_eq!(result.steps[0].exit_code, Some(4));
+        assert_eq!(result.cli_exit_code, None);
+        // setup_cli's check of the downloaded binary, the CLI invocation the step makes
+        let error = run_step(&mut result, &request, "setup_cli", &policy, is_retryable, || {
+            check_cli_runs(&config, &run_dir, &[], live_runs.run("run"))
+        })
+        .await
+        .unwrap_err();
+        std::fs::remove_dir_all(&run_dir).unwrap();
+        result.fail_from_step(TestError::SetupCliFailed, &error, format!("Failed to setup CLI: {}", error));
+        
+        assert!(error.to_string().contains("wallet locked"));
+        assert!(result.steps[1].error.as_deref().unwrap().contains("wallet locked"));
+        assert_eq!(result.steps[1].exit_code, Some(3));
+        assert_eq!(result.cli_exit_code, Some(3));
+        
+        let db_pool = test_pool();
+        save_test_result(&db_pool, &result).unwrap();
+        save_test_result(&db_pool, &TestResult::new(Uuid::new_v4().to_string(), &Mnemonic::default(), "tb1q", "pk")).unwrap();
+        let by_code = |code| get_test_results(&db_pool, &ResultFilter { cli_exit_code: Some(code), ..Default::default() }).unwrap();
+        let matched = by_code(3);
+        assert_eq!(matched.len(), 1);
+        assert_eq!(matched[0].id, result.id);
+        assert_eq!(matched[0].steps[1].exit_code, Some(3));
+        assert!(by_code(4).is_empty());
+    }
+    
+    #[actix_web::test]
+    async fn daily_budget_blocks_funding_until_the_reset() {
+        let db_pool = test_pool();
+        let mut config = Config::from_env();
+        config.daily_wallet_cap = Some(2);
+        config.daily_budget_reset_hour = 6;
+        let noon = chrono::DateTime::parse_from_rfc3339("2026-03-10T12:00:00Z").unwrap().with_timezone(&chrono::Utc);
+        
+        assert!(reserve_daily_budget(&config, &db_pool, "a", noon).is_ok());
+        assert!(reserve_daily_budget(&config, &db_pool, "b", noon).is_ok());
+        // A run that already holds a reservation doesn't use more budget
+        assert!(reserve_daily_budget(&config, &db_pool, "b", noon).is_ok());
+        let error = reserve_daily_budget(&config, &db_pool, "c", noon).unwrap_err();
+        assert!(error.starts_with("daily_budget_exceeded"), "{}", error);
+        assert!(error.contains("2026-03-11T06:00:00+00:00"), "{}", error);
+        
+        // The day runs until the next reset at 06:00 UTC, not midnight
+        assert!(reserve_daily_budget(&config, &db_pool, "c", noon + chrono::Duration::hours(17)).is_err());
+        let next_day = noon + chrono::Duration::hours(18);
+        assert!(reserve_daily_budget(&config, &db_pool, "c", next_day).is_ok());
+        
+        // The sats cap counts the faucet amount of each funded wallet
+        config.daily_wallet_cap = None;
+        config.daily_sats_cap = Some(config.btc_faucet_amount.to_sats() * 3 / 2);
+        assert!(reserve_daily_budget(&config, &db_pool, "d", next_day).is_err());
+        
+        // A run over budget fails before asking the faucet
+        let faucet = MockServer::start(vec![(200, json!({ "txid": TXID }).to_string())]).await;
+        config.allow_placeholder_wallet = true;
+        config.connectivity_probe_url = faucet.url.clone();
+        config.btc_faucet_url = faucet.url.clone();
+        config.daily_sats_cap = Some(0);
+        let request: RunTestRequest = serde_json::from_value(json!({})).unwrap();
+        let result = run_test_suite(&config, &db_pool, &test_state(1, 5), &request, Uuid::new_v4().to_string()).await.unwrap();
+        assert_eq!(result.details.as_ref().unwrap()["error_kind"], "daily_budget_exceeded");
+        assert!(faucet.requests().iter().all(|r| r.method == "HEAD"));
+        
+        let budget = daily_budget_snapshot(&config, &db_pool).unwrap();
+        assert_eq!(budget["sats_remaining"], 0);
+        assert_eq!(budget["wallet_cap"], Value::Null);
+        
+        // A faucet answer without a txid paid nothing, so the reservation is given back
+        let dry = MockServer::start(vec![(200, json!({ "error": "faucet is dry" }).to_string())]).await;
+        config.btc_faucet_url = dry.url.clone();
+        config.daily_sats_cap = None;
+        config.daily_wallet_cap = Some(10);
+        let today = budget_day_start(&config, chrono::Utc::now()).to_rfc3339();
+        let before = get_faucet_usage_since(&db_pool, &today).unwrap();
+        let result = run_test_suite(&config, &db_pool, &test_state(1, 5), &request, Uuid::new_v4().to_string()).await.unwrap();
+        assert!(!result.faucet_accepted);
+        assert_eq!(get_faucet_usage_since(&db_pool, &today).unwrap(), before);
+    }
+    
+    #[actix_web::test]
+    async fn unconfirmed_repayment_is_flagged_as_a_discrepancy() {
+        let closed = json!({ "Closed": {}, "outcome": { "repayment": { "collateral_repayment_txid": TXID } } });
+        let mut config = Config::from_env();
+        config.confirmation_poll_interval_ms = 10;
+        config.repayment_confirmation_timeout_ms = 50;
+        
+        // The explorer has never seen the txid the CLI reported
+        let explorer = MockServer::start(vec![(404, "Transaction not found".to_string())]).await;
+        config.explorer_url = explorer.url.clone();
+        let mut result = TestResult::new("unconfirmed".to_string(), &Mnemonic::default(), "tb1q", "pk");
+        apply_contract_details(&mut result, closed.clone());
+        verify_repayment(&config, &mut result).await;
+        
+        assert!(!result.loan_closed);
+        assert!(!result.repayment_confirmed);
+        let discrepancy = &result.details.as_ref().unwrap()["repayment_discrepancy"];
+        assert_eq!(discrepancy["repayment_txid"], TXID);
+        assert!(explorer.requests()[0].path.ends_with(&format!("/api/tx/{}/status", TXID)));
+        
+        // The details still look closed, but the run doesn't pass
+        let request: RunTestRequest = serde_json::from_value(json!({ "return_funds": false })).unwrap();
+        return_funds_and_finish(&config, &test_pool(), &test_state(1, 5), &request, &mut result).await;
+        assert_eq!(result.status, "failed");
+        assert_eq!(result.details.as_ref().unwrap()["error_kind"], "loan_not_closed");
+        
+        let explorer = MockServer::start(vec![(200, json!({ "confirmed": true }).to_string())]).await;
+        config.explorer_url = explorer.url.clone();
+        let mut result = TestResult::new("confirmed".to_string(), &Mnemonic::default(), "tb1q", "pk");
+        apply_contract_details(&mut result, closed);
+        verify_repayment(&config, &mut result).await;
+        
+        assert!(result.loan_closed);
+        assert!(result.repayment_confirmed);
+        assert!(result.details.as_ref().unwrap().get("repayment_discrepancy").is_none());
+    }
+    
+    #[actix_web::test]
+    async fn cli_runs_with_its_own_state_dir() {
+        let base = std::env::temp_dir().join(format!("cli_state_{}", Uuid::new_v4()));
+        let mut config = Config::from_env();
+        let run_dir = base.join("runs").join("run-a");
+        
+        // Without CLI_STATE_DIR the state stays inside the run directory
+        assert_eq!(cli_state_dir(&config, &run_dir), run_dir.join("state"));
+        
+        config.cli_state_dir = Some(base.join("state").to_string_lossy().to_string());
+        let state_dir = cli_state_dir(&config, &run_dir);
+        assert_eq!(state_dir, base.join("state").join("run-a"));
+        assert_ne!(cli_state_dir(&config, &base.join("runs").join("run-b")), state_dir);
+        
+        // A stand-in CLI that reports where it would keep its state
+        std::fs::create_dir_all(&run_dir).unwrap();
+        let cli_path = run_dir.join(CLI_BINARY_NAME);
+        std::fs::write(&cli_path, "#!/bin/sh\necho \"$HOME|$XDG_CONFIG_HOME|$(pwd)\"\n").unwrap();
+        std::process::Command::new("chmod").arg("+x").arg(&cli_path).output().unwrap();
+        
+        let output = cli_command(&run_dir, &state_dir, &[]).output().await.unwrap();
+        let reported = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        let parts: Vec<&str> = reported.split('|').collect();
+        assert_eq!(parts[0], state_dir.to_string_lossy());
+        assert_eq!(parts[1], state_dir.join(".config").to_string_lossy());
+        assert!(parts[2].ends_with("run-a"), "{}", reported);
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+    
+    #[actix_web::test]
+    async fn replay_reuses_the_original_inputs() {
+        let faucet = MockServer::start(vec![(200, json!({ "txid": TXID }).to_string())]).await;
+        let mut config = Config::from_env();
+        config.allow_placeholder_wallet = true;
+        config.enable_failure_injection = true;
+        config.connectivity_probe_url = faucet.url.clone();
+        config.btc_faucet_url = faucet.url.clone();
+        config.api_key = Some("test-key".to_string());
+        
+        let db_pool = test_pool();
+        let inputs = json!({ "fail_at_step": "request_lava_usd", "preset": "default", "request_lava_usd": true });
+        let mut original = TestResult::new("original".to_string(), &Mnemonic::default(), "tb1q", "pk");
+        original.run_request = Some(serde_json::from_value(inputs.clone()).unwrap());
+        save_test_result(&db_pool, &original).unwrap();
+        
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(web::Data::new(config))
+                .app_data(web::Data::new(db_pool.clone()))
+                .app_data(web::Data::new(test_state(1, 5)))
+                .route("/results/{id}/replay", web::post().to(replay_result_handler)),
+        )
+        .await;
+        let replayed: Value = actix_web::test::call_and_read_body_json(
+            &app,
+            actix_web::test::TestRequest::post().uri("/results/original/replay").insert_header(("X-API-Key", "test-key")).to_request(),
+        )
+        .await;
+        
+        assert_ne!(replayed["id"], "original");
+        assert_eq!(replayed["parent_id"], "original");
+        assert_eq!(replayed["preset"], "default");
+        let stored = get_test_result_by_id(&db_pool, replayed["id"].as_str().unwrap()).unwrap().unwrap();
+        assert_eq!(serde_json::to_value(stored.run_request).unwrap(), serde_json::to_value(original.run_request).unwrap());
+    }
+    
+    #[actix_web::test]
+    async fn faucet_txid_from_an_earlier_run_is_rejected() {
+        let faucet = MockServer::start(vec![(200, json!({ "txid": TXID }).to_string())]).await;
+        let mut config = Config::from_env();
+        config.allow_placeholder_wallet = true;
+        config.connectivity_probe_url = faucet.url.clone();
+        config.btc_faucet_url = faucet.url.clone();
+        
+        let db_pool = test_pool();
+        let mut earlier = TestResult::new("earlier".to_string(), &Mnemonic::default(), "tb1q-other", "pk");
+        earlier.btc_faucet_response.txid = Some(TXID.to_string());
+        save_test_result(&db_pool, &earlier).unwrap();
+        
+        let result = run_test_suite(&config, &db_pool, &test_state(1, 5), &RunTestRequest::default(), Uuid::new_v4().to_string()).await.unwrap();
+        assert_eq!(result.status, "failed");
+        assert!(!result.faucet_accepted);
+        assert!(result.error_message.unwrap().contains("already recorded by an earlier run"));
+        assert_eq!(result.details.unwrap()["faucet_txid_check"]["seen_before"], true);
+    }
+    
+    #[actix_web::test]
+    async fn unmatched_routes_and_wrong_methods_get_json_errors() {
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(
+                    ErrorHandlers::new()
+                        .handler(StatusCode::NOT_FOUND, json_error_body)
+                        .handler(StatusCode::METHOD_NOT_ALLOWED, json_error_body)
+                        .handler(StatusCode::INTERNAL_SERVER_ERROR, json_error_body),
+                )
+                .route("/health", web::get().to(health_check))
+                .route("/boom", web::get().to(|| async { Err::<HttpResponse, _>(actix_web::error::ErrorInternalServerError("boom")) }))
+                .default_service(web::route().to(not_found_handler)),
+        )
+        .await;
+        let call = |req: actix_web::test::TestRequest| async {
+            let res = actix_web::test::call_service(&app, req.to_request()).await;
+            let status = res.status();
+            let body: Value = actix_web::test::read_body_json(res).await;
+            (status, body)
+        };
+        
+        let (status, body) = call(actix_web::test::TestRequest::get().uri("/nope")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"], "No route for GET /nope");
+        
+        let (status, body) = call(actix_web::test::TestRequest::delete().uri("/health")).await;
+        assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(body["error"], "Method DELETE not allowed for /health");
+        
+        let (status, body) = call(actix_web::test::TestRequest::get().uri("/boom")).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body["error"], "Internal Server Error");
+    }
+    
+    #[actix_web::test]
+    async fn skipped_fund_return_does_not_fail_the_run() {
+        let config = Config::from_env();
+        let mut result = TestResult::new("run".to_string(), &Mnemonic::default(), "tb1q", "pk");
+        result.loan_closed = true;
+        result.repayment_txid = Some("repay".to_string());
+        result.faucet_accepted = true;
+        result.details = Some(json!({ "Closed": {}, "outcome": { "repayment": { "collateral_repayment_txid": TXID } } }));
+        let request: RunTestRequest = serde_json::from_value(json!({ "return_funds": false })).unwrap();
+        
+        return_funds_and_finish(&config, &test_pool(), &test_state(1, 5), &request, &mut result).await;
+        
+        assert_eq!(result.status, "success");
+        assert!(!result.returned_funds);
+        assert!(result.return_txid.is_none());
+        let step = result.steps.iter().find(|s| s.name == "return_funds").unwrap();
+        assert_eq!(step.status, "skipped");
+        assert!(result.details.unwrap()["return_funds_skipped"].is_string());
+    }
+    
+    #[actix_web::test]
+    async fn failed_fund_return_fails_the_run_only_when_strict() {
+        let mut config = Config::from_env();
+        config.enable_failure_injection = true;
+        let request: RunTestRequest = serde_json::from_value(json!({ "fail_at_step": "return_funds" })).unwrap();
+        let closed_run = || {
+            let mut result = TestResult::new("run".to_string(), &Mnemonic::default(), "tb1q", "pk");
+            result.loan_closed = true;
+            result.details = Some(json!({ "Closed": {}, "outcome": { "repayment": { "collateral_repayment_txid": TXID } } }));
+            result
+        };
+        
+        let mut lenient = closed_run();
+        return_funds_and_finish(&config, &test_pool(), &test_state(1, 5), &request, &mut lenient).await;
+        assert_eq!(lenient.status, "success");
+        assert!(!lenient.returned_funds);
+        assert!(lenient.details.unwrap()["return_funds_error"].as_str().unwrap().contains("Injected failure"));
+        
+        config.strict_fund_return = true;
+        let mut strict = closed_run();
+        return_funds_and_finish(&config, &test_pool(), &test_state(1, 5), &request, &mut strict).await;
+        assert_eq!(strict.status, "failed");
+        assert!(strict.error_message.unwrap().starts_with("Loan closed but the fund return failed"));
+        assert_eq!(strict.details.unwrap()["error_kind"], "fund_return_failed");
+    }
+    
+    #[actix_web::test]
+    async fn waits_are_recorded_as_phases() {
+        let faucet = MockServer::start(vec![(200, json!({ "txid": TXID }).to_string())]).await;
+        let mut config = Config::from_env();
+        config.allow_placeholder_wallet = true;
+        config.enable_failure_injection = true;
+        config.connectivity_probe_url = faucet.url.clone();
+        config.btc_faucet_url = faucet.url.clone();
+        config.lava_usd_faucet_url = faucet.url.clone();
+        
+        let request: RunTestRequest = serde_json::from_value(json!({ "fail_at_step": "setup_cli" })).unwrap();
+        let result = run_test_suite(&config, &test_pool(), &test_state(1, 5), &request, Uuid::new_v4().to_string()).await.unwrap();
+        
+        let kinds: Vec<(&str, &str)> = result.steps.iter().map(|s| (s.name.as_str(), s.kind.as_str())).collect();
+        assert_eq!(
+            kinds,
+            [("request_btc", "step"), ("faucet_wait", "wait"), ("request_lava_usd", "step"), ("setup_cli", "step")]
+        );
+        assert!(result.steps[1].duration_ms >= 2000);
+    }
+    
+    #[actix_web::test]
+    async fn cancelling_queued_runs_leaves_the_running_one_alone() {
+        let faucet = MockServer::start(vec![(200, json!({ "txid": TXID }).to_string())]).await;
+        let mut config = Config::from_env();
+        config.allow_placeholder_wallet = true;
+        config.enable_failure_injection = true;
+        config.connectivity_probe_url = faucet.url.clone();
+        config.btc_faucet_url = faucet.url.clone();
+        config.lava_usd_faucet_url = faucet.url.clone();
+        config.api_key = Some("test-key".to_string());
+        
+        let state = web::Data::new(test_state(1, 5));
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(web::Data::new(config))
+                .app_data(web::Data::new(test_pool()))
+                .app_data(state.clone())
+                .route("/run-test", web::post().to(run_test_handler))
+                .route("/results/{id}", web::get().to(get_result_by_id_handler))
+                .route("/admin/cancel-queued", web::post().to(cancel_queued_handler)),
+        )
+        .await;
+        let run_test = || {
+            actix_web::test::TestRequest::post()
+                .uri("/run-test")
+                .set_json(json!({ "fail_at_step": "setup_cli" }))
+                .to_request()
+        };
+        
+        // The first run takes the only slot (~2s in the faucet wait) while the rest queue behind it
+        let running = actix_web::test::call_and_read_body_json::<_, _, Value>(&app, run_test());
+        let queue_and_cancel = async {
+            sleep(Duration::from_millis(300)).await;
+            let mut ids = Vec::new();
+            for _ in 0..3 {
+                let queued: Value = actix_web::test::call_and_read_body_json(&app, run_test()).await;
+                assert_eq!(queued["status"], "queued");
+                ids.push(queued["id"].as_str().unwrap().to_string());
+            }
+            let cancelled: Value = actix_web::test::call_and_read_body_json(
+                &app,
+                actix_web::test::TestRequest::post().uri("/admin/cancel-queued").insert_header(("X-API-Key", "test-key")).to_request(),
+            )
+            .await;
+            assert_eq!(cancelled["cancelled"], 3);
+            ids
+        };
+        let (finished, ids) = futures::future::join(running, queue_and_cancel).await;
+        
+        // The running one finished on its own terms
+        assert_eq!(finished["status"], "failed");
+        assert!(finished["error_message"].as_str().unwrap().contains("setup_cli"));
+        
+        // The slot is free again, yet none of the cancelled runs starts
+        sleep(Duration::from_millis(300)).await;
+        for id in &ids {
+            let stored: Value = actix_web::test::call_and_read_body_json(
+                &app,
+                actix_web::test::TestRequest::get().uri(&format!("/results/{}", id)).to_request(),
+            )
+            .await;
+            assert_eq!(stored["status"], "cancelled");
+            assert!(stored.get("queue_position").is_none());
+        }
+        assert_eq!(state.run_slots.available_permits(), 1);
+    }
+    
+    #[actix_web::test]
+    async fn queued_run_that_fails_to_start_is_stored_as_failed() {
+        let mut config = Config::from_env();
+        // The placeholder wallet is refused before any network call
+        config.mnemonic_file = None;
+        config.allow_placeholder_wallet = false;
+        let db_pool = web::Data::new(test_pool());
+        let state = web::Data::new(test_state(1, 5));
+        
+        let response = queue_run(web::Data::new(config), db_pool.clone(), state.clone(), RunTestRequest::default(), None);
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let queued: Value = serde_json::from_slice(&actix_web::body::to_bytes(response.into_body()).await.unwrap()).unwrap();
+        let id = queued["id"].as_str().unwrap();
+        
+        let mut stored = get_test_result_by_id(&db_pool, id).unwrap().unwrap();
+        for _ in 0..50 {
+            if stored.status != "queued" {
+                break;
+            }
+            sleep(Duration::from_millis(20)).await;
+            stored = get_test_result_by_id(&db_pool, id).unwrap().unwrap();
+        }
+        assert_eq!(stored.status, "failed");
+        assert!(stored.error_message.unwrap().contains("placeholder"));
+        assert_eq!(state.run_slots.available_permits(), 1);
+    }
+    
+    #[actix_web::test]
+    async fn queued_runs_move_up_as_slots_free() {
+        let faucet = MockServer::start(vec![(200, json!({ "txid": TXID }).to_string())]).await;
+        // A txid seen in an earlier run is rejected, so each run gets its own
+        let btc_faucet = MockServer::start(
+            ["a", "b", "c"].iter().map(|c| (200, json!({ "txid": c.repeat(64) }).to_string())).collect(),
+        )
+        .await;
+        let mut config = Config::from_env();
+        config.allow_placeholder_wallet = true;
+        config.enable_failure_injection = true;
+        config.connectivity_probe_url = faucet.url.clone();
+        config.btc_faucet_url = btc_faucet.url.clone();
+        config.lava_usd_faucet_url = faucet.url.clone();
+        
+        let state = web::Data::new(test_state(1, 5));
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(web::Data::new(config))
+                .app_data(web::Data::new(test_pool()))
+                .app_data(state.clone())
+                .route("/run-test", web::post().to(run_test_handler))
+                .route("/results/{id}", web::get().to(get_result_by_id_handler)),
+        )
+        .await;
+        
+        // Hold the only slot so every request queues; each run then takes ~2s (the faucet wait)
+        let slot = state.run_slots.try_acquire().unwrap();
+        let mut ids = Vec::new();
+        for expected in 1..=3 {
+            let queued: Value = actix_web::test::call_and_read_body_json(
+                &app,
+                actix_web::test::TestRequest::post()
+                    .uri("/run-test")
+                    .set_json(json!({ "fail_at_step": "setup_cli" }))
+                    .to_request(),
+            )
+            .await;
+            assert_eq!(queued["status"], "queued");
+            assert_eq!(queued["queue_position"], expected);
+            ids.push(queued["id"].as_str().unwrap().to_string());
+        }
+        let stored: Value = actix_web::test::call_and_read_body_json(
+            &app,
+            actix_web::test::TestRequest::get().uri(&format!("/results/{}", ids[2])).to_request(),
+        )
+        .await;
+        assert_eq!(stored["queue_position"], 3);
+        
+        let started = |id: &str| {
+            let deadline = Instant::now() + Duration::from_secs(10);
+            let state = state.clone();
+            let id = id.to_string();
+            async move {
+                while state.queue_position(&id).is_some() {
+                    assert!(Instant::now() < deadline, "queued run {} never started", id);
+                    sleep(Duration::from_millis(50)).await;
+                }
+            }
+        };
+        drop(slot);
+        started(&ids[0]).await;
+        assert_eq!((state.queue_position(&ids[1]), state.queue_position(&ids[2])), (Some(1), Some(2)));
+        started(&ids[1]).await;
+        assert_eq!(state.queue_position(&ids[2]), Some(1));
+        started(&ids[2]).await;
+    }
+    
+    #[actix_web::test]
+    async fn run_uses_the_requested_networks_endpoints() {
+        let default_faucet = MockServer::start(vec![(200, json!({ "txid": TXID }).to_string())]).await;
+        let signet_faucet = MockServer::start(vec![(200, json!({ "txid": TXID }).to_string())]).await;
+        let mut config = Config::from_env();
+        config.allow_placeholder_wallet = true;
+        config.enable_failure_injection = true;
+        config.connectivity_probe_url = default_faucet.url.clone();
+        config.btc_faucet_url = default_faucet.url.clone();
+        config.networks.insert(
+            "signet".to_string(),
+            config::NetworkEndpoints {
+                btc_faucet_url: format!("{}/signet-btc", signet_faucet.url),
+                lava_usd_faucet_url: format!("{}/signet-lava", signet_faucet.url),
+                explorer_url: signet_faucet.url.clone(),
+            },
+        );
+        
+        let request: RunTestRequest = serde_json::from_value(json!({ "network": "signet", "fail_at_step": "request_lava_usd" })).unwrap();
+        assert!(validate_run_request(&request, &config).is_ok());
+        let result = run_test_suite(&config, &test_pool(), &test_state(1, 5), &request, Uuid::new_v4().to_string()).await.unwrap();
+        let paths: Vec<String> = signet_faucet.requests().into_iter().map(|r| r.path).collect();
+        // The address snapshot taken before the BTC request goes to the network's explorer too
+        assert_eq!(paths, [format!("/api/address/{}/txs", result.btc_address), "/signet-btc".to_string()]);
+        // The default network's faucet only saw the connectivity probe
+        assert!(default_faucet.requests().iter().all(|r| r.method == "HEAD"));
+        
+        let unknown: RunTestRequest = serde_json::from_value(json!({ "network": "regtest" })).unwrap();
+        assert!(validate_run_request(&unknown, &config).unwrap_err().contains("mutinynet, signet"));
+    }
+    
+    #[actix_web::test]
+    async fn faucet_rate_limit_headers_are_recorded_and_shown_in_stats() {
+        let faucet = MockServer::start_with_headers(
+            vec![(200, json!({ "txid": TXID }).to_string())],
+            &[("X-RateLimit-Limit", "10"), ("X-RateLimit-Remaining", "3"), ("X-RateLimit-Reset", "1800")],
+        )
+        .await;
+        let mut config = Config::from_env();
+        config.allow_placeholder_wallet = true;
+        config.enable_failure_injection = true;
+        config.connectivity_probe_url = faucet.url.clone();
+        config.btc_faucet_url = faucet.url.clone();
+        
+        let db_pool = test_pool();
+        let state = web::Data::new(test_state(1, 5));
+        let request: RunTestRequest = serde_json::from_value(json!({ "fail_at_step": "request_lava_usd" })).unwrap();
+        let result = run_test_suite(&config, &db_pool, &state, &request, Uuid::new_v4().to_string()).await.unwrap();
+        let recorded = &result.details.as_ref().unwrap()["btc_faucet_rate_limit"];
+        assert_eq!(recorded["limit"], 10);
+        assert_eq!(recorded["remaining"], 3);
+        assert_eq!(recorded["reset"], 1800);
+        
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(web::Data::new(db_pool))
+                .app_data(web::Data::new(Config::from_env()))
+                .app_data(state.clone())
+                .route("/stats", web::get().to(get_stats_handler)),
+        )
+        .await;
+        let stats: Value = actix_web::test::call_and_read_body_json(&app, actix_web::test::TestRequest::get().uri("/stats").to_request()).await;
+        assert_eq!(stats["faucet_rate_limits"]["btc"]["remaining"], 3);
+        assert!(stats["faucet_rate_limits"].get("lava_usd").is_none());
+    }
+    
+    #[actix_web::test]
+    async fn prefund_sends_from_the_source_wallet_instead_of_the_faucets() {
+        let faucet = MockServer::start(vec![(200, json!({ "txid": TXID }).to_string())]).await;
+        let source_file = std::env::temp_dir().join(format!("prefund_{}", Uuid::new_v4()));
+        std::fs::write(&source_file, format!("{} about", "abandon ".repeat(11))).unwrap();
+        let mut config = Config::from_env();
+        config.allow_placeholder_wallet = true;
+        config.enable_failure_injection = true;
+        config.connectivity_probe_url = faucet.url.clone();
+        config.btc_faucet_url = faucet.url.clone();
+        config.lava_usd_faucet_url = faucet.url.clone();
+        config.prefund_mnemonic_file = Some(source_file.to_string_lossy().to_string());
+        
+        let request: RunTestRequest = serde_json::from_value(json!({ "fail_at_step": "setup_cli" })).unwrap();
+        let result = run_test_suite(&config, &test_pool(), &test_state(1, 5), &request, Uuid::new_v4().to_string()).await.unwrap();
+        std::fs::remove_file(&source_file).unwrap();
+        
+        let prefund = &result.details.as_ref().unwrap()["prefund"];
+        assert_eq!(prefund["source_address"], "tb1q6rz28mcfaxtmd6v789l9rrlrusdprr9pqcpvkl");
+        assert_eq!(prefund["txid"].as_str().unwrap().len(), 64);
+        assert_eq!(prefund["amount_sats"], config.btc_faucet_amount.to_sats());
+        assert!(result.faucet_accepted);
+        assert_eq!(result.funding_source.as_deref(), Some("prefund_wallet"));
+        let status = |name: &str| result.steps.iter().find(|s| s.name == name).unwrap().status.clone();
+        assert_eq!(status("prefund_wallet"), "success");
+        assert_eq!(status("request_btc"), "skipped");
+        assert_eq!(status("request_lava_usd"), "skipped");
+        // The faucet only saw the connectivity probe
+        assert!(faucet.requests().iter().all(|r| r.method == "HEAD"));
+        // No made-up txid is waited on, and leftovers go back to the source wallet
+        assert!(!result.funds_confirmed);
+        assert_eq!(result.details.as_ref().unwrap()["funding_confirmation"]["simulated"], true);
+        assert_eq!(result.details.as_ref().unwrap()["cleanup_return"]["return_address"], prefund["source_address"]);
+    }
+    
+    #[actix_web::test]
+    async fn metrics_break_runs_down_by_network_status_and_error_kind() {
+        let faucet = MockServer::start(vec![(200, json!({ "txid": TXID }).to_string())]).await;
+        let mut config = Config::from_env();
+        config.allow_placeholder_wallet = true;
+        config.enable_failure_injection = true;
+        config.connectivity_probe_url = faucet.url.clone();
+        config.btc_faucet_url = faucet.url.clone();
+        
+        let state = web::Data::new(test_state(1, 5));
+        let request: RunTestRequest = serde_json::from_value(json!({ "fail_at_step": "request_lava_usd" })).unwrap();
+        run_test_suite(&config, &test_pool(), &state, &request, Uuid::new_v4().to_string()).await.unwrap();
+        
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(state.clone())
+                .route("/metrics", web::get().to(metrics_handler)),
+        )
+        .await;
+        let response = actix_web::test::call_service(&app, actix_web::test::TestRequest::get().uri("/metrics").to_request()).await;
+        assert!(response.status().is_success());
+        let body = String::from_utf8(actix_web::test::read_body(response).await.to_vec()).unwrap();
+        
+        let labels = "network=\"mutinynet\",status=\"failed\",error_kind=\"lava_usd_faucet_failed\"";
+        assert!(body.contains(&format!("lava_test_runs_total{{{}}} 1", labels)), "{}", body);
+        assert!(body.contains(&format!("lava_test_run_duration_seconds_bucket{{{},le=\"+Inf\"}} 1", labels)), "{}", body);
+        assert!(body.contains(&format!("lava_test_run_duration_seconds_count{{{}}} 1", labels)), "{}", body);
+        // No per-run label such as the run ID
+        assert!(!body.contains("id=\""));
+    }
+    
+    #[actix_web::test]
+    async fn cached_result_is_served_until_the_run_is_saved_again() {
+        let config = web::Data::new(Config::from_env());
+        let db_pool = test_pool();
+        let state = web::Data::new(test_state(1, 5));
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(web::Data::new(db_pool.clone()))
+                .app_data(config.clone())
+                .app_data(state.clone())
+                .route("/results/{id}", web::get().to(get_result_by_id_handler)),
+        )
+        .await;
+        let status = || async {
+            let result: Value = actix_web::test::call_and_read_body_json(
+                &app,
+                actix_web::test::TestRequest::get().uri("/results/watched").to_request(),
+            )
+            .await;
+            result["status"].clone()
+        };
+        
+        let mut result = TestResult::new("watched".to_string(), &Mnemonic::default(), "tb1q", "pk");
+        result.status = "failed".to_string();
+        save_test_result(&db_pool, &result).unwrap();
+        assert_eq!(status().await, "failed");
+        
+        // A change behind the cache's back isn't seen, so the read didn't touch the DB
+        db_pool.get().unwrap().execute("UPDATE test_results SET status = 'edited' WHERE id = 'watched'", []).unwrap();
+        assert_eq!(status().await, "failed");
+        
+        result.status = "success".to_string();
+        finish_run(&config, &db_pool, &state, Ok(result), RunTestRequest::default(), None).await;
+        assert_eq!(status().await, "success");
+    }
+    
+    #[actix_web::test]
+    async fn sustained_low_success_rate_alerts_once() {
+        let webhook = MockServer::start(vec![(200, "{}".to_string())]).await;
+        let mut config = Config::from_env();
+        config.alert_webhook_url = Some(webhook.url.clone());
+        config.success_rate_alert_window = 4;
+        config.success_rate_alert_sustain_checks = 2;
+        let db_pool = test_pool();
+        let state = test_state(1, 5);
+        let save_run = |id: &str, status: &str| {
+            let mut result = TestResult::new(id.to_string(), &Mnemonic::default(), "tb1q", "pk");
+            result.status = status.to_string();
+            save_test_result(&db_pool, &result).unwrap();
+        };
+        
+        // A full window is needed before the rate counts: 1 of 3 succeeded
+        for (i, status) in ["success", "failed", "failed"].iter().enumerate() {
+            save_run(&format!("run-{}", i), status);
+        }
+        check_success_rate(&config, &db_pool, &state, 0.5).await;
+        check_success_rate(&config, &db_pool, &state, 0.5).await;
+        assert!(webhook.requests().is_empty());
+        
+        // 1 of the last 4: the first low check only starts the count, the second alerts
+        save_run("run-3", "failed");
+        check_success_rate(&config, &db_pool, &state, 0.5).await;
+        assert!(webhook.requests().is_empty());
+        for i in 4..8 {
+            save_run(&format!("run-{}", i), "failed");
+            check_success_rate(&config, &db_pool, &state, 0.5).await;
+        }
+        let alerts = webhook.requests();
+        assert_eq!(alerts.len(), 1);
+        let alert: Value = serde_json::from_str(&alerts[0].body).unwrap();
+        assert_eq!(alert["event"], "success_rate_low");
+        assert_eq!(alert["runs"], 4);
+        let rolling = state.success_rate.snapshot().unwrap();
+        assert_eq!(rolling.success_rate, Some(0.0));
+        assert!(rolling.alerted);
+        
+        // Recovering re-arms the alert
+        for i in 8..12 {
+            save_run(&format!("run-{}", i), "success");
+        }
+        check_success_rate(&config, &db_pool, &state, 0.5).await;
+        assert!(!state.success_rate.snapshot().unwrap().alerted);
+        assert_eq!(webhook.requests().len(), 1);
+    }
+    
+    #[actix_web::test]
+    async fn validate_rejects_what_run_test_rejects_without_running() {
+        let faucet = MockServer::start(vec![(200, json!({ "txid": TXID }).to_string())]).await;
+        let mut config = Config::from_env();
+        config.connectivity_probe_url = faucet.url.clone();
+        config.btc_faucet_url = faucet.url.clone();
+        config.lava_usd_faucet_url = faucet.url.clone();
+        let db_pool = test_pool();
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(web::Data::new(db_pool.clone()))
+                .app_data(web::Data::new(config))
+                .app_data(web::Data::new(test_state(1, 5)))
+                .route("/run-test", web::post().to(run_test_handler))
+                .route("/run-test/validate", web::post().to(validate_run_test_handler)),
+        )
+        .await;
+        let post = |uri: &'static str, body: Value| actix_web::test::TestRequest::post().uri(uri).set_json(body).to_request();
+        
+        for invalid in [json!({ "wallets": 0 }), json!({ "preset": "nope" }), json!({ "timeouts": { "faucet_request_ms": 1 } }), json!({ "wallets": "two" })] {
+            let real = actix_web::test::call_service(&app, post("/run-test", invalid.clone())).await;
+            let dry = actix_web::test::call_service(&app, post("/run-test/validate", invalid.clone())).await;
+            assert_eq!(real.status(), StatusCode::BAD_REQUEST, "{}", invalid);
+            assert_eq!(dry.status(), real.status(), "{}", invalid);
+            assert_eq!(actix_web::test::read_body(dry).await, actix_web::test::read_body(real).await, "{}", invalid);
+        }
+        
+        let response = actix_web::test::call_service(&app, post("/run-test/validate", json!({ "wallets": 2, "return_funds": false }))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value = actix_web::test::read_body_json(response).await;
+        assert_eq!(body["request"]["preset"], "default");
+        assert_eq!(body["request"]["network"], "mutinynet");
+        assert_eq!(body["request"]["account_index"], 0);
+        assert_eq!(body["effective"]["funding"], "faucet");
+        assert!(body["warnings"].as_array().unwrap().iter().any(|w| w.as_str().unwrap().contains("return_funds is false")));
+        
+        // Nothing was run or stored
+        assert!(faucet.requests().is_empty());
+        assert!(get_test_results(&db_pool, &ResultFilter::default()).unwrap().is_empty());
+    }
+    
+    #[actix_web::test]
+    async fn failed_canary_fires_the_alert() {
+        let webhook = MockServer::start(vec![(200, "{}".to_string())]).await;
+        let mut config = Config::from_env();
+        config.alert_webhook_url = Some(webhook.url.clone());
+        let db_pool = test_pool();
+        let state = test_state(1, 5);
+        let canary: RunTestRequest = serde_json::from_value(json!({ "canary": true })).unwrap();
+        
+        let mut result = TestResult::new("canary-run".to_string(), &Mnemonic::default(), "tb1q", "pk");
+        result.status = "failed".to_string();
+        result.error_message = Some("Failed to request BTC: faucet down".to_string());
+        finish_run(&config, &db_pool, &state, Ok(result), canary.clone(), None).await;
+        
+        let alerts = webhook.requests();
+        assert_eq!(alerts.len(), 1);
+        let alert: Value = serde_json::from_str(&alerts[0].body).unwrap();
+        assert_eq!(alert["event"], "canary_failed");
+        assert_eq!(alert["priority"], "high");
+        assert_eq!(alert["id"], "canary-run");
+        
+        // Passing canaries and failed non-canary runs don't alert
+        let mut passed = TestResult::new("canary-ok".to_string(), &Mnemonic::default(), "tb1q", "pk");
+        passed.status = "success".to_string();
+        finish_run(&config, &db_pool, &state, Ok(passed), canary, None).await;
+        let mut functional = TestResult::new("functional".to_string(), &Mnemonic::default(), "tb1q", "pk");
+        functional.status = "failed".to_string();
+        finish_run(&config, &db_pool, &state, Ok(functional), RunTestRequest::default(), None).await;
+        assert_eq!(webhook.requests().len(), 1);
+        
+        let canaries = get_canary_results(&db_pool, 50, 0).unwrap();
+        let mut ids: Vec<&str> = canaries.iter().map(|r| r.id.as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, ["canary-ok", "canary-run"]);
+        let stored = get_test_result_by_id(&db_pool, "canary-run").unwrap().unwrap();
+        assert_eq!(stored.details.unwrap()["canary_alert"]["sent"], true);
+        
+        // A webhook that accepts the connection but never answers can't keep the run from being saved
+        let hung = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        config.alert_webhook_url = Some(format!("http://{}", hung.local_addr().unwrap()));
+        config.alert_webhook_timeout_ms = 100;
+        let mut result = TestResult::new("canary-hung".to_string(), &Mnemonic::default(), "tb1q", "pk");
+        result.status = "failed".to_string();
+        let finished = tokio::time::timeout(
+            Duration::from_secs(5),
+            finish_run(&config, &db_pool, &state, Ok(result), RunTestRequest { canary: Some(true), ..Default::default() }, None),
+        )
+        .await;
+        assert!(finished.is_ok());
+        let stored = get_test_result_by_id(&db_pool, "canary-hung").unwrap().unwrap();
+        assert_eq!(stored.details.unwrap()["canary_alert"]["sent"], false);
+    }
+    
+    #[actix_web::test]
+    async fn stored_funding_confirmation_skips_the_wait_on_rerun() {
+        let explorer = MockServer::start(vec![(200, json!({ "confirmed": true }).to_string())]).await;
+        let mut config = Config::from_env();
+        config.explorer_url = explorer.url.clone();
+        config.confirmation_poll_interval_ms = 100;
+        
+        let db_pool = test_pool();
+        let mut first = TestResult::new("run".to_string(), &Mnemonic::default(), "tb1q", "pk");
+        confirm_funding(&config, &db_pool, &mut first, TXID).await;
+        assert!(first.funds_confirmed);
+        assert_eq!(get_funding_confirmation(&db_pool, "run").unwrap().unwrap().txid, TXID);
+        
+        // The rerun would poll an explorer that never confirms, so only the stored state can finish it
+        let stalled = MockServer::start(vec![(200, json!({ "confirmed": false }).to_string())]).await;
+        config.explorer_url = stalled.url.clone();
+        config.confirmation_timeout_ms = 60_000;
+        let mut rerun = TestResult::new("run".to_string(), &Mnemonic::default(), "tb1q", "pk");
+        let started = Instant::now();
+        confirm_funding(&config, &db_pool, &mut rerun, TXID).await;
+        
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(rerun.funds_confirmed);
+        assert_eq!(rerun.details.unwrap()["funding_confirmation"]["stored"]["txid"], TXID);
+        assert!(stalled.requests().is_empty());
+    }
+    
+    #[test]
+    fn duplicate_contract_id_is_flagged_without_failing() {
+        let db_pool = test_pool();
+        let mut earlier = TestResult::new("earlier".to_string(), &Mnemonic::default(), "tb1q", "pk");
+        earlier.loan_contract_id = Some("contract-1".to_string());
+        save_test_result(&db_pool, &earlier).unwrap();
+        
+        let mut result = TestResult::new("later".to_string(), &Mnemonic::default(), "tb1q", "pk");
+        check_contract_id_collision(&db_pool, &mut result, "contract-2");
+        assert!(!result.contract_id_collision);
+        
+        check_contract_id_collision(&db_pool, &mut result, "contract-1");
+        assert!(result.contract_id_collision);
+        assert_eq!(result.details.as_ref().unwrap()["contract_id_collision"]["existing_result_id"], "earlier");
+        assert!(result.error_message.is_none());
+        
+        // A result doesn't collide with its own stored row
+        check_contract_id_collision(&db_pool, &mut earlier, "contract-1");
+        assert!(!earlier.contract_id_collision);
+    }
+    
+    #[actix_web::test]
+    async fn post_run_hook_gets_the_redacted_result_on_stdin() {
+        use std::os::unix::fs::PermissionsExt;
+        
+        let dir = std::env::temp_dir().join(format!("hook_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let received = dir.join("received.json");
+        let hook = dir.join("hook.sh");
+        std::fs::write(&hook, format!("#!/bin/sh\ncat > '{}'\nexit 3\n", received.display())).unwrap();
+        std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755)).unwrap();
+        
+        let mut result = TestResult::new("hooked".to_string(), &Mnemonic::new("top secret words".to_string()), "tb1q", "pk");
+        result.record_detail("wallets", json!([{ "id": "hooked-w1", "mnemonic": "nested secret words" }]));
+        run_post_run_hook(hook.to_str().unwrap(), 5000, &mut result).await;
+        
+        let payload = std::fs::read_to_string(&received).unwrap();
+        let sent: Value = serde_json::from_str(&payload).unwrap();
+        assert_eq!(sent["id"], "hooked");
+        assert_eq!(sent["mnemonic"], "[REDACTED]");
+        assert_eq!(sent["details"]["wallets"][0]["mnemonic"], "[REDACTED]");
+        assert!(!payload.contains("secret words"));
+        // A failing hook is recorded, not fatal
+        assert_eq!(result.details.as_ref().unwrap()["post_run_hook"]["exit_code"], 3);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    #[actix_web::test]
+    async fn faucet_retry_after_a_lost_response_is_not_repeated() {
+        let probe = MockServer::start(vec![(200, "{}".to_string())]).await;
+        // The first request reaches the faucet but its response never arrives
+        let faucet = MockServer::start(vec![(0, String::new()), (200, json!({ "txid": "b".repeat(64) }).to_string())]).await;
+        let mut config = Config::from_env();
+        config.allow_placeholder_wallet = true;
+        config.enable_failure_injection = true;
+        config.connectivity_probe_url = probe.url.clone();
+        config.btc_faucet_url = faucet.url.clone();
+        config.retries.btc_faucet.backoff_ms = 10;
+        let (_, address, _) = generate_wallet(&config, 0).unwrap();
+        let sent_to_wallet = |txid: &str, block_time: Option<i64>| {
+            let status = match block_time {
+                Some(time) => json!({ "confirmed": true, "block_time": time }),
+                None => json!({ "confirmed": false }),
+            };
+            json!({ "txid": txid, "status": status, "vout": [{ "scriptpubkey_address": address }] })
+        };
+        // Empty before the first attempt
+        let explorer = MockServer::start(vec![(200, "[]".to_string()), (200, json!([sent_to_wallet(TXID, None)]).to_string())]).await;
+        config.explorer_url = explorer.url.clone();
+        
+        let db_pool = test_pool();
+        let request: RunTestRequest = serde_json::from_value(json!({ "fail_at_step": "request_lava_usd" })).unwrap();
+        let result = run_test_suite(&config, &db_pool, &test_state(1, 5), &request, Uuid::new_v4().to_string()).await.unwrap();
+        
+        assert_eq!(faucet.requests().len(), 1);
+        assert!(result.btc_faucet_response.deduplicated);
+        assert_eq!(result.funding_source.as_deref(), Some("reused"));
+        assert_eq!(result.btc_faucet_response.txid.as_deref(), Some(TXID));
+        assert_eq!(result.details.as_ref().unwrap()["btc_faucet_dedup"]["attempts"], 2);
+        assert!(faucet.requests()[0].headers.iter().any(|(name, _)| name == "idempotency-key"));
+        save_test_result(&db_pool, &result).unwrap();
+        
+        // A txid an earlier run recorded, or one confirmed before this run started, isn't
+        // the lost attempt's, so the retry goes back to the faucet
+        let faucet = MockServer::start(vec![(0, String::new()), (200, json!({ "txid": "c".repeat(64) }).to_string())]).await;
+        let explorer = MockServer::start(vec![
+            (200, "[]".to_string()),
+            (200, json!([sent_to_wallet(TXID, None), sent_to_wallet(&"d".repeat(64), Some(1_600_000_000))]).to_string()),
+        ])
+        .await;
+        config.btc_faucet_url = faucet.url.clone();
+        config.explorer_url = explorer.url.clone();
+        let result = run_test_suite(&config, &db_pool, &test_state(1, 5), &request, Uuid::new_v4().to_string()).await.unwrap();
+        
+        assert_eq!(faucet.requests().len(), 2);
+        assert!(!result.btc_faucet_response.deduplicated);
+        assert_eq!(result.funding_source.as_deref(), Some("faucet"));
+        assert_eq!(result.btc_faucet_response.txid, Some("c".repeat(64)));
+        save_test_result(&db_pool, &result).unwrap();
+        let stats = get_run_stats(&db_pool).unwrap();
+        assert_eq!(stats.by_funding_source["reused"].runs, 1);
+        assert_eq!(stats.by_funding_source["faucet"].runs, 1);
+    }
+    
+    #[actix_web::test]
+    async fn faucet_retry_ignores_what_a_reused_wallet_already_held() {
+        let dir = std::env::temp_dir().join(format!("reused_wallet_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mnemonic_file = dir.join("mnemonic");
+        std::fs::write(&mnemonic_file, "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about\n").unwrap();
+        let probe = MockServer::start(vec![(200, "{}".to_string())]).await;
+        let faucet = MockServer::start(vec![(0, String::new()), (200, json!({ "txid": "c".repeat(64) }).to_string())]).await;
+        let mut config = Config::from_env();
+        config.mnemonic_file = Some(mnemonic_file.to_string_lossy().to_string());
+        config.enable_failure_injection = true;
+        config.connectivity_probe_url = probe.url.clone();
+        config.btc_faucet_url = faucet.url.clone();
+        config.retries.btc_faucet.backoff_ms = 10;
+        let (_, address, _) = generate_wallet(&config, 0).unwrap();
+        // Still unconfirmed from the wallet's last use and recorded by no run here, so only
+        // the snapshot tells it apart from the lost attempt's transaction
+        let held = json!({ "txid": "e".repeat(64), "status": { "confirmed": false }, "vout": [{ "scriptpubkey_address": address }] });
+        let explorer = MockServer::start(vec![(200, json!([held]).to_string())]).await;
+        config.explorer_url = explorer.url.clone();
+        
+        let request: RunTestRequest = serde_json::from_value(json!({ "fail_at_step": "request_lava_usd" })).unwrap();
+        let result = run_test_suite(&config, &test_pool(), &test_state(1, 5), &request, Uuid::new_v4().to_string()).await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        
+        assert_eq!(explorer.requests().len(), 2);
+        assert_eq!(faucet.requests().len(), 2);
+        assert!(!result.btc_faucet_response.deduplicated);
+        assert_eq!(result.funding_source.as_deref(), Some("faucet"));
+        assert_eq!(result.btc_faucet_response.txid, Some("c".repeat(64)));
+    }
+    
+    #[test]
+    fn structured_faucet_error_sets_error_code() {
+        let policy = RetryPolicy { max_attempts: 3, backoff_ms: 0, retry_on_error: true, retry_on_server_error: true };
+        
+        let body = json!({ "error": { "code": "RATE_LIMIT", "message": "slow down" } }).to_string();
+        let response = parse_faucet_response(reqwest::StatusCode::BAD_REQUEST, &body, &txid_paths());
+        assert_eq!(response.error_code.as_deref(), Some(FAUCET_RATE_LIMIT));
+        assert_eq!(response.error.as_deref(), Some("slow down"));
+        assert!(response.txid.is_none());
+        assert!(is_retryable_faucet(&policy, &Ok(response)));
+        
+        let body = json!({ "error": { "code": "INSUFFICIENT_FUNDS" } }).to_string();
+        let response = parse_faucet_response(reqwest::StatusCode::BAD_REQUEST, &body, &txid_paths());
+        assert_eq!(response.error_code.as_deref(), Some(FAUCET_INSUFFICIENT_FUNDS));
+        assert_eq!(response.error.as_deref(), Some(body.as_str()));
+        assert!(is_faucet_exhausted(&response, &[]));
+        assert!(!is_retryable_faucet(&policy, &Ok(response)));
+    }
+    
+    #[test]
+    fn confirmation_timeout_only_extends_the_default_with_enough_history() {
+        assert_eq!(median_ms(&[]), None);
+        assert_eq!(median_ms(&[30, 10, 20]), Some(20));
+        assert_eq!(median_ms(&[40, 10, 30, 20]), Some(25));
+        
+        let mut config = Config::from_env();
+        config.adaptive_confirmation_timeout = true;
+        config.confirmation_timeout_ms = 120_000;
+        config.confirmation_max_timeout_ms = 600_000;
+        config.confirmation_timeout_multiplier = 3.0;
+        config.confirmation_min_samples = 3;
+        let default = Duration::from_millis(120_000);
+        
+        // Too little history to adapt from
+        assert_eq!(confirmation_timeout(&config, &[100_000, 100_000]), default);
+        // Fast confirmations never shorten the default
+        assert_eq!(confirmation_timeout(&config, &[1_000, 2_000, 3_000]), default);
+        // Slow ones extend it, up to the maximum
+        assert_eq!(confirmation_timeout(&config, &[50_000, 60_000, 70_000]), Duration::from_millis(180_000));
+        assert_eq!(confirmation_timeout(&config, &[300_000, 300_000, 300_000]), Duration::from_millis(600_000));
+        
+        config.adaptive_confirmation_timeout = false;
+        assert_eq!(confirmation_timeout(&config, &[50_000, 60_000, 70_000]), default);
+    }
+    
+    #[actix_web::test]
+    async fn faucet_server_error_fails_the_step_once_retries_run_out() {
+        let probe = MockServer::start(vec![(200, "{}".to_string())]).await;
+        let faucet = MockServer::start(vec![(503, json!({ "error": "upstream down" }).to_string())]).await;
+        let mut config = Config::from_env();
+        config.allow_placeholder_wallet = true;
+        config.connectivity_probe_url = probe.url.clone();
+        config.explorer_url = probe.url.clone();
+        config.btc_faucet_url = faucet.url.clone();
+        config.retries.btc_faucet = RetryPolicy { max_attempts: 2, backoff_ms: 0, retry_on_error: true, retry_on_server_error: true };
+        // Returning funds could send twice on an ambiguous error, so it doesn't retry by default
+        assert_eq!(config.retries.return_funds.max_attempts, 1);
+        
+        let request = RunTestRequest::default();
+        let result = run_test_suite(&config, &test_pool(), &test_state(1, 5), &request, Uuid::new_v4().to_string()).await.unwrap();
+        
+        assert_eq!(faucet.requests().len(), 2);
+        let step = result.steps.iter().find(|s| s.name == "request_btc").unwrap();
+        assert_eq!((step.status.as_str(), step.attempts), ("failed", 2));
+        assert!(step.error.as_deref().unwrap().contains("HTTP 503"), "{:?}", step.error);
+        assert_eq!(result.details.as_ref().unwrap()["error_kind"], "btc_faucet_failed");
+        assert!(!result.faucet_accepted);
+    }
+    
+    #[test]
+    fn per_run_timeouts_override_the_global_defaults() {
+        let mut config = Config::from_env();
+        config.confirmation_timeout_ms = 120_000;
+        config.confirmation_max_timeout_ms = 600_000;
+        config.confirmation_timeout_multiplier = 3.0;
+        config.faucet_request_timeout_ms = 30_000;
+        let history = [10_000, 20_000, 30_000];
+        assert_eq!(confirmation_timeout(&config, &history), Duration::from_millis(120_000));
+        
+        let timeouts = RunTimeouts { confirmation_ms: Some(300_000), faucet_request_ms: None };
+        let request = RunTestRequest { timeouts: Some(timeouts.clone()), ..Default::default() };
+        assert!(validate_run_request(&request, &config).is_ok());
+        
+        let mut run_config = config.clone();
+        apply_run_timeouts(&mut run_config, &timeouts);
+        assert_eq!(confirmation_timeout(&run_config, &history), Duration::from_millis(300_000));
+        assert_eq!(run_config.faucet_request_timeout_ms, 30_000);
+        
+        let too_long = RunTestRequest {
+            timeouts: Some(RunTimeouts { confirmation_ms: Some(700_000), faucet_request_ms: None }),
+            ..Default::default()
+        };
+        assert!(validate_run_request(&too_long, &config).unwrap_err().contains("confirmation_ms"));
+        let too_short = RunTestRequest {
+            timeouts: Some(RunTimeouts { confirmation_ms: None, faucet_request_ms: Some(10) }),
+            ..Default::default()
+        };
+        assert!(validate_run_request(&too_short, &config).unwrap_err().contains("faucet_request_ms"));
+    }
+    
+    #[test]
+    fn results_range_is_parsed_and_checked() {
+        assert_eq!(parse_range_bound("2024-03-01T12:00:00+02:00", false).unwrap(), "2024-03-01T10:00:00+00:00");
+        assert_eq!(parse_range_bound("2024-03-01", false).unwrap(), "2024-03-01T00:00:00+00:00");
+        assert_eq!(parse_range_bound("2024-03-01", true).unwrap(), "2024-03-01T23:59:59.999999999+00:00");
+        assert!(parse_range_bound("yesterday", false).is_err());
+        assert!(parse_range_bound("2024-13-01", false).is_err());
+        
+        let query = |from: &str, to: &str| -> ResultsQuery {
+            serde_json::from_value(json!({ "from": from, "to": to })).unwrap()
+        };
+        assert!(results_filter(&query("2024-03-01", "2024-03-01"), 1000).is_ok());
+        assert!(results_filter(&query("2024-03-02", "2024-03-01"), 1000).is_err());
+    }
+    
+    #[actix_web::test]
+    async fn lava_usd_wait_follows_the_solana_signature_status() {
+        let status = |value: Value| (200, json!({ "jsonrpc": "2.0", "id": 1, "result": { "value": [value] } }).to_string());
+        let mut config = Config::from_env();
+        config.confirmation_poll_interval_ms = 10;
+        config.lava_usd_confirmation_timeout_ms = 2000;
+        
+        let rpc = MockServer::start(vec![
+            status(Value::Null),
+            status(json!({ "confirmationStatus": "confirmed", "err": null })),
+            status(json!({ "confirmationStatus": "finalized", "err": null })),
+        ])
+        .await;
+        config.solana_rpc_url = rpc.url.clone();
+        assert!(matches!(wait_for_lava_usd_confirmation(&config, "sig").await, SolanaConfirmation::Finalized));
+        assert_eq!(rpc.requests().len(), 3);
+        assert!(rpc.requests()[0].body.contains("getSignatureStatuses"));
+        
+        let rpc = MockServer::start(vec![status(json!({ "confirmationStatus": "processed", "err": { "InstructionError": [0, "Custom"] } }))]).await;
+        config.solana_rpc_url = rpc.url.clone();
+        assert!(matches!(wait_for_lava_usd_confirmation(&config, "sig").await, SolanaConfirmation::Failed(_)));
+        
+        let rpc = MockServer::start(vec![status(json!({ "confirmationStatus": "confirmed", "err": null }))]).await;
+        config.solana_rpc_url = rpc.url.clone();
+        config.lava_usd_confirmation_timeout_ms = 100;
+        match wait_for_lava_usd_confirmation(&config, "sig").await {
+            SolanaConfirmation::TimedOut(last_status) => assert_eq!(last_status.as_deref(), Some("confirmed")),
+            _ => panic!("expected the wait to time out"),
+        }
+    }
+    
+    #[actix_web::test]
+    async fn redaction_rules_mask_fields_in_read_responses() {
+        let db_pool = test_pool();
+        let mut result = TestResult::new("private".to_string(), &Mnemonic::new("secret words".to_string()), "tb1qprivateaddress", "pk");
+        result.btc_faucet_response.txid = Some(TXID.to_string());
+        save_test_result(&db_pool, &result).unwrap();
+        
+        let read = |config: Config| {
+            let db_pool = db_pool.clone();
+            async move {
+                let app = actix_web::test::init_service(
+                    App::new()
+                        .app_data(web::Data::new(db_pool))
+                        .app_data(web::Data::new(config))
+                        .app_data(web::Data::new(test_state(1, 5)))
+                        .route("/results/{id}", web::get().to(get_result_by_id_handler)),
+                )
+                .await;
+                let body: Value = actix_web::test::call_and_read_body_json(
+                    &app,
+                    actix_web::test::TestRequest::get().uri("/results/private").to_request(),
+                )
+                .await;
+                body
+            }
+        };
+        
+        // By default only the mnemonic is masked
+        let default = read(Config::from_env()).await;
+        assert_eq!(default["mnemonic"], "[REDACTED]");
+        assert_eq!(default["btc_address"], "tb1qprivateaddress");
+        
+        let mut config = Config::from_env();
+        config.redaction_rules = ["mnemonic", "btc_address", "btc_faucet_response.txid:partial"]
+            .iter()
+            .map(|rule| rule.parse().unwrap())
+            .collect();
+        let custom = read(config).await;
+        assert_eq!(custom["mnemonic"], "[REDACTED]");
+        assert_eq!(custom["btc_address"], "[REDACTED]");
+        assert_eq!(custom["btc_faucet_response"]["txid"], format!("{}...{}", &TXID[..4], &TXID[TXID.len() - 4..]));
+        assert_eq!(custom["lava_usd_pubkey"], "pk");
+    }
+    
+    #[actix_web::test]
+    async fn results_past_the_export_cap_are_paged() {
+        let db_pool = test_pool();
+        for _ in 0..3 {
+            let result = TestResult::new(Uuid::new_v4().to_string(), &Mnemonic::default(), "tb1qexport", "pk");
+            save_test_result(&db_pool, &result).unwrap();
+        }
+        let mut config = Config::from_env();
+        config.max_export_rows = 2;
+        
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(web::Data::new(db_pool))
+                .app_data(web::Data::new(config))
+                .route("/results", web::get().to(get_results_handler)),
+        )
+        .await;
+        
+        let request = actix_web::test::TestRequest::get().uri("/results?limit=50").to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert_eq!(response.headers().get("X-Results-Truncated").unwrap(), "true");
+        assert_eq!(response.headers().get("X-Next-Offset").unwrap(), "2");
+        let page: Vec<Value> = actix_web::test::read_body_json(response).await;
+        assert_eq!(page.len(), 2);
+        
+        let request = actix_web::test::TestRequest::get().uri("/results?offset=2").to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert!(response.headers().get("X-Results-Truncated").is_none());
+        let page: Vec<Value> = actix_web::test::read_body_json(response).await;
+        assert_eq!(page.len(), 1);
+    }
+    
+    #[actix_web::test]
+    async fn stored_result_is_reevaluated_under_changed_criteria() {
+        let db_pool = test_pool();
+        let mut stored = TestResult::new("judged".to_string(), &Mnemonic::default(), "tb1q", "pk");
+        apply_contract_details(&mut stored, json!({
+            "Closed": { "timestamp": "2024-03-01T12:00:00+00:00" },
+            "outcome": { "repayment": { "collateral_repayment_txid": TXID } }
+        }));
+        stored.status = "success".to_string();
+        save_test_result(&db_pool, &stored).unwrap();
+        
+        // A stricter idea of "closed" that the stored details don't meet
+        let mut config = Config::from_env();
+        config.closed_loan_required_paths.push("outcome.repayment.settled_at".to_string());
+        config.api_key = Some("test-key".to_string());
+        let (status, reasons) = evaluate_result(stored.details.as_ref(), &config.closed_loan_required_paths);
+        assert_eq!((status.as_str(), reasons.as_slice()), ("failed", ["details.outcome.repayment.settled_at is missing".to_string()].as_slice()));
+        
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(web::Data::new(db_pool.clone()))
+                .app_data(web::Data::new(config))
+                .app_data(web::Data::new(test_state(1, 5)))
+                .route("/results/{id}/reevaluate", web::post().to(reevaluate_result_handler)),
+        )
+        .await;
+        let reevaluated: Value = actix_web::test::call_and_read_body_json(
+            &app,
+            actix_web::test::TestRequest::post().uri("/results/judged/reevaluate").insert_header(("X-API-Key", "test-key")).to_request(),
+        )
+        .await;
+        
+        assert_eq!(reevaluated["status"], "failed");
+        assert_eq!(reevaluated["mnemonic"], "[REDACTED]");
+        assert_eq!(reevaluated["details"]["error_kind"], "loan_not_closed");
+        assert_eq!(reevaluated["details"]["reevaluation"]["previous_status"], "success");
+        let saved = get_test_result_by_id(&db_pool, "judged").unwrap().unwrap();
+        assert_eq!(saved.status, "failed");
+        assert!(saved.error_message.unwrap().contains("settled_at"));
+        
+        // Runs that failed at a step aren't re-judged
+        let mut stopped = TestResult::new("stopped".to_string(), &Mnemonic::default(), "tb1q", "pk");
+        stopped.fail(TestError::BtcFaucetFailed, "Failed to request BTC".to_string());
+        save_test_result(&db_pool, &stopped).unwrap();
+        let response = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::post().uri("/results/stopped/reevaluate").insert_header(("X-API-Key", "test-key")).to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
 }
@@ -0,0 +1,339 @@
+use crate::TestResult;
+
+/// Standalone page for a single result; no external assets
+const REPORT_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Test run {{id}}</title>
+<style>
+body { font-family: -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; margin: 2rem auto; max-width: 960px; color: #1f2328; padding: 0 1rem; }
+h1 { font-size: 1.4rem; margin-bottom: 0.25rem; }
+h2 { font-size: 1.1rem; margin-top: 2rem; border-bottom: 1px solid #d0d7de; padding-bottom: 0.3rem; }
+.status { display: inline-block; padding: 0.15rem 0.6rem; border-radius: 1rem; font-weight: 600; color: #fff; }
+.status-success { background: #1a7f37; }
+.status-failed { background: #cf222e; }
+.status-other { background: #6e7781; }
+table { border-collapse: collapse; width: 100%; }
+th, td { text-align: left; padding: 0.35rem 0.6rem; border-bottom: 1px solid #eaeef2; vertical-align: top; }
+th { background: #f6f8fa; }
+code { font-family: ui-monospace, Menlo, Consolas, monospace; font-size: 0.85rem; word-break: break-all; }
+.muted { color: #6e7781; }
+.error { color: #cf222e; }
+</style>
+</head>
+<body>
+<h1>Test run <code>{{id}}</code></h1>
+<p><span class="status {{status_class}}">{{status}}</span></p>
+{{error}}
+<h2>Summary</h2>
+<table>
+{{summary}}
+</table>
+<h2>Steps</h2>
+<table>
+<tr><th>Name</th><th>Kind</th><th>Status</th><th>Attempts</th><th>Duration</th><th>Error</th></tr>
+{{steps}}
+</table>
+<h2>Wallet</h2>
+<table>
+{{wallet}}
+</table>
+<p class="muted">Generated {{generated_at}}</p>
+</body>
+</html>
+"#;
+
+/// Render a result as a self-contained HTML report. The mnemonic is never included.
+pub fn render_html(result: &TestResult, explorer_url: &str) -> String {
+    let status_class = match result.status.as_str() {
+        "success" => "status-success",
+        "failed" => "status-failed",
+        _ => "status-other",
+    };
+    let error = result
+        .error_message
+        .as_deref()
+        .map(|message| format!("<p class=\"error\">{}</p>", escape(message)))
+        .unwrap_or_default();
+    
+    let tx_link = |txid: &Option<String>| match txid {
+        Some(txid) => format!(
+            "<a href=\"{}/tx/{}\"><code>{}</code></a>",
+            escape(explorer_url.trim_end_matches('/')),
+            escape(txid),
+            escape(txid)
+        ),
+        None => "<span class=\"muted\">none</span>".to_string(),
+    };
+    let optional = |value: &Option<String>| match value {
+        Some(value) => format!("<code>{}</code>", escape(value)),
+        None => "<span class=\"muted\">none</span>".to_string(),
+    };
+    
+    let summary = [
+        ("Loan contract", optional(&result.loan_contract_id)),
+        ("Preset", optional(&result.preset)),
+        ("Loan closed", yes_no(result.loan_closed)),
+        ("BTC faucet txid", tx_link(&result.btc_faucet_response.txid)),
+        ("LavaUSD faucet txid", optional(&result.lava_usd_faucet_response.txid)),
+        ("Funds confirmed", yes_no(result.funds_confirmed)),
+        ("Repayment txid", tx_link(&result.repayment_txid)),
+        ("Return txid", tx_link(&result.return_txid)),
+        ("Funds returned", yes_no(result.returned_funds)),
+        ("Sats requested", result.sats_requested.to_string()),
+        ("Sats returned", result.sats_returned.map_or_else(|| "unknown".to_string(), |sats| sats.to_string())),
+        ("Net sats consumed", result.net_sats_consumed.map_or_else(|| "unknown".to_string(), |sats| sats.to_string())),
+    ]
+    .iter()
+    .map(|(label, value)| format!("<tr><th>{}</th><td>{}</td></tr>", label, value))
+    .collect::<Vec<_>>()
+    .join("\n");
+    
+    let steps = result
+        .steps
+        .iter()
+        .map(|step| {
+            format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{:.1}s</td><td>{}</td></tr>",
+                escape(&step.name),
+                escape(&step.kind),
+                escape(&step.status),
+                step.attempts,
+                step.duration_ms as f64 / 1000.0,
+                step.error.as_deref().map(escape).unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    
+    let wallet = [
+        ("BTC address", format!("<code>{}</code>", escape(&result.btc_address))),
+        ("LavaUSD pubkey", format!("<code>{}</code>", escape(&result.lava_usd_pubkey))),
+        ("Mnemonic", "<span class=\"muted\">[REDACTED]</span>".to_string()),
+    ]
+    .iter()
+    .map(|(label, value)| format!("<tr><th>{}</th><td>{}</td></tr>", label, value))
+    .collect::<Vec<_>>()
+    .join("\n");
+    
+    REPORT_TEMPLATE
+        .replace("{{id}}", &escape(&result.id))
+        .replace("{{status_class}}", status_class)
+        .replace("{{status}}", &escape(&result.status))
+        .replace("{{error}}", &error)
+        .replace("{{summary}}", &summary)
+        .replace("{{steps}}", &steps)
+        .replace("{{wallet}}", &wallet)
+        .replace("{{generated_at}}", &chrono::Utc::now().to_rfc3339())
+}
+
+/// Render results as a JUnit XML report: one testsuite per run with a testcase per
+/// suite step. Wait phases are left out. A run that failed without a failed step, e.g.
+/// on loan_not_closed, gets a failing `final_status` testcase so CI doesn't show it green.
+pub fn render_junit(results: &[TestResult]) -> String {
+    let suites: Vec<XmlElement> = results.iter().map(junit_testsuite).collect();
+    let count = |name: &str| {
+        suites
+            .iter()
+            .map(|suite| suite.attr_value(name).and_then(|v| v.parse::<usize>().ok()).unwrap_or(0))
+            .sum::<usize>()
+    };
+    
+    let root = XmlElement::new("testsuites")
+        .attr("name", "loan-lifecycle")
+        .attr("tests", count("tests"))
+        .attr("failures", count("failures"))
+        .attr("skipped", count("skipped"))
+        .children(suites);
+    
+    let mut xml = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n".to_string();
+    root.write(&mut xml, 0);
+    xml
+}
+
+fn junit_testsuite(result: &TestResult) -> XmlElement {
+    let mut cases: Vec<XmlElement> = Vec::new();
+    let mut failures = 0;
+    let mut skipped = 0;
+    let mut total_ms = 0;
+    for step in result.steps.iter().filter(|step| step.kind == "step") {
+        total_ms += step.duration_ms;
+        let mut case = XmlElement::new("testcase")
+            .attr("classname", "loan_lifecycle")
+            .attr("name", &step.name)
+            .attr("time", seconds(step.duration_ms));
+        match step.status.as_str() {
+            "failed" => {
+                failures += 1;
+                let message = step.error.as_deref().unwrap_or("step failed");
+                case = case.child(XmlElement::new("failure").attr("message", message).text(message));
+            }
+            "skipped" => {
+                skipped += 1;
+                case = case.child(XmlElement::new("skipped"));
+            }
+            _ => {}
+        }
+        cases.push(case);
+    }
+    
+    if result.status == "failed" && failures == 0 {
+        failures += 1;
+        let message = result.error_message.as_deref().unwrap_or("run failed");
+        cases.push(
+            XmlElement::new("testcase")
+                .attr("classname", "loan_lifecycle")
+                .attr("name", "final_status")
+                .attr("time", seconds(0))
+                .child(XmlElement::new("failure").attr("message", message).text(message)),
+        );
+    }
+    
+    XmlElement::new("testsuite")
+        .attr("name", format!("run {}", result.id))
+        .attr("tests", cases.len())
+        .attr("failures", failures)
+        .attr("skipped", skipped)
+        .attr("time", seconds(total_ms))
+        .children(cases)
+}
+
+fn seconds(ms: u64) -> String {
+    format!("{:.3}", ms as f64 / 1000.0)
+}
+
+/// Just enough of an XML element to build the JUnit report; attribute values and text
+/// are escaped on write
+struct XmlElement {
+    name: &'static str,
+    attrs: Vec<(&'static str, String)>,
+    children: Vec<XmlElement>,
+    text: Option<String>,
+}
+
+impl XmlElement {
+    fn new(name: &'static str) -> Self {
+        XmlElement {
+            name,
+            attrs: Vec::new(),
+            children: Vec::new(),
+            text: None,
+        }
+    }
+    
+    fn attr(mut self, name: &'static str, value: impl ToString) -> Self {
+        self.attrs.push((name, value.to_string()));
+        self
+    }
+    
+    fn attr_value(&self, name: &str) -> Option<&str> {
+        self.attrs.iter().find(|(key, _)| *key == name).map(|(_, value)| value.as_str())
+    }
+    
+    fn child(mut self, child: XmlElement) -> Self {
+        self.children.push(child);
+        self
+    }
+    
+    fn children(mut self, children: Vec<XmlElement>) -> Self {
+        self.children.extend(children);
+        self
+    }
+    
+    fn text(mut self, text: &str) -> Self {
+        self.text = Some(text.to_string());
+        self
+    }
+    
+    fn write(&self, out: &mut String, depth: usize) {
+        let indent = "  ".repeat(depth);
+        out.push_str(&indent);
+        out.push('<');
+        out.push_str(self.name);
+        for (name, value) in &self.attrs {
+            out.push_str(&format!(" {}=\"{}\"", name, escape(value)));
+        }
+        match (&self.text, self.children.is_empty()) {
+            (None, true) => out.push_str("/>\n"),
+            (Some(text), true) => out.push_str(&format!(">{}</{}>\n", escape(text), self.name)),
+            (text, false) => {
+                out.push_str(">\n");
+                if let Some(text) = text {
+                    out.push_str(&format!("{}  {}\n", indent, escape(text)));
+                }
+                for child in &self.children {
+                    child.write(out, depth + 1);
+                }
+                out.push_str(&format!("{}</{}>\n", indent, self.name));
+            }
+        }
+    }
+}
+
+fn yes_no(value: bool) -> String {
+    if value { "yes" } else { "no" }.to_string()
+}
+
+/// Escape text for HTML (and XML) element and attribute content
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret::Mnemonic;
+    
+    #[test]
+    fn report_shows_contract_and_status_but_not_the_mnemonic() {
+        let mnemonic = Mnemonic::new("secret words <b>".to_string());
+        let mut result = TestResult::new("run-1".to_string(), &mnemonic, "tb1qaddr", "pk");
+        result.status = "failed".to_string();
+        result.loan_contract_id = Some("contract-42".to_string());
+        result.repayment_txid = Some("abc123".to_string());
+        result.error_message = Some("repay <failed>".to_string());
+        
+        let html = render_html(&result, "https://mempool.space/testnet/");
+        assert!(html.contains("<code>contract-42</code>"));
+        assert!(html.contains("<span class=\"status status-failed\">failed</span>"));
+        assert!(html.contains("<a href=\"https://mempool.space/testnet/tx/abc123\">"));
+        assert!(html.contains("repay &lt;failed&gt;"));
+        assert!(!html.contains("secret words"));
+    }
+    
+    #[test]
+    fn junit_has_a_testcase_per_step_and_failures_with_their_message() {
+        let mut passed = TestResult::new("ok".to_string(), &Mnemonic::default(), "tb1q", "pk");
+        passed.status = "success".to_string();
+        passed.record_step("request_btc", std::time::Instant::now(), 1, None, None);
+        passed.record_wait("faucet_wait", std::time::Instant::now());
+        passed.record_skipped_step("request_lava_usd");
+        passed.record_step("create_loan", std::time::Instant::now(), 1, None, None);
+        
+        let mut failed = TestResult::new("bad".to_string(), &Mnemonic::default(), "tb1q", "pk");
+        failed.record_step("request_btc", std::time::Instant::now(), 3, Some("faucet said <503>".to_string()), None);
+        failed.status = "failed".to_string();
+        
+        let mut unclosed = TestResult::new("open".to_string(), &Mnemonic::default(), "tb1q", "pk");
+        unclosed.record_step("repay_loan", std::time::Instant::now(), 1, None, None);
+        unclosed.status = "failed".to_string();
+        unclosed.error_message = Some("Loan was not properly closed".to_string());
+        
+        let xml = render_junit(&[passed, failed, unclosed]);
+        assert!(xml.starts_with("<?xml"));
+        assert_eq!(xml.matches("<testsuite ").count(), 3);
+        // 3 steps without the wait, 1 failed step, 1 step plus final_status
+        assert_eq!(xml.matches("<testcase ").count(), 6);
+        assert_eq!(xml.matches("<failure ").count(), 2);
+        assert_eq!(xml.matches("<skipped/>").count(), 1);
+        assert!(xml.contains("<testsuites name=\"loan-lifecycle\" tests=\"6\" failures=\"2\" skipped=\"1\">"));
+        assert!(xml.contains("<failure message=\"faucet said &lt;503&gt;\">faucet said &lt;503&gt;</failure>"));
+        assert!(xml.contains("name=\"final_status\""));
+        assert!(!xml.contains("faucet_wait"));
+    }
+}
@@ -0,0 +1,112 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Url};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+use crate::config::Config;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Object key for a result: `<prefix>/<yyyy>/<mm>/<dd>/<id>.json`
+pub fn result_key(prefix: &str, id: &str) -> String {
+    let date = Utc::now().format("%Y/%m/%d");
+    let prefix = prefix.trim_matches('/');
+    if prefix.is_empty() {
+        format!("{}/{}.json", date, id)
+    } else {
+        format!("{}/{}/{}.json", prefix, date, id)
+    }
+}
+
+/// Upload a JSON document to the configured bucket with a SigV4-signed PUT
+pub async fn put_json(config: &Config, key: &str, body: Vec<u8>) -> Result<()> {
+    let bucket = config.s3_bucket.as_deref().ok_or_else(|| anyhow!("S3_BUCKET is not set"))?;
+    let access_key = config.s3_access_key_id.as_deref().ok_or_else(|| anyhow!("AWS_ACCESS_KEY_ID is not set"))?;
+    let secret_key = config.s3_secret_access_key.as_deref().ok_or_else(|| anyhow!("AWS_SECRET_ACCESS_KEY is not set"))?;
+    
+    let endpoint = match &config.s3_endpoint {
+        Some(endpoint) => endpoint.trim_end_matches('/').to_string(),
+        None => format!("https://s3.{}.amazonaws.com", config.s3_region),
+    };
+    // Path-style addressing so custom endpoints (MinIO, mocks) work the same way
+    let path = format!("/{}/{}", uri_encode(bucket), key.split('/').map(uri_encode).collect::<Vec<_>>().join("/"));
+    let url = Url::parse(&format!("{}{}", endpoint, path))?;
+    let host = match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        (None, _) => return Err(anyhow!("Invalid S3 endpoint: {}", endpoint)),
+    };
+    
+    let now = Utc::now();
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let payload_hash = hex::encode(Sha256::digest(&body));
+    
+    let mut headers = vec![
+        ("host", host),
+        ("x-amz-content-sha256", payload_hash.clone()),
+        ("x-amz-date", amz_date.clone()),
+    ];
+    if let Some(token) = &config.s3_session_token {
+        headers.push(("x-amz-security-token", token.clone()));
+    }
+    let canonical_headers: String = headers.iter().map(|(k, v)| format!("{}:{}\n", k, v.trim())).collect();
+    let signed_headers = headers.iter().map(|(k, _)| *k).collect::<Vec<_>>().join(";");
+    
+    let canonical_request = format!("PUT\n{}\n\n{}\n{}\n{}", path, canonical_headers, signed_headers, payload_hash);
+    let scope = format!("{}/{}/s3/aws4_request", date, config.s3_region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+    
+    let signing_key = [date.as_str(), config.s3_region.as_str(), "s3", "aws4_request"]
+        .iter()
+        .fold(format!("AWS4{}", secret_key).into_bytes(), |key, part| hmac(&key, part.as_bytes()));
+    let signature = hex::encode(hmac(&signing_key, string_to_sign.as_bytes()));
+    let authorization = format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        access_key, scope, signed_headers, signature
+    );
+    
+    let client = Client::builder()
+        .timeout(Duration::from_millis(config.s3_request_timeout_ms))
+        .build()?;
+    let mut request = client
+        .put(url)
+        .header("Authorization", authorization)
+        .header("Content-Type", "application/json");
+    for (name, value) in headers.into_iter().filter(|(name, _)| *name != "host") {
+        request = request.header(name, value);
+    }
+    
+    let response = request.body(body).send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        let text = response.text().await.unwrap_or_default();
+        return Err(anyhow!("S3 upload failed with status {}: {}", status, text));
+    }
+    
+    Ok(())
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Percent-encode everything except RFC 3986 unreserved characters
+fn uri_encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
@@ -0,0 +1,51 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use zeroize::Zeroizing;
+
+/// Wallet seed phrase. Wiped from memory on drop and redacted in Debug output.
+#[derive(Clone, Default)]
+pub struct Mnemonic(Zeroizing<String>);
+
+impl Mnemonic {
+    pub fn new(phrase: String) -> Self {
+        Mnemonic(Zeroizing::new(phrase))
+    }
+    
+    /// Borrow the phrase; keep the borrow short and never log it
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Mnemonic([REDACTED])")
+    }
+}
+
+// Results still carry the phrase over the API and in the database
+impl Serialize for Mnemonic {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.expose())
+    }
+}
+
+impl<'de> Deserialize<'de> for Mnemonic {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Mnemonic::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn debug_does_not_reveal_the_phrase() {
+        let mnemonic = Mnemonic::new("abandon ability able about above absent".to_string());
+        let debug = format!("{:?}", mnemonic);
+        assert_eq!(debug, "Mnemonic([REDACTED])");
+        assert!(!debug.contains("abandon"));
+        assert_eq!(mnemonic.expose(), "abandon ability able about above absent");
+    }
+}
@@ -0,0 +1,425 @@
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::atomic::AtomicBool;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+use crate::config::Config;
+use crate::live::LiveRuns;
+
+/// Runtime state shared between handlers
+pub struct AppState {
+    /// Output of runs in flight, as their CLI prints it
+    pub live_runs: LiveRuns,
+    /// Limits how many wallet flows run at once
+    pub run_slots: Semaphore,
+    /// Limits how many faucet requests are in flight at once, independent of run_slots
+    pub faucet_slots: Semaphore,
+    /// While set, /run-test refuses to start new runs
+    pub paused: AtomicBool,
+    /// Source of simulated txids and contract IDs, seeded from RNG_SEED when set
+    rng: Mutex<StdRng>,
+    /// Rolling record of whether recent faucet calls looked exhausted
+    pub faucet_health: FaucetHealth,
+    /// IDs of runs waiting for a slot, oldest first
+    run_queue: Mutex<VecDeque<String>>,
+    /// Recently read results, serialized, for /results/{id}
+    pub result_cache: ResultCache,
+    /// Finished-run counters and durations served by /metrics
+    pub run_metrics: RunMetrics,
+    /// Latest rolling success rate and whether it's being alerted on
+    pub success_rate: SuccessRateMonitor,
+}
+
+impl AppState {
+    pub fn new(config: &Config) -> Self {
+        let rng = match config.rng_seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        AppState {
+            live_runs: LiveRuns::default(),
+            run_slots: Semaphore::new(config.max_concurrent_runs),
+            faucet_slots: Semaphore::new(config.max_concurrent_faucet_calls),
+            paused: AtomicBool::new(false),
+            rng: Mutex::new(rng),
+            faucet_health: FaucetHealth::new(config.faucet_health_window),
+            run_queue: Mutex::new(VecDeque::new()),
+            result_cache: ResultCache::new(
+                config.result_cache_capacity,
+                Duration::from_millis(config.result_cache_ttl_ms),
+            ),
+            run_metrics: RunMetrics::default(),
+            success_rate: SuccessRateMonitor::default(),
+        }
+    }
+
+    /// Add a run to the back of the queue unless it already holds `max` runs.
+    /// Returns its 1-based position.
+    pub fn enqueue_run(&self, id: &str, max: usize) -> Option<usize> {
+        let mut queue = self.run_queue.lock().unwrap_or_else(|e| e.into_inner());
+        if queue.len() >= max {
+            return None;
+        }
+        queue.push_back(id.to_string());
+        Some(queue.len())
+    }
+
+    /// Take a run off the queue once it has a slot. False when it was no longer
+    /// queued, i.e. it was cancelled.
+    pub fn dequeue_run(&self, id: &str) -> bool {
+        let mut queue = self.run_queue.lock().unwrap_or_else(|e| e.into_inner());
+        let before = queue.len();
+        queue.retain(|queued| queued != id);
+        queue.len() < before
+    }
+    
+    /// Empty the queue, returning the IDs of the runs that were waiting
+    pub fn cancel_queued_runs(&self) -> Vec<String> {
+        self.run_queue
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .drain(..)
+            .collect()
+    }
+
+    /// 1-based position of a queued run, None once it has started
+    pub fn queue_position(&self, id: &str) -> Option<usize> {
+        self.run_queue
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .position(|queued| queued == id)
+            .map(|index| index + 1)
+    }
+
+    /// Fill a buffer from the shared RNG
+    pub fn random_bytes<const N: usize>(&self) -> [u8; N] {
+        let mut bytes = [0u8; N];
+        self.rng
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .fill_bytes(&mut bytes);
+        bytes
+    }
+
+    /// Random 32-byte hex txid
+    pub fn random_txid(&self) -> String {
+        hex::encode(self.random_bytes::<32>())
+    }
+
+    /// Random v4 UUID for simulated contract IDs
+    pub fn random_uuid(&self) -> String {
+        uuid::Builder::from_random_bytes(self.random_bytes::<16>())
+            .into_uuid()
+            .to_string()
+    }
+}
+
+/// Health of one faucet over its recent calls
+#[derive(Debug, Serialize)]
+pub struct FaucetStatus {
+    pub status: &'static str,
+    pub recent_calls: usize,
+    pub exhausted_calls: usize,
+}
+
+/// Quota a faucet reported in its `X-RateLimit-*` headers
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FaucetRateLimit {
+    pub limit: Option<u64>,
+    pub remaining: Option<u64>,
+    /// As sent by the faucet: seconds until the window resets or a unix timestamp
+    pub reset: Option<u64>,
+    pub observed_at: String,
+}
+
+/// Per-faucet window of recent calls, `true` where the faucet looked exhausted
+pub struct FaucetHealth {
+    window: usize,
+    calls: Mutex<BTreeMap<String, VecDeque<bool>>>,
+    /// Latest rate-limit headers seen from each faucet
+    rate_limits: Mutex<BTreeMap<String, FaucetRateLimit>>,
+}
+
+impl FaucetHealth {
+    pub fn new(window: usize) -> Self {
+        FaucetHealth {
+            window,
+            calls: Mutex::new(BTreeMap::new()),
+            rate_limits: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Remember the quota a faucet reported, replacing the previous one
+    pub fn record_rate_limit(&self, faucet: &str, rate_limit: &FaucetRateLimit) {
+        self.rate_limits
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(faucet.to_string(), rate_limit.clone());
+    }
+
+    /// Most recent quota of every faucet that reported one
+    pub fn rate_limits(&self) -> BTreeMap<String, FaucetRateLimit> {
+        self.rate_limits.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Record one faucet call
+    pub fn record(&self, faucet: &str, exhausted: bool) {
+        let mut calls = self.calls.lock().unwrap_or_else(|e| e.into_inner());
+        let recent = calls.entry(faucet.to_string()).or_default();
+        recent.push_back(exhausted);
+        while recent.len() > self.window {
+            recent.pop_front();
+        }
+    }
+
+    /// Status of every faucet seen so far
+    pub fn snapshot(&self) -> BTreeMap<String, FaucetStatus> {
+        let calls = self.calls.lock().unwrap_or_else(|e| e.into_inner());
+        calls
+            .iter()
+            .map(|(faucet, recent)| {
+                let exhausted_calls = recent.iter().filter(|&&exhausted| exhausted).count();
+                let status = FaucetStatus {
+                    status: if exhausted_calls > 0 { "degraded" } else { "ok" },
+                    recent_calls: recent.len(),
+                    exhausted_calls,
+                };
+                (faucet.clone(), status)
+            })
+            .collect()
+    }
+
+    /// Whether any faucet's recent calls indicate exhaustion
+    pub fn is_degraded(&self) -> bool {
+        let calls = self.calls.lock().unwrap_or_else(|e| e.into_inner());
+        calls.values().any(|recent| recent.contains(&true))
+    }
+}
+
+/// Small LRU of serialized results keyed by ID. Entries expire after `ttl` so
+/// writes from other processes sharing the database are picked up.
+pub struct ResultCache {
+    capacity: usize,
+    ttl: Duration,
+    entries: Mutex<CacheEntries>,
+}
+
+#[derive(Default)]
+struct CacheEntries {
+    values: HashMap<String, (Instant, String)>,
+    /// Least recently used first
+    order: VecDeque<String>,
+}
+
+impl ResultCache {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        ResultCache {
+            capacity,
+            ttl,
+            entries: Mutex::new(CacheEntries::default()),
+        }
+    }
+
+    /// Cached JSON for a result if present and fresh
+    pub fn get(&self, id: &str) -> Option<String> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let (stored_at, json) = entries.values.get(id)?.clone();
+        if stored_at.elapsed() > self.ttl {
+            entries.remove(id);
+            return None;
+        }
+        entries.touch(id);
+        Some(json)
+    }
+
+    /// Cache a result's JSON, evicting the least recently used entry when full
+    pub fn put(&self, id: &str, json: String) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.values.insert(id.to_string(), (Instant::now(), json));
+        entries.touch(id);
+        while entries.order.len() > self.capacity {
+            if let Some(oldest) = entries.order.pop_front() {
+                entries.values.remove(&oldest);
+            }
+        }
+    }
+
+    /// Drop a result after it's been written
+    pub fn invalidate(&self, id: &str) {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).remove(id);
+    }
+}
+
+impl CacheEntries {
+    fn touch(&mut self, id: &str) {
+        self.order.retain(|cached| cached != id);
+        self.order.push_back(id.to_string());
+    }
+
+    fn remove(&mut self, id: &str) {
+        self.values.remove(id);
+        self.order.retain(|cached| cached != id);
+    }
+}
+
+/// Rolling success rate as of the monitor's latest check
+#[derive(Debug, Clone, Serialize)]
+pub struct RollingSuccessRate {
+    pub window: u32,
+    pub runs: i64,
+    pub successful_runs: i64,
+    /// None until the window has any runs
+    pub success_rate: Option<f64>,
+    pub threshold: f64,
+    /// Consecutive checks the rate has been below the threshold
+    pub low_checks: u32,
+    /// An alert was sent and the rate hasn't recovered since
+    pub alerted: bool,
+    pub checked_at: String,
+}
+
+/// Debounces success-rate alerts: one alert per sustained drop, re-armed once the
+/// rate is back at or above the threshold
+#[derive(Default)]
+pub struct SuccessRateMonitor {
+    latest: Mutex<Option<RollingSuccessRate>>,
+}
+
+impl SuccessRateMonitor {
+    /// Record a check of the last `window` runs. True when this check should alert: a
+    /// full window has been below `threshold` for `sustain_checks` checks in a row and
+    /// no alert has been sent for this drop yet.
+    pub fn observe(&self, window: u32, runs: i64, successful_runs: i64, threshold: f64, sustain_checks: u32) -> bool {
+        let mut latest = self.latest.lock().unwrap_or_else(|e| e.into_inner());
+        let (low_checks, alerted) = latest.as_ref().map_or((0, false), |prev| (prev.low_checks, prev.alerted));
+        
+        let success_rate = (runs > 0).then(|| successful_runs as f64 / runs as f64);
+        let low = runs >= window as i64 && success_rate.is_some_and(|rate| rate < threshold);
+        let low_checks = if low { low_checks + 1 } else { 0 };
+        let fire = low_checks >= sustain_checks && !alerted;
+        
+        *latest = Some(RollingSuccessRate {
+            window,
+            runs,
+            successful_runs,
+            success_rate,
+            threshold,
+            low_checks,
+            alerted: low && (alerted || fire),
+            checked_at: chrono::Utc::now().to_rfc3339(),
+        });
+        fire
+    }
+    
+    pub fn snapshot(&self) -> Option<RollingSuccessRate> {
+        self.latest.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+/// Upper bounds of the run duration histogram buckets, in seconds
+const RUN_DURATION_BUCKETS_SECS: [f64; 9] = [1.0, 5.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1800.0];
+
+/// Labels of a finished run. Every value comes from a closed set (configured
+/// networks, run statuses, error kinds) so the number of series stays bounded.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RunLabels {
+    pub network: String,
+    pub status: String,
+    /// `none` for runs without an error kind
+    pub error_kind: String,
+}
+
+#[derive(Default)]
+struct RunSeries {
+    count: u64,
+    sum_secs: f64,
+    /// Non-cumulative count per entry of RUN_DURATION_BUCKETS_SECS
+    buckets: [u64; RUN_DURATION_BUCKETS_SECS.len()],
+}
+
+/// Run counter and duration histogram per label set, in Prometheus text format
+#[derive(Default)]
+pub struct RunMetrics {
+    series: Mutex<BTreeMap<RunLabels, RunSeries>>,
+}
+
+impl RunMetrics {
+    /// Count a finished run and observe its duration
+    pub fn record(&self, labels: RunLabels, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        let mut series = self.series.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = series.entry(labels).or_default();
+        entry.count += 1;
+        entry.sum_secs += secs;
+        if let Some(bucket) = RUN_DURATION_BUCKETS_SECS.iter().position(|&le| secs <= le) {
+            entry.buckets[bucket] += 1;
+        }
+    }
+
+    /// Exposition of every series seen so far
+    pub fn render(&self) -> String {
+        let series = self.series.lock().unwrap_or_else(|e| e.into_inner());
+        let mut out = String::new();
+        out.push_str("# HELP lava_test_runs_total Finished test runs\n");
+        out.push_str("# TYPE lava_test_runs_total counter\n");
+        for (labels, entry) in series.iter() {
+            out.push_str(&format!("lava_test_runs_total{{{}}} {}\n", labels.render(), entry.count));
+        }
+        out.push_str("# HELP lava_test_run_duration_seconds Wall-clock duration of finished test runs\n");
+        out.push_str("# TYPE lava_test_run_duration_seconds histogram\n");
+        for (labels, entry) in series.iter() {
+            let labels = labels.render();
+            let mut cumulative = 0;
+            for (le, count) in RUN_DURATION_BUCKETS_SECS.iter().zip(entry.buckets) {
+                cumulative += count;
+                out.push_str(&format!("lava_test_run_duration_seconds_bucket{{{},le=\"{}\"}} {}\n", labels, le, cumulative));
+            }
+            out.push_str(&format!("lava_test_run_duration_seconds_bucket{{{},le=\"+Inf\"}} {}\n", labels, entry.count));
+            out.push_str(&format!("lava_test_run_duration_seconds_sum{{{}}} {}\n", labels, entry.sum_secs));
+            out.push_str(&format!("lava_test_run_duration_seconds_count{{{}}} {}\n", labels, entry.count));
+        }
+        out
+    }
+}
+
+impl RunLabels {
+    fn render(&self) -> String {
+        let escape = |value: &str| value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+        format!(
+            "network=\"{}\",status=\"{}\",error_kind=\"{}\"",
+            escape(&self.network),
+            escape(&self.status),
+            escape(&self.error_kind)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seeded(seed: u64) -> AppState {
+        let mut config = Config::from_env();
+        config.rng_seed = Some(seed);
+        AppState::new(&config)
+    }
+
+    #[test]
+    fn same_seed_gives_identical_output() {
+        let first = seeded(42);
+        let second = seeded(42);
+
+        let draw = |state: &AppState| (state.random_txid(), state.random_uuid(), state.random_txid());
+        assert_eq!(draw(&first), draw(&second));
+
+        assert_ne!(seeded(42).random_txid(), seeded(43).random_txid());
+    }
+}
@@ -0,0 +1,93 @@
+use anyhow::{anyhow, Result};
+use base58::ToBase58;
+use bip39::Mnemonic;
+use bitcoincore_rpc::bitcoin::bip32::{DerivationPath, ExtendedPrivKey};
+use bitcoincore_rpc::bitcoin::secp256k1::Secp256k1;
+use bitcoincore_rpc::bitcoin::{Address, Network};
+use hmac::{Hmac, Mac};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use sha2::Sha512;
+use std::str::FromStr;
+use zeroize::Zeroizing;
+
+type HmacSha512 = Hmac<Sha512>;
+
+/// Addresses derived from a BIP39 mnemonic
+#[derive(Debug, Clone, PartialEq)]
+pub struct DerivedWallet {
+    /// P2WPKH testnet address of the first receive key, `<account path>/0/0`
+    pub btc_address: String,
+    /// Base58 ed25519 public key at the Solana path
+    pub lava_usd_pubkey: String,
+}
+
+/// Derive the BTC address and LavaUSD pubkey for a mnemonic (no passphrase)
+pub fn derive_wallet(phrase: &str, btc_account_path: &str, lava_usd_path: &str) -> Result<DerivedWallet> {
+    let mnemonic = Mnemonic::parse(phrase).map_err(|e| anyhow!("Invalid mnemonic: {}", e))?;
+    let seed = Zeroizing::new(mnemonic.to_seed(""));
+
+    Ok(DerivedWallet {
+        btc_address: derive_btc_address(seed.as_ref(), btc_account_path)?,
+        lava_usd_pubkey: derive_solana_pubkey(seed.as_ref(), lava_usd_path)?,
+    })
+}
+
+/// BIP32 derivation of the first receive address under a BIP84 account
+fn derive_btc_address(seed: &[u8], account_path: &str) -> Result<String> {
+    let secp = Secp256k1::new();
+    let path = DerivationPath::from_str(&format!("{}/0/0", account_path))?;
+
+    let master = ExtendedPrivKey::new_master(Network::Testnet, seed)?;
+    let key = master.derive_priv(&secp, &path)?.to_priv();
+    let address = Address::p2wpkh(&key.public_key(&secp), Network::Testnet)?;
+
+    Ok(address.to_string())
+}
+
+/// SLIP-0010 ed25519 derivation, which only supports hardened indexes
+fn derive_solana_pubkey(seed: &[u8], path: &str) -> Result<String> {
+    let mut node = slip10_hmac(b"ed25519 seed", &[seed]);
+
+    for segment in path.trim_start_matches("m/").split('/') {
+        let index: u32 = segment
+            .strip_suffix('\'')
+            .ok_or_else(|| anyhow!("ed25519 derivation needs hardened indexes, got {} in {}", segment, path))?
+            .parse()
+            .map_err(|_| anyhow!("Invalid index {} in {}", segment, path))?;
+        let hardened = (index | 0x8000_0000).to_be_bytes();
+        node = slip10_hmac(&node[32..], &[&[0u8], &node[..32], &hardened]);
+    }
+
+    let key_pair = Ed25519KeyPair::from_seed_unchecked(&node[..32])
+        .map_err(|e| anyhow!("Failed to build ed25519 key: {}", e))?;
+    Ok(key_pair.public_key().as_ref().to_base58())
+}
+
+fn slip10_hmac(key: &[u8], parts: &[&[u8]]) -> Zeroizing<Vec<u8>> {
+    let mut mac = HmacSha512::new_from_slice(key).expect("HMAC accepts keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+    Zeroizing::new(mac.finalize().into_bytes().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn derives_known_vectors() {
+        let wallet = derive_wallet(PHRASE, "m/84'/1'/0'", "m/44'/501'/0'/0'").unwrap();
+        assert_eq!(wallet.btc_address, "tb1q6rz28mcfaxtmd6v789l9rrlrusdprr9pqcpvkl");
+        assert_eq!(wallet.lava_usd_pubkey, "HAgk14JpMQLgt6rVgv7cBQFJWFto5Dqxi472uT3DKpqk");
+    }
+
+    #[test]
+    fn rejects_bad_phrases_and_soft_solana_paths() {
+        // Valid words, bad checksum
+        assert!(derive_wallet(&PHRASE.replace("about", "abandon"), "m/84'/1'/0'", "m/44'/501'/0'/0'").is_err());
+        assert!(derive_wallet(PHRASE, "m/84'/1'/0'", "m/44'/501'/0'/0").is_err());
+    }
+}